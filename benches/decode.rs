@@ -0,0 +1,63 @@
+use std::hint::black_box;
+
+use chip8::{
+    instruction::Instruction,
+    memory::{MEMORY_SIZE, Memory, ROM_START_ADDR},
+};
+use criterion::{Criterion, Throughput, criterion_group, criterion_main};
+
+const ROM: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/rom/tetris.ch8");
+
+fn rom_memory() -> Memory {
+    let mut memory = Memory::new();
+    memory.load_rom(&std::fs::read(ROM).unwrap());
+    memory
+}
+
+fn decode(c: &mut Criterion) {
+    let mut group = c.benchmark_group("decode");
+    group.throughput(Throughput::Elements(1 << 16));
+
+    group.bench_function("all_opcodes", |b| {
+        b.iter(|| {
+            for opcode in 0..=u16::MAX {
+                black_box(Instruction::from(black_box(opcode)));
+            }
+        })
+    });
+
+    group.finish();
+}
+
+/// Fetching every address of a ROM, decoding each time as the CPU used to versus reading
+/// the pre-decoded table.
+fn fetch(c: &mut Criterion) {
+    let memory = rom_memory();
+    let addrs = ROM_START_ADDR..(MEMORY_SIZE - 1) as u16;
+
+    let mut group = c.benchmark_group("fetch");
+    group.throughput(Throughput::Elements(addrs.len() as u64));
+
+    group.bench_function("decode_each", |b| {
+        b.iter(|| {
+            for addr in addrs.clone() {
+                let msb = memory.read(addr).unwrap_or(0);
+                let lsb = memory.read(addr + 1).unwrap_or(0);
+                black_box(Instruction::from(((msb as u16) << 8) | lsb as u16));
+            }
+        })
+    });
+
+    group.bench_function("predecoded", |b| {
+        b.iter(|| {
+            for addr in addrs.clone() {
+                black_box(memory.instruction(addr).ok());
+            }
+        })
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, decode, fetch);
+criterion_main!(benches);
@@ -0,0 +1,158 @@
+//! Bundled ROMs run headlessly for a fixed number of instructions, split into the parts
+//! a performance change is likely to touch: decode, execute, `Drw` and save states.
+
+use std::{
+    hint::black_box,
+    sync::{Arc, Mutex},
+};
+
+use bincode::config;
+use chip8::{
+    chip8::Chip8,
+    chip8_state::Chip8State,
+    frame_buffer::{FRAME_BUFFER_COLS, FRAME_BUFFER_ROWS, FrameBuffer},
+    instruction::Instruction,
+    key_matrix::{Chip8Key, KeyMatrix},
+    key_wait::{KeyEvent, KeyWait},
+};
+use criterion::{BatchSize, BenchmarkId, Criterion, Throughput, criterion_group, criterion_main};
+
+const ROMS: [&str; 3] = ["tetris", "space_invaders", "corax_test"];
+
+/// Instructions per run.
+const INSTRUCTIONS: u64 = 100_000;
+/// Instructions between 60 Hz ticks, so timer loops make progress.
+const INSTRUCTIONS_PER_FRAME: u64 = 1_000;
+
+fn rom_path(name: &str) -> String {
+    format!("{}/rom/{name}.ch8", env!("CARGO_MANIFEST_DIR"))
+}
+
+fn load(name: &str) -> Chip8 {
+    let mut chip8 = Chip8::new(Arc::new(Mutex::new(KeyMatrix::new())));
+    chip8.load_rom(&rom_path(name)).unwrap();
+    chip8
+}
+
+/// Runs `INSTRUCTIONS` instructions, tapping a key whenever the program waits for one so
+/// title screens don't stall the run. `on_instruction` sees each instruction first.
+fn run(chip8: &mut Chip8, mut on_instruction: impl FnMut(&Chip8, Instruction)) {
+    for n in 1..=INSTRUCTIONS {
+        if let Ok(instruction) = chip8.next_instruction() {
+            on_instruction(chip8, instruction);
+        }
+        let _ = chip8.tick();
+
+        if n.is_multiple_of(INSTRUCTIONS_PER_FRAME) {
+            if chip8.cpu().key_wait() != KeyWait::Idle {
+                chip8.key_event(KeyEvent::Press(Chip8Key::K5));
+                chip8.key_event(KeyEvent::Release(Chip8Key::K5));
+            }
+            chip8.tick_60hz();
+        }
+    }
+}
+
+fn decode(c: &mut Criterion) {
+    let mut group = c.benchmark_group("decode");
+
+    for name in ROMS {
+        let rom = std::fs::read(rom_path(name)).unwrap();
+        let opcodes: Vec<u16> = rom
+            .chunks_exact(2)
+            .map(|pair| u16::from_be_bytes([pair[0], pair[1]]))
+            .collect();
+
+        group.throughput(Throughput::Elements(opcodes.len() as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(name), &opcodes, |b, opcodes| {
+            b.iter(|| {
+                for &opcode in opcodes {
+                    black_box(Instruction::from(black_box(opcode)));
+                }
+            })
+        });
+    }
+
+    group.finish();
+}
+
+fn execute(c: &mut Criterion) {
+    let mut group = c.benchmark_group("execute");
+    group.throughput(Throughput::Elements(INSTRUCTIONS));
+
+    for name in ROMS {
+        group.bench_function(BenchmarkId::from_parameter(name), |b| {
+            b.iter_batched_ref(
+                || load(name),
+                |chip8| run(chip8, |_, _| {}),
+                BatchSize::LargeInput,
+            )
+        });
+    }
+
+    group.finish();
+}
+
+/// Every sprite a run draws, as (x, y, rows), replayed straight into a frame buffer.
+fn drw(c: &mut Criterion) {
+    let mut group = c.benchmark_group("drw");
+
+    for name in ROMS {
+        let mut sprites = Vec::new();
+        run(&mut load(name), |chip8, instruction| {
+            if let Instruction::Drw { vx, vy, nibble } = instruction {
+                let cpu = chip8.cpu();
+                let start = cpu.i() as usize;
+                let rows = chip8
+                    .memory()
+                    .get_ref()
+                    .get(start..start + nibble as usize)
+                    .map(<[u8]>::to_vec)
+                    .unwrap_or_default();
+                let x = cpu.v()[vx as usize] as usize % FRAME_BUFFER_COLS;
+                let y = cpu.v()[vy as usize] as usize % FRAME_BUFFER_ROWS;
+                sprites.push((x, y, rows));
+            }
+        });
+
+        group.throughput(Throughput::Elements(sprites.len() as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(name), &sprites, |b, sprites| {
+            let mut frame_buffer = FrameBuffer::new();
+            b.iter(|| {
+                for (x, y, rows) in sprites {
+                    black_box(frame_buffer.draw_sprite(*x, *y, rows));
+                }
+            })
+        });
+    }
+
+    group.finish();
+}
+
+/// Snapshotting and encoding a state as `Save` and rewind do, and decoding it back.
+fn save_state(c: &mut Criterion) {
+    let mut group = c.benchmark_group("save_state");
+
+    for name in ROMS {
+        let mut chip8 = load(name);
+        run(&mut chip8, |_, _| {});
+        let bytes = bincode::encode_to_vec(chip8.to_chip8_state(), config::standard()).unwrap();
+
+        group.throughput(Throughput::Bytes(bytes.len() as u64));
+        group.bench_function(BenchmarkId::new("encode", name), |b| {
+            b.iter(|| bincode::encode_to_vec(chip8.to_chip8_state(), config::standard()).unwrap())
+        });
+        group.bench_function(BenchmarkId::new("decode", name), |b| {
+            b.iter(|| {
+                let (state, _): (Chip8State, usize) =
+                    bincode::decode_from_slice(&bytes, config::standard()).unwrap();
+                state
+            })
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, decode, execute, drw, save_state);
+criterion_main!(benches);
@@ -0,0 +1,103 @@
+use std::{
+    collections::VecDeque,
+    hint::black_box,
+    sync::{Arc, Mutex},
+};
+
+use chip8::{
+    cpu::Cpu,
+    frame_buffer::{FRAME_BUFFER_COLS, FRAME_BUFFER_ROWS, FrameBuffer},
+    key_matrix::KeyMatrix,
+    key_wait::KeyWaitMode,
+    memory::Memory,
+};
+use criterion::{Criterion, Throughput, criterion_group, criterion_main};
+
+const SPRITE: [u8; 15] = [
+    0x3C, 0x42, 0x81, 0xA5, 0x81, 0x99, 0x42, 0x3C, 0xFF, 0x00, 0xAA, 0x55, 0xF0, 0x0F, 0x18,
+];
+
+/// Sprite positions that cover aligned, unaligned and clipped draws.
+fn positions() -> impl Iterator<Item = (usize, usize)> {
+    (0..64).map(|n| ((n * 7) % FRAME_BUFFER_COLS, (n * 5) % FRAME_BUFFER_ROWS))
+}
+
+/// The pixel-at-a-time blit `Drw` used before rows were packed, kept as a baseline.
+fn draw_per_pixel(frame_buffer: &mut FrameBuffer, x: usize, y: usize, sprite: &[u8]) -> bool {
+    let mut collision = false;
+
+    for (cy, &byte) in (y..FRAME_BUFFER_ROWS).zip(sprite) {
+        for cx in x..(x + 8).min(FRAME_BUFFER_COLS) {
+            collision |= frame_buffer.xor(cx, cy, (byte >> (7 - (cx - x))) & 1 == 1);
+        }
+    }
+
+    collision
+}
+
+fn draw_sprite(c: &mut Criterion) {
+    let mut group = c.benchmark_group("draw_sprite");
+    group.throughput(Throughput::Elements(positions().count() as u64));
+
+    group.bench_function("per_pixel", |b| {
+        let mut frame_buffer = FrameBuffer::new();
+        b.iter(|| {
+            for (x, y) in positions() {
+                black_box(draw_per_pixel(&mut frame_buffer, x, y, &SPRITE));
+            }
+        })
+    });
+
+    group.bench_function("row_blit", |b| {
+        let mut frame_buffer = FrameBuffer::new();
+        b.iter(|| {
+            for (x, y) in positions() {
+                black_box(frame_buffer.draw_sprite(x, y, &SPRITE));
+            }
+        })
+    });
+
+    group.finish();
+}
+
+/// A loop that draws a 15-row sprite every fourth instruction, as sprite-heavy ROMs do
+/// when run at a high IPS.
+fn drw_loop(c: &mut Criterion) {
+    const INSTRUCTIONS: u64 = 10_000;
+
+    let mut memory = Memory::new();
+    memory.load_rom(&[
+        0xA0, 0x50, // LD I, 0x050 (the font)
+        0xD0, 0x1F, // DRW V0, V1, 15
+        0x70, 0x03, // ADD V0, 3
+        0x71, 0x05, // ADD V1, 5
+        0x12, 0x02, // JP 0x202
+    ]);
+
+    let mut group = c.benchmark_group("drw_loop");
+    group.throughput(Throughput::Elements(INSTRUCTIONS));
+
+    group.bench_function("cpu_tick", |b| {
+        let key_matrix = Arc::new(Mutex::new(KeyMatrix::new()));
+        let mut key_events = VecDeque::new();
+        b.iter(|| {
+            let mut cpu = Cpu::new();
+            let mut frame_buffer = FrameBuffer::new();
+            for _ in 0..INSTRUCTIONS {
+                let _ = cpu.tick(
+                    &mut memory,
+                    &mut frame_buffer,
+                    key_matrix.clone(),
+                    &mut key_events,
+                    KeyWaitMode::Release,
+                );
+            }
+            black_box(frame_buffer)
+        })
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, draw_sprite, drw_loop);
+criterion_main!(benches);
@@ -0,0 +1,66 @@
+//! Scripted GDB remote protocol client for exercising the emulator's GDB server.
+//!
+//! Load a ROM, start the server from Debug → GDB Server, then run
+//! `cargo run --example gdb_client -- 1234`.
+
+use std::{
+    env,
+    io::{self, Read, Write},
+    net::TcpStream,
+};
+
+fn main() -> io::Result<()> {
+    let port = env::args()
+        .nth(1)
+        .and_then(|port| port.parse().ok())
+        .unwrap_or(1234u16);
+
+    let mut stream = TcpStream::connect(("127.0.0.1", port))?;
+
+    let script = [
+        "qSupported",
+        "?",
+        "g",
+        "p11",
+        "m200,10",
+        "Z0,202,2",
+        "c",
+        "p11",
+        "z0,202,2",
+        "s",
+        "p11",
+        "D",
+    ];
+
+    for packet in script {
+        let reply = exchange(&mut stream, packet)?;
+        println!("-> {packet}\n<- {reply}");
+    }
+
+    Ok(())
+}
+
+fn exchange(stream: &mut TcpStream, packet: &str) -> io::Result<String> {
+    let checksum = packet.bytes().fold(0u8, |sum, byte| sum.wrapping_add(byte));
+    stream.write_all(format!("${packet}#{checksum:02x}").as_bytes())?;
+
+    // Skip the ack, then read up to the end of the reply packet
+    let mut reply = Vec::new();
+    let mut byte = [0u8];
+    loop {
+        stream.read_exact(&mut byte)?;
+        match byte[0] {
+            b'+' if reply.is_empty() => {}
+            b'#' => {
+                let mut checksum = [0u8; 2];
+                stream.read_exact(&mut checksum)?;
+                stream.write_all(b"+")?;
+                break;
+            }
+            b'$' => reply.clear(),
+            other => reply.push(other),
+        }
+    }
+
+    Ok(String::from_utf8_lossy(&reply).into_owned())
+}
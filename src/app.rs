@@ -1,4 +1,9 @@
-use std::sync::{Arc, Mutex};
+use std::{
+    fs, mem,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
 
 use eframe::{
     Frame,
@@ -6,30 +11,96 @@ use eframe::{
 };
 
 use crate::{
+    Message,
+    chip8::DebugSnapshot,
+    crt::{self, CRT_SCALE, CrtConfig, CrtPreset},
+    debugger_view::DebuggerView,
+    disassembly_view::DisassemblyView,
     file_picker::{Config, FilePicker, FilePickerResult},
+    flicker::{FlickerMode, Phosphor, blend},
     frame_buffer::{FRAME_BUFFER_COLS, FRAME_BUFFER_ROWS, FrameBuffer},
+    gdb_view::GdbView,
     handle::{Chip8Handle, Chip8Source},
-    key_mapping::KeyMapping,
-    key_matrix::{Chip8Key, KeyMatrix},
+    key_mapping::{KeyMapping, KeyPreset},
+    key_matrix::{Chip8Key, KEY_LAYOUT, KeyMatrix},
+    key_wait::KeyWaitMode,
+    keypad::{Keypad, KeypadEvent},
+    memory_viewer::MemoryViewer,
+    palette::{PALETTE_SIZES, PalettePreset},
+    profiler_view::{ProfileExport, ProfilerView},
     remap::RemapState,
+    scheduler::{DEFAULT_IPS, FramePolicy},
+    screenshot,
+    settings::Settings,
+    shortcuts::{Action, Shortcut},
+    shortcuts_view::ShortcutsView,
+    symbols::Symbols,
+    timing::TimingModel,
+    trace_view::TraceView,
 };
 
 pub struct App {
+    /// Handed to the emulator thread so it can ask for a repaint when there's news.
+    ctx: Context,
     texture: TextureHandle,
 
-    frame_buffer: Arc<Mutex<FrameBuffer>>,
+    /// The last frame published by the emulator thread.
+    frame_buffer: FrameBuffer,
     key_matrix: Arc<Mutex<KeyMatrix>>,
 
     handle: Option<Chip8Handle>,
 
     file_picker: FilePicker,
+    /// Whether the open file picker paused emulation, so closing it should resume.
+    paused_for_picker: bool,
     key_mapping: KeyMapping,
 
     remap_state: RemapState,
+    settings: Settings,
+    keypad: Keypad,
+    /// File name of the loaded ROM, used to look up its key profile.
+    rom_name: Option<String>,
+
     open_color_config: bool,
-    color_config: [[u8; 3]; 2],
+    palette_text: String,
+    palette_error: bool,
+
+    open_flicker_config: bool,
+    flicker_mode: FlickerMode,
+    persistence: f32,
+    phosphor: Phosphor,
+
+    open_crt_config: bool,
+    crt_config: CrtConfig,
+
+    open_timing_config: bool,
+    timing_model: TimingModel,
+    key_wait_mode: KeyWaitMode,
+    ips: u32,
+    frame_policy: FramePolicy,
+
+    snapshot: Option<Box<DebugSnapshot>>,
+    last_snapshot_request: Instant,
+    memory_viewer: MemoryViewer,
+    disassembly_view: DisassemblyView,
+    debugger_view: DebuggerView,
+    trace_view: TraceView,
+    gdb_view: GdbView,
+    profiler_view: ProfilerView,
+    last_profile_request: Instant,
+    shortcuts_view: ShortcutsView,
+
+    /// Short-lived message in the menu bar, e.g. where a screenshot went.
+    status: Option<(String, Instant)>,
 }
 
+/// How long a status message stays in the menu bar.
+const STATUS_DURATION: Duration = Duration::from_secs(3);
+/// How often the open memory, disassembly and debugger views ask for a fresh snapshot.
+const SNAPSHOT_INTERVAL: Duration = Duration::from_millis(100);
+/// How often the open profiler window asks for a fresh profile.
+const PROFILE_INTERVAL: Duration = Duration::from_millis(250);
+
 impl App {
     pub fn new(cc: &eframe::CreationContext<'_>) -> Self {
         let texture = cc.egui_ctx.load_texture(
@@ -38,61 +109,329 @@ impl App {
             TextureOptions::NEAREST,
         );
 
-        let frame_buffer = Arc::new(Mutex::new(FrameBuffer::new()));
+        let frame_buffer = FrameBuffer::new();
         let key_matrix = Arc::new(Mutex::new(KeyMatrix::new()));
 
+        let settings = Settings::load();
+        let key_mapping = settings
+            .profile(&settings.active_profile)
+            .map(KeyMapping::from_profile)
+            .unwrap_or_default();
+
         Self {
+            ctx: cc.egui_ctx.clone(),
             texture,
             frame_buffer,
             key_matrix,
             handle: None,
             file_picker: FilePicker::new(),
-            key_mapping: KeyMapping::new(),
+            paused_for_picker: false,
+            key_mapping,
             remap_state: RemapState::new(),
+            settings,
+            keypad: Keypad::new(),
+            rom_name: None,
             open_color_config: false,
-            color_config: [[0u8, 0u8, 0u8], [255u8, 255u8, 255u8]],
+            palette_text: String::new(),
+            palette_error: false,
+            open_flicker_config: false,
+            flicker_mode: FlickerMode::Off,
+            persistence: 0.6,
+            phosphor: Phosphor::new(),
+            open_crt_config: false,
+            crt_config: CrtConfig::default(),
+            open_timing_config: false,
+            timing_model: TimingModel::FixedIps,
+            key_wait_mode: KeyWaitMode::Release,
+            ips: DEFAULT_IPS,
+            frame_policy: FramePolicy::Skip,
+            snapshot: None,
+            last_snapshot_request: Instant::now(),
+            memory_viewer: MemoryViewer::new(),
+            disassembly_view: DisassemblyView::new(),
+            debugger_view: DebuggerView::new(),
+            trace_view: TraceView::new(),
+            gdb_view: GdbView::new(),
+            profiler_view: ProfilerView::new(),
+            last_profile_request: Instant::now(),
+            shortcuts_view: ShortcutsView::new(),
+            status: None,
         }
     }
 
-    fn set_new_handle(&mut self, source: Chip8Source) {
-        let _ = self.handle.take();
-
-        let frame_buffer = Arc::new(Mutex::new(FrameBuffer::new()));
+    /// Starts emulating `source`. If it can't be loaded, the current emulator keeps
+    /// running and the error goes to the status bar.
+    fn set_new_handle(&mut self, source: Chip8Source) -> bool {
+        let path = match source {
+            Chip8Source::ROM(ref path) | Chip8Source::SaveState(ref path) => path.clone(),
+        };
         let key_matrix = Arc::new(Mutex::new(KeyMatrix::new()));
 
-        self.handle = Some(Chip8Handle::new(
-            key_matrix.clone(),
-            frame_buffer.clone(),
-            source,
-        ));
+        let handle = match Chip8Handle::new(key_matrix.clone(), source) {
+            Ok(handle) => handle,
+            Err(err) => {
+                self.set_status(format!("Couldn't load {path}: {err}"));
+                return false;
+            }
+        };
+        // The UI only repaints on input or when the emulator thread has something new
+        let ctx = self.ctx.clone();
+        handle.set_waker(move || ctx.request_repaint());
+        // Stops the old emulator thread
+        self.handle = Some(handle);
 
-        self.frame_buffer = frame_buffer;
+        self.frame_buffer = FrameBuffer::new();
         self.key_matrix = key_matrix;
+        self.phosphor = Phosphor::new();
+        self.snapshot = None;
+        self.debugger_view.last_break = None;
+        self.trace_view.tracing = false;
+        self.gdb_view.listening = false;
+        self.gdb_view.status = "Stopped".to_string();
+        self.profiler_view.reset();
+
+        self.set_flicker_mode(self.flicker_mode);
+        self.send(Message::SetTimingModel(self.timing_model));
+        self.send(Message::SetKeyWaitMode(self.key_wait_mode));
+        self.send(Message::SetIps(self.ips));
+        self.send(Message::SetFramePolicy(self.frame_policy));
+        true
     }
 
-    fn set_texture(&mut self) {
-        let image = {
-            let frame_buffer = self
-                .frame_buffer
-                .lock()
-                .unwrap()
-                .get_ref()
-                .map(|v| {
-                    if v {
-                        self.color_config[1]
+    fn select_profile(&mut self, name: &str) {
+        if let Some(profile) = self.settings.profile(name) {
+            self.key_mapping = KeyMapping::from_profile(profile);
+            self.settings.active_profile = name.to_string();
+            self.settings.save();
+        }
+    }
+
+    /// Writes the current bindings back to the active profile.
+    fn save_key_mapping(&mut self) {
+        let profile = self.key_mapping.to_profile(&self.settings.active_profile);
+        self.settings.store_profile(profile);
+        self.settings.save();
+    }
+
+    /// Profile picker at the top of the remap window.
+    fn profile_ui(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            let mut selected = self.settings.active_profile.clone();
+            egui::ComboBox::from_label("Profile")
+                .selected_text(selected.as_str())
+                .show_ui(ui, |ui| {
+                    for profile in &self.settings.key_profiles {
+                        ui.selectable_value(&mut selected, profile.name.clone(), &profile.name);
+                    }
+                });
+            if selected != self.settings.active_profile {
+                self.select_profile(&selected);
+            }
+
+            if let Some(rom) = self.rom_name.clone() {
+                let mut use_for_rom =
+                    self.settings.rom_profiles.get(&rom) == Some(&self.settings.active_profile);
+                if ui.checkbox(&mut use_for_rom, "Use for this ROM").changed() {
+                    if use_for_rom {
+                        let profile = self.settings.active_profile.clone();
+                        self.settings.rom_profiles.insert(rom, profile);
                     } else {
-                        self.color_config[0]
+                        self.settings.rom_profiles.remove(&rom);
                     }
-                })
-                .concat();
-            ColorImage::from_rgb([FRAME_BUFFER_COLS, FRAME_BUFFER_ROWS], &frame_buffer)
+                    self.settings.save();
+                }
+            }
+
+            if self.settings.key_profiles.len() > 1 && ui.button("Delete").clicked() {
+                let name = self.settings.active_profile.clone();
+                self.settings.remove_profile(&name);
+                let first = self.settings.key_profiles[0].name.clone();
+                self.select_profile(&first);
+            }
+        });
+
+        ui.horizontal(|ui| {
+            ui.add(
+                egui::TextEdit::singleline(&mut self.remap_state.new_profile_name)
+                    .hint_text("New profile name")
+                    .desired_width(160.0),
+            );
+
+            let name = self.remap_state.new_profile_name.trim().to_string();
+            let valid = !name.is_empty() && self.settings.profile(&name).is_none();
+            if ui
+                .add_enabled(valid, egui::Button::new("Save as"))
+                .on_hover_text("Copy the current bindings into a new profile")
+                .clicked()
+            {
+                self.settings.active_profile = name;
+                self.save_key_mapping();
+                self.remap_state.new_profile_name.clear();
+            }
+        });
+    }
+
+    fn set_status(&mut self, status: String) {
+        self.status = Some((status, Instant::now()));
+    }
+
+    fn run_action(&mut self, ctx: &Context, action: Action, pressed: bool) {
+        let Some(ref handle) = self.handle else {
+            if pressed {
+                match action {
+                    Action::OpenRom => self.file_picker.open_file_picker(Config::ROM),
+                    Action::Fullscreen => toggle_fullscreen(ctx),
+                    _ => {}
+                }
+            }
+            return;
+        };
+
+        match action {
+            Action::FastForward => handle.send(Message::SetFastForward(pressed)),
+            Action::Rewind => handle.send(Message::SetRewinding(pressed)),
+            _ if !pressed => {}
+            Action::PauseResume => handle.send(Message::TogglePause),
+            Action::Reset => handle.send(Message::Reset),
+            Action::HardReset => handle.send(Message::HardReset),
+            Action::Step => handle.step(),
+            Action::QuickSave => self.quick_save(),
+            Action::QuickLoad => self.quick_load(),
+            Action::Screenshot => self.screenshot(),
+            Action::Fullscreen => toggle_fullscreen(ctx),
+            Action::OpenRom => self.open_file_picker_paused(Config::ROM),
+        }
+    }
+
+    /// One quick save slot per ROM, kept in the config directory.
+    fn quick_save_path(&self) -> Option<PathBuf> {
+        let name = self
+            .rom_name
+            .as_deref()
+            .and_then(|name| Path::new(name).file_stem())
+            .and_then(|stem| stem.to_str())
+            .unwrap_or("untitled");
+
+        Some(
+            Settings::dir()?
+                .join("quicksaves")
+                .join(format!("{name}.sav")),
+        )
+    }
+
+    fn quick_save(&mut self) {
+        let Some(path) = self.quick_save_path() else {
+            return;
+        };
+
+        if let Some(dir) = path.parent()
+            && let Err(err) = fs::create_dir_all(dir)
+        {
+            self.set_status(format!("Quick save failed: {err}"));
+            return;
+        }
+
+        self.save(path.display().to_string());
+        self.set_status("Quick saved".to_string());
+    }
+
+    fn quick_load(&mut self) {
+        match self.quick_save_path() {
+            Some(path) if path.is_file() => {
+                if self.set_new_handle(Chip8Source::SaveState(path.display().to_string())) {
+                    self.set_status("Quick loaded".to_string());
+                }
+            }
+            _ => self.set_status("No quick save for this ROM".to_string()),
+        }
+    }
+
+    fn screenshot(&mut self) {
+        let image = self.frame_image();
+        let rgb = image
+            .pixels
+            .iter()
+            .flat_map(|color| [color.r(), color.g(), color.b()])
+            .collect::<Vec<u8>>();
+
+        let result = screenshot::next_path(self.rom_name.as_deref()).and_then(|path| {
+            screenshot::write_png(&path, image.width(), image.height(), &rgb)?;
+            Ok(path)
+        });
+
+        match result {
+            Ok(path) => self.set_status(format!("Saved {}", path.display())),
+            Err(err) => self.set_status(format!("Screenshot failed: {err}")),
+        }
+    }
+
+    fn set_texture(&mut self) {
+        let persistence = if self.flicker_mode == FlickerMode::Ghosting {
+            self.persistence
+        } else {
+            0.0
+        };
+
+        self.phosphor.update(&self.frame_buffer, persistence);
+
+        self.render_texture();
+    }
+
+    fn render_texture(&mut self) {
+        let options = if self.crt_config.is_off() {
+            TextureOptions::NEAREST
+        } else {
+            TextureOptions::LINEAR
         };
 
-        self.texture.set(image, TextureOptions::NEAREST);
+        let image = self.frame_image();
+        self.texture.set(image, options);
+    }
+
+    /// The display as drawn, with palette, ghosting and effects applied.
+    fn frame_image(&self) -> ColorImage {
+        let (off, on) = (self.settings.palette.off(), self.settings.palette.on());
+
+        let pixels = self
+            .phosphor
+            .levels()
+            .iter()
+            .map(|&level| blend(off, on, level))
+            .collect::<Vec<[u8; 3]>>();
+
+        if self.crt_config.is_off() {
+            ColorImage::from_rgb([FRAME_BUFFER_COLS, FRAME_BUFFER_ROWS], &pixels.concat())
+        } else {
+            let pixels = crt::apply(
+                &self.crt_config,
+                &pixels,
+                FRAME_BUFFER_COLS,
+                FRAME_BUFFER_ROWS,
+            );
+            ColorImage::from_rgb(
+                [FRAME_BUFFER_COLS * CRT_SCALE, FRAME_BUFFER_ROWS * CRT_SCALE],
+                &pixels,
+            )
+        }
+    }
+
+    fn set_flicker_mode(&mut self, mode: FlickerMode) {
+        self.flicker_mode = mode;
+        if let Some(ref handle) = self.handle {
+            handle.send_flicker_mode_message(mode);
+        }
     }
 
     fn press_key(&self, key: Chip8Key) {
-        self.key_matrix.lock().unwrap().press(key);
+        let mut key_matrix = self.key_matrix.lock().unwrap();
+
+        // Key repeat shouldn't look like fresh presses to FX0A
+        if !key_matrix.is_pressed(key) {
+            key_matrix.press(key);
+            if let Some(ref handle) = self.handle {
+                handle.send_key_press_message(key);
+            }
+        }
     }
 
     fn release_key(&self, key: Chip8Key) {
@@ -114,6 +453,19 @@ impl App {
         }
     }
 
+    /// Pauses while the picker is open; it resumes once the picker is closed.
+    fn open_file_picker_paused(&mut self, config: Config) {
+        self.pause();
+        self.paused_for_picker = true;
+        self.file_picker.open_file_picker(config);
+    }
+
+    fn send(&self, message: Message) {
+        if let Some(ref handle) = self.handle {
+            handle.send(message);
+        }
+    }
+
     fn save(&self, path: String) {
         if let Some(ref handle) = self.handle {
             handle.save(path);
@@ -123,12 +475,34 @@ impl App {
 
 impl eframe::App for App {
     fn update(&mut self, ctx: &Context, _frame: &mut Frame) {
+        // Shortcuts are off while a key is being picked or text is being typed
+        let shortcuts_enabled = !self.remap_state.open_selection
+            && !self.shortcuts_view.is_capturing()
+            && !ctx.wants_keyboard_input();
+        let mut actions = Vec::new();
+
         ctx.input(|i| {
             for event in &i.raw.events {
                 match event {
                     egui::Event::Key {
-                        key, pressed: true, ..
+                        key,
+                        pressed: true,
+                        repeat,
+                        modifiers,
+                        ..
                     } => {
+                        if shortcuts_enabled
+                            && let Some(action) = self
+                                .settings
+                                .shortcuts
+                                .action(Shortcut::new(*key, *modifiers), &self.key_mapping)
+                        {
+                            if !repeat {
+                                actions.push((action, true));
+                            }
+                            continue;
+                        }
+
                         let key = self.key_mapping.get_chip8_key(key);
 
                         if let Some(key) = key {
@@ -140,9 +514,16 @@ impl eframe::App for App {
                         pressed: false,
                         ..
                     } => {
+                        if let Some(action) = self.settings.shortcuts.held_action(*key) {
+                            actions.push((action, false));
+                        }
+
                         let key = self.key_mapping.get_chip8_key(key);
 
-                        if let Some(key) = key {
+                        // The key may have gone to a shortcut with modifiers instead
+                        if let Some(key) = key
+                            && self.key_matrix.lock().unwrap().is_pressed(key)
+                        {
                             self.release_key(key);
                         }
                     }
@@ -151,21 +532,35 @@ impl eframe::App for App {
             }
         });
 
+        for (action, pressed) in actions {
+            self.run_action(ctx, action, pressed);
+        }
+
         egui::TopBottomPanel::top("panel").show(ctx, |ui| {
             MenuBar::new().ui(ui, |ui| {
                 ui.menu_button("File", |ui| {
                     if ui.button("Load ROM").clicked() {
-                        self.pause();
-                        self.file_picker.open_file_picker(Config::ROM);
+                        self.open_file_picker_paused(Config::ROM);
                     }
                     if ui.button("Save State").clicked() {
-                        self.pause();
-                        self.file_picker.open_file_picker(Config::Save);
+                        self.open_file_picker_paused(Config::Save);
                     }
                     if ui.button("Load State").clicked() {
-                        self.pause();
-                        self.file_picker.open_file_picker(Config::Load);
+                        self.open_file_picker_paused(Config::Load);
+                    }
+                    if ui.button("Load Symbols").clicked() {
+                        self.file_picker.open_file_picker(Config::Symbols);
                     }
+                    ui.separator();
+                    ui.add_enabled_ui(self.handle.is_some(), |ui| {
+                        if ui.button("Reset").clicked() {
+                            self.send(Message::Reset);
+                        }
+                        if ui.button("Hard Reset").clicked() {
+                            self.send(Message::HardReset);
+                        }
+                    });
+                    ui.separator();
                     if ui.button("Quit").clicked() {
                         ui.ctx().send_viewport_cmd(egui::ViewportCommand::Close);
                     }
@@ -178,19 +573,101 @@ impl eframe::App for App {
                     }
 
                     if ui.button("Reset keymapping").clicked() {
-                        self.key_mapping.reset_keymap();
+                        // Built-in profiles go back to how they shipped, custom ones to default
+                        let preset = KeyPreset::from_name(&self.settings.active_profile)
+                            .unwrap_or(KeyPreset::Default);
+                        self.key_mapping = KeyMapping::from_preset(preset);
+                        self.save_key_mapping();
                     }
                 });
 
+                ui.menu_button("View", |ui| {
+                    ui.checkbox(&mut self.keypad.open, "On-screen Keypad");
+                });
+
                 ui.menu_button("Config", |ui| {
                     if ui.button("Color Config").clicked() {
                         self.pause();
                         self.open_color_config = true;
                     }
-                })
+                    if ui.button("Flicker Config").clicked() {
+                        self.open_flicker_config = true;
+                    }
+                    if ui.button("Effects Config").clicked() {
+                        self.open_crt_config = true;
+                    }
+                    if ui.button("Timing Config").clicked() {
+                        self.open_timing_config = true;
+                    }
+                    ui.menu_button("Key Wait (FX0A)", |ui| {
+                        let mut mode = self.key_wait_mode;
+                        for option in KeyWaitMode::ALL {
+                            ui.radio_value(&mut mode, option, <&'static str>::from(option));
+                        }
+                        if mode != self.key_wait_mode {
+                            self.key_wait_mode = mode;
+                            self.send(Message::SetKeyWaitMode(mode));
+                        }
+                    });
+                    if ui.button("Shortcuts").clicked() {
+                        self.shortcuts_view.open = true;
+                    }
+                });
+
+                ui.menu_button("Debug", |ui| {
+                    if ui.button("Memory Viewer").clicked() {
+                        self.memory_viewer.open = true;
+                    }
+                    if ui.button("Disassembly").clicked() {
+                        self.disassembly_view.open = true;
+                    }
+                    if ui.button("Breakpoints & Watchpoints").clicked() {
+                        self.debugger_view.open = true;
+                    }
+                    if ui.button("Trace Log").clicked() {
+                        self.trace_view.open = true;
+                    }
+                    if ui.button("Profiler").clicked() {
+                        self.profiler_view.open = true;
+                    }
+                    if ui.button("GDB Server").clicked() {
+                        self.gdb_view.open = true;
+                    }
+                });
+
+                if let Some((ref status, since)) = self.status {
+                    if since.elapsed() < STATUS_DURATION {
+                        ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                            ui.weak(status);
+                        });
+                        ctx.request_repaint_after(STATUS_DURATION.saturating_sub(since.elapsed()));
+                    } else {
+                        self.status = None;
+                    }
+                }
             });
         });
 
+        if self.keypad.open {
+            let events = egui::SidePanel::right("keypad")
+                .resizable(false)
+                .show(ctx, |ui| {
+                    ui.add_space(10.0);
+                    let key_matrix = self.key_matrix.lock().unwrap().clone();
+                    self.keypad.ui(ui, &key_matrix, &self.key_mapping)
+                })
+                .inner;
+
+            for event in events {
+                match event {
+                    KeypadEvent::Press(key) => self.press_key(key),
+                    KeypadEvent::Release(key) => self.release_key(key),
+                }
+            }
+        } else if let Some(key) = self.keypad.release() {
+            self.release_key(key);
+        }
+
         egui::CentralPanel::default()
             .frame(egui::Frame::default().fill(ctx.style().visuals.window_fill))
             .show(ctx, |ui| {
@@ -209,47 +686,99 @@ impl eframe::App for App {
                 egui::ViewportId::from_hash_of("color config"),
                 egui::ViewportBuilder::default()
                     .with_title("Color Config")
-                    .with_inner_size([220.0, 70.0])
+                    .with_inner_size([420.0, 260.0])
                     .with_resizable(false)
                     .with_always_on_top(),
                 |ctx, _| {
                     egui::CentralPanel::default().show(ctx, |ui| {
-                        ui.vertical_centered(|ui| {
-                            ui.horizontal(|ui| {
-                                ui.label("OFF Color");
-                                ui.add(
-                                    egui::DragValue::new(&mut self.color_config[0][0])
-                                        .range(0..=255),
-                                );
-                                ui.add(
-                                    egui::DragValue::new(&mut self.color_config[0][1])
-                                        .range(0..=255),
-                                );
-                                ui.add(
-                                    egui::DragValue::new(&mut self.color_config[0][2])
-                                        .range(0..=255),
-                                );
-                            });
+                        let old_palette = self.settings.palette.clone();
+                        let palette = &mut self.settings.palette;
+
+                        ui.horizontal(|ui| {
+                            egui::ComboBox::from_label("Preset")
+                                .selected_text(
+                                    PalettePreset::matching(palette)
+                                        .map(<&'static str>::from)
+                                        .unwrap_or("Custom"),
+                                )
+                                .show_ui(ui, |ui| {
+                                    for preset in PalettePreset::ALL {
+                                        if ui
+                                            .selectable_label(
+                                                PalettePreset::matching(palette) == Some(preset),
+                                                <&'static str>::from(preset),
+                                            )
+                                            .clicked()
+                                        {
+                                            *palette = preset.palette(palette.len());
+                                        }
+                                    }
+                                });
+
                             ui.add_space(10.0);
-                            ui.horizontal(|ui| {
-                                ui.label("ON Color ");
-                                ui.add(
-                                    egui::DragValue::new(&mut self.color_config[1][0])
-                                        .range(0..=255),
-                                );
-                                ui.add(
-                                    egui::DragValue::new(&mut self.color_config[1][1])
-                                        .range(0..=255),
-                                );
-                                ui.add(
-                                    egui::DragValue::new(&mut self.color_config[1][2])
-                                        .range(0..=255),
-                                );
+                            ui.label("Colors");
+                            for size in PALETTE_SIZES {
+                                if ui
+                                    .selectable_label(palette.len() == size, size.to_string())
+                                    .clicked()
+                                {
+                                    palette.resize(size);
+                                }
+                            }
+                        });
+
+                        ui.add_space(10.0);
+                        egui::Grid::new("palette colors")
+                            .spacing([20.0, 8.0])
+                            .show(ui, |ui| {
+                                for (index, color) in palette.colors_mut().iter_mut().enumerate() {
+                                    ui.horizontal(|ui| {
+                                        ui.label(match index {
+                                            0 => "OFF".to_string(),
+                                            1 => "ON".to_string(),
+                                            _ => format!("{index:X}"),
+                                        });
+                                        ui.color_edit_button_srgb(color);
+                                    });
+                                    if index % 4 == 3 {
+                                        ui.end_row();
+                                    }
+                                }
                             });
-                        })
+
+                        ui.add_space(10.0);
+                        ui.label("Hex palette");
+                        ui.add(
+                            egui::TextEdit::singleline(&mut self.palette_text)
+                                .desired_width(f32::INFINITY),
+                        );
+                        ui.horizontal(|ui| {
+                            if ui.button("Import").clicked() {
+                                match self.palette_text.parse() {
+                                    Ok(imported) => {
+                                        *palette = imported;
+                                        self.palette_error = false;
+                                    }
+                                    Err(()) => self.palette_error = true,
+                                }
+                            }
+                            if ui.button("Export").clicked() {
+                                self.palette_text = palette.to_string();
+                                self.palette_error = false;
+                                ui.ctx().copy_text(self.palette_text.clone());
+                            }
+                            if self.palette_error {
+                                ui.colored_label(ui.visuals().error_fg_color, "Invalid palette");
+                            }
+                        });
+
+                        if self.settings.palette != old_palette {
+                            self.render_texture();
+                        }
                     });
                     ctx.input(|i| {
                         if i.viewport().close_requested() {
+                            self.settings.save();
                             self.unpause();
                             self.open_color_config = false;
                         }
@@ -258,6 +787,338 @@ impl eframe::App for App {
             );
         }
 
+        if self.open_flicker_config {
+            ctx.show_viewport_immediate(
+                egui::ViewportId::from_hash_of("flicker config"),
+                egui::ViewportBuilder::default()
+                    .with_title("Flicker Config")
+                    .with_inner_size([260.0, 150.0])
+                    .with_resizable(false)
+                    .with_always_on_top(),
+                |ctx, _| {
+                    egui::CentralPanel::default().show(ctx, |ui| {
+                        let mut mode = self.flicker_mode;
+                        for option in FlickerMode::ALL {
+                            ui.radio_value(&mut mode, option, <&'static str>::from(option));
+                        }
+                        if mode != self.flicker_mode {
+                            self.set_flicker_mode(mode);
+                        }
+
+                        ui.add_space(5.0);
+                        ui.add_enabled(
+                            self.flicker_mode == FlickerMode::Ghosting,
+                            egui::Slider::new(&mut self.persistence, 0.0..=0.9).text("Persistence"),
+                        );
+                    });
+                    ctx.input(|i| {
+                        if i.viewport().close_requested() {
+                            self.open_flicker_config = false;
+                        }
+                    })
+                },
+            );
+        }
+
+        if self.open_timing_config {
+            ctx.show_viewport_immediate(
+                egui::ViewportId::from_hash_of("timing config"),
+                egui::ViewportBuilder::default()
+                    .with_title("Timing Config")
+                    .with_inner_size([280.0, 180.0])
+                    .with_resizable(false)
+                    .with_always_on_top(),
+                |ctx, _| {
+                    egui::CentralPanel::default().show(ctx, |ui| {
+                        let mut model = self.timing_model;
+                        for option in TimingModel::ALL {
+                            ui.radio_value(&mut model, option, <&'static str>::from(option));
+                        }
+                        if model != self.timing_model {
+                            self.timing_model = model;
+                            self.send(Message::SetTimingModel(model));
+                        }
+
+                        let response = ui.add_enabled(
+                            self.timing_model == TimingModel::FixedIps,
+                            egui::Slider::new(&mut self.ips, 60..=5000)
+                                .logarithmic(true)
+                                .text("Instructions/s"),
+                        );
+                        if response.changed() {
+                            self.send(Message::SetIps(self.ips));
+                        }
+
+                        ui.add_space(5.0);
+                        ui.label("When the emulator falls behind:");
+                        let mut policy = self.frame_policy;
+                        for option in FramePolicy::ALL {
+                            ui.radio_value(&mut policy, option, <&'static str>::from(option));
+                        }
+                        if policy != self.frame_policy {
+                            self.frame_policy = policy;
+                            self.send(Message::SetFramePolicy(policy));
+                        }
+                    });
+                    ctx.input(|i| {
+                        if i.viewport().close_requested() {
+                            self.open_timing_config = false;
+                        }
+                    })
+                },
+            );
+        }
+
+        if self.open_crt_config {
+            ctx.show_viewport_immediate(
+                egui::ViewportId::from_hash_of("effects config"),
+                egui::ViewportBuilder::default()
+                    .with_title("Effects Config")
+                    .with_inner_size([280.0, 170.0])
+                    .with_resizable(false)
+                    .with_always_on_top(),
+                |ctx, _| {
+                    egui::CentralPanel::default().show(ctx, |ui| {
+                        let old_config = self.crt_config;
+
+                        egui::ComboBox::from_label("Preset")
+                            .selected_text(
+                                CrtPreset::matching(&self.crt_config)
+                                    .map(<&'static str>::from)
+                                    .unwrap_or("Custom"),
+                            )
+                            .show_ui(ui, |ui| {
+                                for preset in CrtPreset::ALL {
+                                    if ui
+                                        .selectable_label(
+                                            CrtPreset::matching(&self.crt_config) == Some(preset),
+                                            <&'static str>::from(preset),
+                                        )
+                                        .clicked()
+                                    {
+                                        self.crt_config = preset.config();
+                                    }
+                                }
+                            });
+
+                        ui.add_space(5.0);
+                        ui.add(
+                            egui::Slider::new(&mut self.crt_config.scanlines, 0.0..=1.0)
+                                .text("Scanlines"),
+                        );
+                        ui.add(
+                            egui::Slider::new(&mut self.crt_config.bloom, 0.0..=1.0).text("Bloom"),
+                        );
+                        ui.add(
+                            egui::Slider::new(&mut self.crt_config.curvature, 0.0..=1.0)
+                                .text("Curvature"),
+                        );
+                        ui.add(
+                            egui::Slider::new(&mut self.crt_config.vignette, 0.0..=1.0)
+                                .text("Vignette"),
+                        );
+
+                        if self.crt_config != old_config {
+                            self.render_texture();
+                        }
+                    });
+                    ctx.input(|i| {
+                        if i.viewport().close_requested() {
+                            self.open_crt_config = false;
+                        }
+                    })
+                },
+            );
+        }
+
+        if self.memory_viewer.open {
+            ctx.show_viewport_immediate(
+                egui::ViewportId::from_hash_of("memory viewer"),
+                egui::ViewportBuilder::default()
+                    .with_title("Memory Viewer")
+                    .with_inner_size([720.0, 480.0]),
+                |ctx, _| {
+                    egui::CentralPanel::default().show(ctx, |ui| {
+                        match (&self.snapshot, &self.handle) {
+                            (Some(snapshot), Some(handle)) => {
+                                if self.memory_viewer.ui(
+                                    ui,
+                                    snapshot,
+                                    handle,
+                                    (self.settings.palette.off(), self.settings.palette.on()),
+                                ) {
+                                    self.file_picker.open_file_picker(Config::CoverageReport);
+                                }
+                            }
+                            _ => {
+                                ui.label("No ROM loaded");
+                            }
+                        }
+                    });
+                    ctx.input(|i| {
+                        if i.viewport().close_requested() {
+                            self.memory_viewer.open = false;
+                        }
+                    })
+                },
+            );
+        }
+
+        if self.disassembly_view.open {
+            ctx.show_viewport_immediate(
+                egui::ViewportId::from_hash_of("disassembly"),
+                egui::ViewportBuilder::default()
+                    .with_title("Disassembly")
+                    .with_inner_size([360.0, 520.0]),
+                |ctx, _| {
+                    egui::CentralPanel::default().show(ctx, |ui| {
+                        match (&self.snapshot, &self.handle) {
+                            (Some(snapshot), Some(handle)) => {
+                                self.disassembly_view.ui(ui, snapshot, handle)
+                            }
+                            _ => {
+                                ui.label("No ROM loaded");
+                            }
+                        }
+                    });
+                    ctx.input(|i| {
+                        if i.viewport().close_requested() {
+                            self.disassembly_view.open = false;
+                        }
+                    })
+                },
+            );
+        }
+
+        if self.debugger_view.open {
+            ctx.show_viewport_immediate(
+                egui::ViewportId::from_hash_of("debugger"),
+                egui::ViewportBuilder::default()
+                    .with_title("Breakpoints & Watchpoints")
+                    .with_inner_size([460.0, 480.0]),
+                |ctx, _| {
+                    egui::CentralPanel::default().show(ctx, |ui| {
+                        match (&self.snapshot, &self.handle) {
+                            (Some(snapshot), Some(handle)) => {
+                                self.debugger_view.ui(ui, snapshot, handle)
+                            }
+                            _ => {
+                                ui.label("No ROM loaded");
+                            }
+                        }
+                    });
+                    ctx.input(|i| {
+                        if i.viewport().close_requested() {
+                            self.debugger_view.open = false;
+                        }
+                    })
+                },
+            );
+        }
+
+        if self.trace_view.open {
+            ctx.show_viewport_immediate(
+                egui::ViewportId::from_hash_of("trace"),
+                egui::ViewportBuilder::default()
+                    .with_title("Trace Log")
+                    .with_inner_size([420.0, 180.0]),
+                |ctx, _| {
+                    egui::CentralPanel::default().show(ctx, |ui| match self.handle {
+                        Some(ref handle) => {
+                            if self.trace_view.ui(ui, handle) {
+                                self.file_picker.open_file_picker(Config::Trace);
+                            }
+                        }
+                        None => {
+                            ui.label("No ROM loaded");
+                        }
+                    });
+                    ctx.input(|i| {
+                        if i.viewport().close_requested() {
+                            self.trace_view.open = false;
+                        }
+                    })
+                },
+            );
+        }
+
+        if self.profiler_view.open {
+            ctx.show_viewport_immediate(
+                egui::ViewportId::from_hash_of("profiler"),
+                egui::ViewportBuilder::default()
+                    .with_title("Profiler")
+                    .with_inner_size([420.0, 480.0]),
+                |ctx, _| {
+                    egui::CentralPanel::default().show(ctx, |ui| match self.handle {
+                        Some(ref handle) => match self.profiler_view.ui(ui, handle) {
+                            Some(ProfileExport::Csv) => {
+                                self.file_picker.open_file_picker(Config::ProfileCsv)
+                            }
+                            Some(ProfileExport::Folded) => {
+                                self.file_picker.open_file_picker(Config::ProfileFolded)
+                            }
+                            None => {}
+                        },
+                        None => {
+                            ui.label("No ROM loaded");
+                        }
+                    });
+                    ctx.input(|i| {
+                        if i.viewport().close_requested() {
+                            self.profiler_view.open = false;
+                        }
+                    })
+                },
+            );
+        }
+
+        if self.shortcuts_view.open {
+            ctx.show_viewport_immediate(
+                egui::ViewportId::from_hash_of("shortcuts"),
+                egui::ViewportBuilder::default()
+                    .with_title("Shortcuts")
+                    .with_inner_size([520.0, 340.0]),
+                |ctx, _| {
+                    egui::CentralPanel::default().show(ctx, |ui| {
+                        if self.shortcuts_view.ui(
+                            ui,
+                            &mut self.settings.shortcuts,
+                            &self.key_mapping,
+                        ) {
+                            self.settings.save();
+                        }
+                    });
+                    ctx.input(|i| {
+                        if i.viewport().close_requested() {
+                            self.shortcuts_view.close();
+                        }
+                    })
+                },
+            );
+        }
+
+        if self.gdb_view.open {
+            ctx.show_viewport_immediate(
+                egui::ViewportId::from_hash_of("gdb"),
+                egui::ViewportBuilder::default()
+                    .with_title("GDB Server")
+                    .with_inner_size([320.0, 120.0]),
+                |ctx, _| {
+                    egui::CentralPanel::default().show(ctx, |ui| match self.handle {
+                        Some(ref handle) => self.gdb_view.ui(ui, handle),
+                        None => {
+                            ui.label("No ROM loaded");
+                        }
+                    });
+                    ctx.input(|i| {
+                        if i.viewport().close_requested() {
+                            self.gdb_view.open = false;
+                        }
+                    })
+                },
+            );
+        }
+
         if self.remap_state.open_selection {
             ctx.show_viewport_immediate(
                 egui::ViewportId::from_hash_of("edit key"),
@@ -269,25 +1130,36 @@ impl eframe::App for App {
                             .map(<&'static str>::from)
                             .unwrap_or("")
                     ))
-                    .with_inner_size([300.0, 90.0])
+                    .with_inner_size([340.0, 130.0])
                     .with_resizable(false)
                     .with_always_on_top(),
                 |ctx, _| {
                     egui::CentralPanel::default().show(ctx, |ui| {
-                        let key = self.remap_state.selected_key.map(|k| k.name()).unwrap_or(
-                            self.key_mapping
-                                .get_key(self.remap_state.target_key.unwrap())
-                                .map(|k| k.name())
-                                .unwrap_or("N/A"),
-                        );
+                        let target_key = self.remap_state.target_key.unwrap();
+                        let current = self.key_mapping.describe(target_key);
 
                         ui.vertical_centered(|ui| {
                             ui.add_space(5.0);
                             ui.label(
-                                "Press and release the desired key, then press ENTER to confirm",
+                                "Press and release the key to add, then press ENTER to confirm",
                             );
                             ui.add_space(10.0);
-                            ui.label(format!("Current key => {key}"));
+                            ui.label(format!("Current keys => {current}"));
+                            if let Some(selected_key) = self.remap_state.selected_key {
+                                ui.label(format!("New key => {}", selected_key.name()));
+                                if let Some(bound) =
+                                    self.key_mapping.conflict(selected_key, target_key)
+                                {
+                                    ui.colored_label(
+                                        ui.visuals().warn_fg_color,
+                                        format!(
+                                            "{} is already bound to {}; ENTER moves it",
+                                            selected_key.name(),
+                                            <&'static str>::from(bound)
+                                        ),
+                                    );
+                                }
+                            }
                         })
                     });
                     ctx.input(|i| {
@@ -305,7 +1177,8 @@ impl eframe::App for App {
                                     if let (Some(target_key), Some(selected_key)) =
                                         (self.remap_state.target_key, self.remap_state.selected_key)
                                     {
-                                        self.key_mapping.remap(target_key, selected_key);
+                                        self.key_mapping.bind(target_key, selected_key);
+                                        self.save_key_mapping();
                                     }
                                     self.remap_state.reset_selection();
                                 } else {
@@ -323,23 +1196,17 @@ impl eframe::App for App {
                 egui::ViewportId::from_hash_of("remap window"),
                 egui::ViewportBuilder::default()
                     .with_title("Key Remap")
-                    .with_inner_size([500.0, 260.0])
+                    .with_inner_size([720.0, 340.0])
                     .with_resizable(false)
                     .with_always_on_top(),
                 |ctx, _vi| {
-                    let key_layout = [
-                        [Chip8Key::K1, Chip8Key::K2, Chip8Key::K3, Chip8Key::KC],
-                        [Chip8Key::K4, Chip8Key::K5, Chip8Key::K6, Chip8Key::KD],
-                        [Chip8Key::K7, Chip8Key::K8, Chip8Key::K9, Chip8Key::KE],
-                        [Chip8Key::KA, Chip8Key::K0, Chip8Key::KB, Chip8Key::KF],
-                    ];
-
                     egui::CentralPanel::default().show(ctx, |ui| {
-                        ui.add_space(10.0);
+                        self.profile_ui(ui);
+                        ui.separator();
                         egui::Grid::new("key mapping")
                             .spacing([20.0, 20.0])
                             .show(ui, |ui| {
-                                for row in key_layout {
+                                for row in KEY_LAYOUT {
                                     for col in row {
                                         ui.vertical(|ui| {
                                             ui.add_space(10.0);
@@ -348,15 +1215,16 @@ impl eframe::App for App {
                                                 ui.label(format!(
                                                     "{} => {}",
                                                     <&'static str>::from(col),
-                                                    self.key_mapping
-                                                        .get_key(col)
-                                                        .map(|k| k.name())
-                                                        .unwrap_or("N/A")
+                                                    self.key_mapping.describe(col)
                                                 ));
-                                                if ui.button("Edit").clicked() {
+                                                if ui.small_button("Add").clicked() {
                                                     self.remap_state.open_selection = true;
                                                     self.remap_state.target_key = Some(col);
                                                 }
+                                                if ui.small_button("Clear").clicked() {
+                                                    self.key_mapping.clear(col);
+                                                    self.save_key_mapping();
+                                                }
                                                 ui.add_space(10.0);
                                             });
                                             ui.add_space(10.0);
@@ -377,34 +1245,147 @@ impl eframe::App for App {
             );
         }
 
-        match self.file_picker.check_file_picker() {
-            Some(FilePickerResult::ROM(path)) => {
-                self.set_new_handle(Chip8Source::ROM(path));
-                self.remap_state.reset_selection();
-                self.remap_state.open_main = false;
-                self.open_color_config = false;
+        if let Some(result) = self.file_picker.check_file_picker() {
+            // Pickers opened while running, like the debug windows' export and browse
+            // buttons, leave the emulator as it was
+            let paused_for_picker = mem::take(&mut self.paused_for_picker);
+
+            match result {
+                FilePickerResult::ROM(path) => {
+                    let rom_name = Path::new(&path)
+                        .file_name()
+                        .map(|name| name.to_string_lossy().into_owned());
+                    if self.set_new_handle(Chip8Source::ROM(path)) {
+                        self.rom_name = rom_name;
+                        if let Some(profile) = self
+                            .rom_name
+                            .as_ref()
+                            .and_then(|rom| self.settings.rom_profiles.get(rom))
+                            .cloned()
+                        {
+                            self.select_profile(&profile);
+                        }
+                        self.remap_state.reset_selection();
+                        self.remap_state.open_main = false;
+                        self.open_color_config = false;
+                    } else if paused_for_picker {
+                        // The old emulator keeps running
+                        self.unpause();
+                    }
+                }
+                FilePickerResult::Load(path) => {
+                    if self.set_new_handle(Chip8Source::SaveState(path)) {
+                        self.remap_state.reset_selection();
+                        self.remap_state.open_main = false;
+                        self.open_color_config = false;
+                    } else if paused_for_picker {
+                        self.unpause();
+                    }
+                }
+                FilePickerResult::Save(path) => {
+                    App::save(self, path);
+                    if paused_for_picker {
+                        self.unpause();
+                    }
+                }
+                FilePickerResult::Trace(path) => {
+                    self.trace_view.path = path;
+                }
+                FilePickerResult::Symbols(path) => match Symbols::load(Path::new(&path)) {
+                    Ok(symbols) => {
+                        if let Some(ref handle) = self.handle {
+                            handle.send(Message::SetSymbols(Arc::new(symbols)));
+                        }
+                        self.debugger_view.error = None;
+                    }
+                    Err(err) => {
+                        self.debugger_view.error = Some(format!("Couldn't load {path}: {err}"));
+                        self.debugger_view.open = true;
+                    }
+                },
+                FilePickerResult::CoverageReport(path) => {
+                    if let Some(ref snapshot) = self.snapshot {
+                        self.memory_viewer.error =
+                            std::fs::write(&path, snapshot.coverage.report())
+                                .err()
+                                .map(|err| format!("Couldn't write {path}: {err}"));
+                    }
+                }
+                FilePickerResult::ProfileCsv(path) => {
+                    if let Some(ref profile) = self.profiler_view.profile {
+                        self.profiler_view.error = std::fs::write(&path, profile.to_csv())
+                            .err()
+                            .map(|err| format!("Couldn't write {path}: {err}"));
+                    }
+                }
+                FilePickerResult::ProfileFolded(path) => {
+                    if let Some(ref profile) = self.profiler_view.profile {
+                        self.profiler_view.error =
+                            std::fs::write(&path, profile.to_folded(&self.profiler_view.symbols))
+                                .err()
+                                .map(|err| format!("Couldn't write {path}: {err}"));
+                    }
+                }
+                FilePickerResult::None => {
+                    if paused_for_picker {
+                        self.unpause();
+                    }
+                }
             }
-            Some(FilePickerResult::Load(path)) => {
-                self.set_new_handle(Chip8Source::SaveState(path));
-                self.remap_state.reset_selection();
-                self.remap_state.open_main = false;
-                self.open_color_config = false;
+        }
+
+        let frame_buffer = self.handle.as_mut().and_then(Chip8Handle::take_frame);
+        if let Some(ref handle) = self.handle {
+            while let Some(message) = handle.try_recv() {
+                match message {
+                    Message::Snapshot(snapshot) => self.snapshot = Some(snapshot),
+                    Message::Break(reason) => {
+                        self.debugger_view.last_break = Some(reason);
+                        self.disassembly_view.open = true;
+                    }
+                    Message::Status(status) => self.status = Some((status, Instant::now())),
+                    Message::TraceStatus(tracing, error) => {
+                        self.trace_view.tracing = tracing;
+                        self.trace_view.error = error;
+                    }
+                    Message::Profile(profile, symbols) => {
+                        self.profiler_view.profile = Some(profile);
+                        self.profiler_view.symbols = symbols;
+                    }
+                    Message::GdbStatus(listening, status) => {
+                        self.gdb_view.listening = listening;
+                        self.gdb_view.status = status;
+                    }
+                    _ => {}
+                }
             }
-            Some(FilePickerResult::Save(path)) => {
-                App::save(self, path);
-                self.unpause();
+
+            if self.memory_viewer.open || self.disassembly_view.open || self.debugger_view.open {
+                // A snapshot copies all of memory, so the views refresh at a fixed rate
+                if self.last_snapshot_request.elapsed() >= SNAPSHOT_INTERVAL {
+                    self.last_snapshot_request = Instant::now();
+                    handle.request_snapshot();
+                }
+                ctx.request_repaint_after(SNAPSHOT_INTERVAL);
             }
-            Some(FilePickerResult::None) => {
-                self.unpause();
+            if self.profiler_view.open {
+                if self.last_profile_request.elapsed() >= PROFILE_INTERVAL {
+                    self.last_profile_request = Instant::now();
+                    handle.send(Message::RequestProfile);
+                }
+                // A program that isn't drawing publishes no frames to wake us up
+                ctx.request_repaint_after(PROFILE_INTERVAL);
             }
-            None => {}
-        }
 
-        if let Some(ref handle) = self.handle {
-            if handle.check_draw_message() {
+            if let Some(frame_buffer) = frame_buffer {
+                self.frame_buffer = frame_buffer;
                 self.set_texture();
-                ctx.request_repaint();
             }
         }
     }
 }
+
+fn toggle_fullscreen(ctx: &Context) {
+    let fullscreen = ctx.input(|i| i.viewport().fullscreen.unwrap_or(false));
+    ctx.send_viewport_cmd(egui::ViewportCommand::Fullscreen(!fullscreen));
+}
@@ -1,10 +1,18 @@
-use std::sync::mpsc::{self, Receiver, Sender, TryRecvError};
+use std::{
+    sync::mpsc::{self, Receiver, RecvTimeoutError, Sender, TryRecvError},
+    time::Duration,
+};
 
 use crate::Message;
 
+/// Lets the receiving side know a message is waiting, e.g. by asking egui to repaint,
+/// so it doesn't have to poll.
+pub type Waker = Box<dyn Fn() + Send>;
+
 pub struct Channel {
     sender: Sender<Message>,
     receiver: Receiver<Message>,
+    waker: Option<Waker>,
 }
 
 impl Channel {
@@ -16,19 +24,39 @@ impl Channel {
             Self {
                 sender: tx1,
                 receiver: rx2,
+                waker: None,
             },
             Self {
                 sender: tx2,
                 receiver: rx1,
+                waker: None,
             },
         )
     }
 
+    /// Calls `waker` after every send, and once now in case something was sent earlier.
+    pub fn set_waker(&mut self, waker: Waker) {
+        waker();
+        self.waker = Some(waker);
+    }
+
     pub fn send(&self, msg: Message) {
         let _ = self.sender.send(msg);
+        self.wake();
+    }
+
+    /// Tells the other side to look for news, such as a frame handed over outside the channel.
+    pub fn wake(&self) {
+        if let Some(ref waker) = self.waker {
+            waker();
+        }
     }
 
     pub fn try_recv(&self) -> Result<Message, TryRecvError> {
         self.receiver.try_recv()
     }
+
+    pub fn recv_timeout(&self, timeout: Duration) -> Result<Message, RecvTimeoutError> {
+        self.receiver.recv_timeout(timeout)
+    }
 }
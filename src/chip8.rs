@@ -1,4 +1,5 @@
 use std::{
+    collections::VecDeque,
     fs::File,
     io::{self, Read},
     sync::{Arc, Mutex},
@@ -6,52 +7,120 @@ use std::{
 
 use crate::{
     chip8_state::Chip8State,
+    coverage::Coverage,
     cpu::Cpu,
+    debugger::{BreakReason, Debugger},
     error::Result,
     frame_buffer::FrameBuffer,
-    key_matrix::{Chip8Key, KeyMatrix},
+    instruction::Instruction,
+    key_matrix::KeyMatrix,
+    key_wait::{KeyEvent, KeyWait, KeyWaitMode},
     memory::Memory,
+    profiler::Profiler,
+    symbols::Symbols,
+    tracer::{TraceConfig, Tracer},
 };
 
+/// Copy of the machine state handed to the GUI's debug views.
+pub struct DebugSnapshot {
+    pub cpu: Cpu,
+    pub memory: Memory,
+    pub paused: bool,
+    pub debugger: Debugger,
+    pub coverage: Coverage,
+    pub recording_coverage: bool,
+    pub symbols: Arc<Symbols>,
+    /// Instructions executed since the emulator started, for measuring speed.
+    pub instructions: u64,
+}
+
 pub struct Chip8 {
     cpu: Cpu,
     memory: Memory,
 
-    frame_buffer: Arc<Mutex<FrameBuffer>>,
+    /// Owned by the emulator thread; finished frames are copied out to the UI.
+    frame_buffer: FrameBuffer,
     key_matrix: Arc<Mutex<KeyMatrix>>,
 
     paused: bool,
 
-    last_released_key: Option<Chip8Key>,
+    debugger: Debugger,
+    pending_break: Option<BreakReason>,
+
+    display_wait: bool,
+    drew_this_frame: bool,
+
+    tracer: Option<Tracer>,
+    /// Why the trace stopped on its own, until the emulator thread reports it.
+    trace_error: Option<io::Error>,
+    symbols: Arc<Symbols>,
+
+    profiling: bool,
+    profiler: Profiler,
+
+    recording_coverage: bool,
+    coverage: Coverage,
+
+    /// Key presses and releases since the last vblank, for `FX0A`.
+    key_events: VecDeque<KeyEvent>,
+    key_wait_mode: KeyWaitMode,
+
+    /// The ROM as loaded, for hard resets. Empty when started from a save state.
+    rom: Vec<u8>,
+
+    instructions: u64,
 }
 
 impl Chip8 {
-    pub fn new(frame_buffer: Arc<Mutex<FrameBuffer>>, key_matrix: Arc<Mutex<KeyMatrix>>) -> Self {
+    pub fn new(key_matrix: Arc<Mutex<KeyMatrix>>) -> Self {
         Self {
             cpu: Cpu::new(),
             memory: Memory::new(),
-            frame_buffer,
+            frame_buffer: FrameBuffer::new(),
             key_matrix,
             paused: false,
-            last_released_key: None,
+            debugger: Debugger::new(),
+            pending_break: None,
+            display_wait: false,
+            drew_this_frame: false,
+            tracer: None,
+            trace_error: None,
+            symbols: Arc::new(Symbols::new()),
+            profiling: false,
+            profiler: Profiler::new(),
+            recording_coverage: false,
+            coverage: Coverage::new(),
+            key_events: VecDeque::new(),
+            key_wait_mode: KeyWaitMode::Release,
+            rom: Vec::new(),
+            instructions: 0,
         }
     }
 
-    pub fn new_from_save_state(
-        frame_buffer: Arc<Mutex<FrameBuffer>>,
-        key_matrix: Arc<Mutex<KeyMatrix>>,
-        state: Chip8State,
-    ) -> Self {
-        frame_buffer.lock().unwrap().load(state.frame_buffer);
+    pub fn new_from_save_state(key_matrix: Arc<Mutex<KeyMatrix>>, state: Chip8State) -> Self {
         key_matrix.lock().unwrap().load(state.key_matrix);
 
         Self {
             cpu: state.cpu,
             memory: state.memory,
-            frame_buffer,
+            frame_buffer: state.frame_buffer,
             key_matrix,
             paused: false,
-            last_released_key: state.last_released_key,
+            debugger: Debugger::new(),
+            pending_break: None,
+            display_wait: false,
+            drew_this_frame: false,
+            tracer: None,
+            trace_error: None,
+            symbols: Arc::new(Symbols::new()),
+            profiling: false,
+            profiler: Profiler::new(),
+            recording_coverage: false,
+            coverage: Coverage::new(),
+            key_events: VecDeque::new(),
+            key_wait_mode: KeyWaitMode::Release,
+            rom: Vec::new(),
+            instructions: 0,
         }
     }
 
@@ -59,14 +128,134 @@ impl Chip8 {
         Chip8State {
             cpu: self.cpu.clone(),
             memory: self.memory.clone(),
-            frame_buffer: self.frame_buffer.lock().unwrap().clone(),
+            frame_buffer: self.frame_buffer.clone(),
             key_matrix: self.key_matrix.lock().unwrap().clone(),
-            last_released_key: self.last_released_key,
         }
     }
 
-    pub fn set_last_released_key(&mut self, key: Chip8Key) {
-        self.last_released_key = Some(key);
+    /// Rolls the machine back to `state`. Held keys are left alone since they reflect the
+    /// keyboard right now.
+    pub fn load_state(&mut self, state: Chip8State) {
+        self.cpu = state.cpu;
+        self.memory = state.memory;
+        self.frame_buffer = state.frame_buffer;
+        self.key_events.clear();
+        self.pending_break = None;
+        self.drew_this_frame = false;
+    }
+
+    pub fn debug_snapshot(&self) -> DebugSnapshot {
+        DebugSnapshot {
+            cpu: self.cpu.clone(),
+            memory: self.memory.clone(),
+            paused: self.paused,
+            debugger: self.debugger.clone(),
+            coverage: self.coverage.clone(),
+            recording_coverage: self.recording_coverage,
+            symbols: self.symbols.clone(),
+            instructions: self.instructions,
+        }
+    }
+
+    pub fn cpu(&self) -> &Cpu {
+        &self.cpu
+    }
+
+    pub fn cpu_mut(&mut self) -> &mut Cpu {
+        &mut self.cpu
+    }
+
+    /// The instruction at PC, which the next `tick` will run.
+    pub fn next_instruction(&self) -> Result<Instruction> {
+        self.cpu.peek_instruction(&self.memory)
+    }
+
+    pub fn frame_buffer(&self) -> &FrameBuffer {
+        &self.frame_buffer
+    }
+
+    pub fn memory(&self) -> &Memory {
+        &self.memory
+    }
+
+    pub fn write_memory(&mut self, addr: u16, byte: u8) -> Result<()> {
+        self.memory.write(addr, byte)
+    }
+
+    pub fn debugger_mut(&mut self) -> &mut Debugger {
+        &mut self.debugger
+    }
+
+    pub fn run_to(&mut self, addr: u16) {
+        self.debugger.run_to(addr);
+        self.unpause();
+    }
+
+    /// Checked before every tick: reports a watchpoint hit by the previous instruction,
+    /// or a breakpoint/condition on the one about to run.
+    pub fn check_break(&mut self) -> Option<BreakReason> {
+        self.pending_break
+            .take()
+            .or_else(|| self.debugger.before_tick(&self.cpu, &self.memory))
+    }
+
+    pub fn start_trace(&mut self, config: TraceConfig) -> io::Result<()> {
+        self.stop_trace()?;
+        self.tracer = Some(Tracer::new(config, self.symbols.clone())?);
+        Ok(())
+    }
+
+    pub fn stop_trace(&mut self) -> io::Result<()> {
+        match self.tracer.take() {
+            Some(mut tracer) => tracer.finish(),
+            None => Ok(()),
+        }
+    }
+
+    /// Used by the debug views and by traces started afterwards.
+    pub fn set_symbols(&mut self, symbols: Arc<Symbols>) {
+        self.symbols = symbols;
+    }
+
+    pub fn symbols(&self) -> &Arc<Symbols> {
+        &self.symbols
+    }
+
+    /// The write error that ended tracing during a tick, if there was one since last asked.
+    pub fn take_trace_error(&mut self) -> Option<io::Error> {
+        self.trace_error.take()
+    }
+
+    pub fn is_tracing(&self) -> bool {
+        self.tracer.is_some()
+    }
+
+    pub fn set_profiling(&mut self, profiling: bool) {
+        self.profiling = profiling;
+    }
+
+    pub fn reset_profile(&mut self) {
+        self.profiler = Profiler::new();
+    }
+
+    pub fn profile(&self) -> &Profiler {
+        &self.profiler
+    }
+
+    pub fn set_recording_coverage(&mut self, recording: bool) {
+        self.recording_coverage = recording;
+    }
+
+    pub fn reset_coverage(&mut self) {
+        self.coverage = Coverage::new();
+    }
+
+    pub fn key_event(&mut self, event: KeyEvent) {
+        self.key_events.push_back(event);
+    }
+
+    pub fn set_key_wait_mode(&mut self, mode: KeyWaitMode) {
+        self.key_wait_mode = mode;
     }
 
     pub fn load_rom(&mut self, file_name: &str) -> io::Result<()> {
@@ -76,21 +265,117 @@ impl Chip8 {
         file.read_to_end(&mut buffer)?;
 
         self.memory.load_rom(&buffer);
+        self.rom = buffer;
 
         Ok(())
     }
 
+    /// Restarts the program from `ROM_START_ADDR` with registers, stack and timers cleared.
+    /// Memory and the display are left as they are.
+    pub fn reset(&mut self) {
+        self.cpu = Cpu::new();
+        self.key_events.clear();
+        self.pending_break = None;
+        self.drew_this_frame = false;
+    }
+
+    /// Like `reset`, but also reloads memory from the cached ROM and clears the display.
+    /// Without a ROM (started from a save state) memory is kept.
+    pub fn hard_reset(&mut self) {
+        self.reset();
+
+        if !self.rom.is_empty() {
+            self.memory = Memory::new();
+            self.memory.load_rom(&self.rom);
+        }
+        self.frame_buffer.clear();
+    }
+
+    pub fn set_display_wait(&mut self, display_wait: bool) {
+        self.display_wait = display_wait;
+    }
+
     pub fn tick_60hz(&mut self) {
         self.cpu.tick_60hz();
+        self.drew_this_frame = false;
+
+        // Only a wait in progress cares about older key events
+        if self.cpu.key_wait() == KeyWait::Idle {
+            self.key_events.clear();
+        }
+    }
+
+    /// Runs one instruction for the debugger. A `Drw` stalled by display wait can't run
+    /// before the next vblank, so the step goes through that vblank (timers count down
+    /// once) instead of doing nothing.
+    pub fn step(&mut self) -> Result<bool> {
+        let instruction = self.cpu.peek_instruction(&self.memory)?;
+        if self.waits_for_vblank(&instruction) {
+            self.tick_60hz();
+        }
+
+        self.tick()
     }
 
     pub fn tick(&mut self) -> Result<bool> {
-        self.cpu.tick(
+        let instruction = self.cpu.peek_instruction(&self.memory)?;
+
+        if self.waits_for_vblank(&instruction) {
+            return Ok(false);
+        }
+
+        let pc = self.cpu.pc();
+        let before = self.debugger.watches_registers().then(|| self.cpu.clone());
+        // Read up front, the instruction may overwrite itself
+        let opcode = self.tracer.is_some().then(|| {
+            let bytes = self.memory.get_ref();
+            u16::from_be_bytes([bytes[pc as usize], bytes[pc as usize + 1]])
+        });
+
+        if self.profiling {
+            self.profiler
+                .record(pc, &instruction, &self.cpu, &self.memory);
+        }
+
+        // Only watchpoints and coverage look at what the instruction touched
+        self.memory
+            .set_recording(self.recording_coverage || !self.debugger.watchpoints().is_empty());
+        self.memory.clear_accesses();
+        let drew = self.cpu.tick(
             &mut self.memory,
-            self.frame_buffer.clone(),
+            &mut self.frame_buffer,
             self.key_matrix.clone(),
-            self.last_released_key.take(),
-        )
+            &mut self.key_events,
+            self.key_wait_mode,
+        )?;
+
+        self.drew_this_frame |= drew;
+        self.instructions += 1;
+
+        if let (Some(tracer), Some(opcode)) = (self.tracer.as_mut(), opcode) {
+            // A trace that can no longer be written shouldn't bring down the emulator
+            if let Err(err) = tracer.record(pc, opcode, &instruction, &self.cpu) {
+                self.tracer = None;
+                self.trace_error = Some(err);
+            }
+        }
+
+        let accesses = self.memory.accesses();
+
+        if self.recording_coverage {
+            self.coverage.record(pc, &accesses);
+        }
+
+        self.pending_break = self
+            .debugger
+            .after_tick(pc, &accesses, before.as_ref(), &self.cpu);
+
+        Ok(drew)
+    }
+
+    /// With display wait on, a second sprite in the same frame stalls until the next vblank.
+    fn waits_for_vblank(&self, instruction: &Instruction) -> bool {
+        self.display_wait && self.drew_this_frame && matches!(instruction, Instruction::Drw { .. })
     }
 
     pub fn pause(&mut self) {
@@ -103,3 +388,39 @@ impl Chip8 {
         self.paused
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory::ROM_START_ADDR;
+
+    fn chip8_with(rom: &[u8]) -> Chip8 {
+        let mut chip8 = Chip8::new(Arc::new(Mutex::new(KeyMatrix::new())));
+        for (offset, &byte) in rom.iter().enumerate() {
+            chip8
+                .write_memory(ROM_START_ADDR + offset as u16, byte)
+                .unwrap();
+        }
+        chip8
+    }
+
+    #[test]
+    fn step_runs_a_sprite_stalled_by_display_wait() {
+        // LD ST, V0 with V0 = 2, then two sprites
+        let mut chip8 = chip8_with(&[0x60, 0x02, 0xF0, 0x18, 0xD0, 0x01, 0xD0, 0x01]);
+        chip8.set_display_wait(true);
+
+        chip8.tick().unwrap();
+        chip8.tick().unwrap();
+        assert!(chip8.tick().unwrap());
+
+        // Running, the second sprite waits for vblank...
+        assert!(!chip8.tick().unwrap());
+        assert_eq!(chip8.cpu().pc(), ROM_START_ADDR + 6);
+
+        // ...while a step goes through it
+        assert!(chip8.step().unwrap());
+        assert_eq!(chip8.cpu().pc(), ROM_START_ADDR + 8);
+        assert_eq!(chip8.cpu().st(), 1);
+    }
+}
@@ -1,13 +1,17 @@
-use std::fs::File;
+use std::{
+    fs::File,
+    io::{self, BufReader, BufWriter, Read, Write},
+};
 
 use bincode::{Decode, Encode};
 
-use crate::{
-    cpu::Cpu,
-    frame_buffer::FrameBuffer,
-    key_matrix::{Chip8Key, KeyMatrix},
-    memory::Memory,
-};
+use crate::{cpu::Cpu, frame_buffer::FrameBuffer, key_matrix::KeyMatrix, memory::Memory};
+
+/// Starts every save state, so other files are rejected instead of misread.
+const MAGIC: [u8; 4] = *b"CH8S";
+/// The bincode layout below is positional: bump this whenever a saved field changes shape,
+/// including fields of `Cpu`, `Memory`, `FrameBuffer` and `KeyMatrix`.
+const VERSION: u32 = 1;
 
 #[derive(Encode, Decode)]
 pub struct Chip8State {
@@ -19,18 +23,111 @@ pub struct Chip8State {
     pub frame_buffer: FrameBuffer,
     #[bincode(with_serde)]
     pub key_matrix: KeyMatrix,
-    #[bincode(with_serde)]
-    pub last_released_key: Option<Chip8Key>,
 }
 
 impl Chip8State {
-    pub fn save(&self, path: &str) {
-        let mut file = File::create(path).expect("Failed to create save file");
-        bincode::encode_into_std_write(self, &mut file, bincode::config::standard()).unwrap();
+    pub fn write(&self, path: &str) -> io::Result<()> {
+        let mut file = BufWriter::new(File::create(path)?);
+        self.write_to(&mut file)?;
+        file.flush()
+    }
+
+    pub fn load(path: &str) -> io::Result<Self> {
+        Self::read_from(BufReader::new(File::open(path)?))
+    }
+
+    /// Writes the header, then the state.
+    pub fn write_to(&self, mut writer: impl Write) -> io::Result<()> {
+        writer.write_all(&MAGIC)?;
+        writer.write_all(&VERSION.to_le_bytes())?;
+        bincode::encode_into_std_write(self, &mut writer, bincode::config::standard())
+            .map(|_| ())
+            .map_err(io::Error::other)
+    }
+
+    /// Reads a state written by `write_to` in this version of the emulator.
+    pub fn read_from(mut reader: impl Read) -> io::Result<Self> {
+        let mut header = [0; 8];
+        reader
+            .read_exact(&mut header)
+            .map_err(|err| match err.kind() {
+                io::ErrorKind::UnexpectedEof => invalid("file is too short to be a save state"),
+                _ => err,
+            })?;
+
+        if header[..4] != MAGIC {
+            return Err(invalid("not a save state, or saved by an older version"));
+        }
+        let version = u32::from_le_bytes([header[4], header[5], header[6], header[7]]);
+        if version != VERSION {
+            return Err(invalid(&format!(
+                "saved by a different version (format {version}, expected {VERSION})"
+            )));
+        }
+
+        bincode::decode_from_std_read(&mut reader, bincode::config::standard())
+            .map_err(|err| invalid(&format!("corrupt save state: {err}")))
+    }
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn state() -> Chip8State {
+        Chip8State {
+            cpu: Cpu::new(),
+            memory: Memory::new(),
+            frame_buffer: FrameBuffer::new(),
+            key_matrix: KeyMatrix::new(),
+        }
+    }
+
+    fn saved() -> Vec<u8> {
+        let mut bytes = Vec::new();
+        state().write_to(&mut bytes).unwrap();
+        bytes
+    }
+
+    fn error(bytes: &[u8]) -> String {
+        match Chip8State::read_from(bytes) {
+            Ok(_) => panic!("loaded a bad save state"),
+            Err(err) => {
+                assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+                err.to_string()
+            }
+        }
+    }
+
+    #[test]
+    fn round_trips() {
+        let bytes = saved();
+        let loaded = Chip8State::read_from(bytes.as_slice()).unwrap();
+
+        assert_eq!(loaded.memory.get_ref(), state().memory.get_ref());
+        assert_eq!(loaded.cpu.pc(), state().cpu.pc());
+    }
+
+    #[test]
+    fn rejects_bad_headers() {
+        let mut bytes = saved();
+
+        assert!(error(&bytes[..3]).contains("too short"));
+
+        bytes[4] = VERSION as u8 + 1;
+        assert!(error(&bytes).contains("different version"));
+
+        // A headerless state from before versioning
+        assert!(error(&bytes[8..]).contains("not a save state"));
     }
 
-    pub fn load(path: &str) -> Self {
-        let mut file = File::open(path).expect("Failed to open save file");
-        bincode::decode_from_std_read(&mut file, bincode::config::standard()).unwrap()
+    #[test]
+    fn rejects_truncated_states() {
+        let bytes = saved();
+        assert!(error(&bytes[..bytes.len() - 1]).contains("corrupt"));
     }
 }
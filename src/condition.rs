@@ -0,0 +1,435 @@
+use std::{fmt, str::FromStr};
+
+use crate::{cpu::Cpu, cpu::Register, memory::Memory};
+
+/// A parsed breakpoint condition such as `v0 > 5 && [i] != 0`.
+///
+/// Supports registers (`v0`-`vf`, `i`, `pc`, `sp`, `dt`, `st`), decimal and `0x` hex numbers,
+/// memory bytes (`[i + 1]`), `+ - & |`, comparisons, `!`, `&&`, `||` and parentheses.
+#[derive(Clone)]
+pub struct Condition {
+    source: String,
+    expr: Expr,
+}
+
+impl Condition {
+    pub fn is_met(&self, cpu: &Cpu, memory: &Memory) -> bool {
+        self.expr.eval(cpu, memory) != 0
+    }
+}
+
+impl fmt::Display for Condition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.source)
+    }
+}
+
+impl FromStr for Condition {
+    type Err = ConditionError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let tokens = tokenize(s)?;
+        let mut parser = Parser { tokens, pos: 0 };
+
+        let expr = parser.or()?;
+        if let Some(token) = parser.peek() {
+            return Err(ConditionError::UnexpectedToken(token.to_string()));
+        }
+
+        Ok(Condition {
+            source: s.trim().to_string(),
+            expr,
+        })
+    }
+}
+
+#[derive(Debug)]
+pub enum ConditionError {
+    UnexpectedToken(String),
+    UnexpectedEnd,
+    InvalidNumber(String),
+}
+
+impl fmt::Display for ConditionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConditionError::UnexpectedToken(token) => write!(f, "unexpected `{token}`"),
+            ConditionError::UnexpectedEnd => write!(f, "unexpected end of expression"),
+            ConditionError::InvalidNumber(number) => write!(f, "invalid number `{number}`"),
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+enum BinaryOp {
+    Or,
+    And,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Add,
+    Sub,
+    BitAnd,
+    BitOr,
+}
+
+#[derive(Clone)]
+enum Expr {
+    Number(u32),
+    Register(Register),
+    Memory(Box<Expr>),
+    Not(Box<Expr>),
+    Binary(BinaryOp, Box<Expr>, Box<Expr>),
+}
+
+impl Expr {
+    fn eval(&self, cpu: &Cpu, memory: &Memory) -> u32 {
+        match self {
+            Expr::Number(value) => *value,
+            Expr::Register(register) => cpu.register(*register) as u32,
+            Expr::Memory(addr) => {
+                let addr = addr.eval(cpu, memory);
+                u16::try_from(addr)
+                    .ok()
+                    .and_then(|addr| memory.read(addr).ok())
+                    .unwrap_or(0) as u32
+            }
+            Expr::Not(expr) => (expr.eval(cpu, memory) == 0) as u32,
+            Expr::Binary(op, lhs, rhs) => {
+                let lhs = lhs.eval(cpu, memory);
+
+                // Short-circuit so `[i] == 1 && ...` style guards behave as expected
+                match op {
+                    BinaryOp::Or if lhs != 0 => return 1,
+                    BinaryOp::And if lhs == 0 => return 0,
+                    _ => {}
+                }
+
+                let rhs = rhs.eval(cpu, memory);
+
+                match op {
+                    BinaryOp::Or | BinaryOp::And => (rhs != 0) as u32,
+                    BinaryOp::Eq => (lhs == rhs) as u32,
+                    BinaryOp::Ne => (lhs != rhs) as u32,
+                    BinaryOp::Lt => (lhs < rhs) as u32,
+                    BinaryOp::Le => (lhs <= rhs) as u32,
+                    BinaryOp::Gt => (lhs > rhs) as u32,
+                    BinaryOp::Ge => (lhs >= rhs) as u32,
+                    BinaryOp::Add => lhs.wrapping_add(rhs),
+                    BinaryOp::Sub => lhs.wrapping_sub(rhs),
+                    BinaryOp::BitAnd => lhs & rhs,
+                    BinaryOp::BitOr => lhs | rhs,
+                }
+            }
+        }
+    }
+}
+
+#[derive(Clone)]
+enum Token {
+    Number(u32),
+    Register(Register),
+    Op(&'static str),
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Token::Number(value) => write!(f, "{value}"),
+            Token::Register(register) => write!(f, "{register}"),
+            Token::Op(op) => write!(f, "{op}"),
+        }
+    }
+}
+
+// Longest operators first so `<=` isn't read as `<` followed by `=`
+const OPERATORS: [&str; 17] = [
+    "||", "&&", "==", "!=", "<=", ">=", "<", ">", "+", "-", "&", "|", "!", "(", ")", "[", "]",
+];
+
+fn tokenize(s: &str) -> Result<Vec<Token>, ConditionError> {
+    let mut tokens = Vec::new();
+    let mut rest = s.trim_start();
+
+    while !rest.is_empty() {
+        if let Some(op) = OPERATORS.into_iter().find(|op| rest.starts_with(op)) {
+            tokens.push(Token::Op(op));
+            rest = &rest[op.len()..];
+        } else {
+            let end = rest
+                .find(|c: char| !c.is_ascii_alphanumeric())
+                .unwrap_or(rest.len());
+            if end == 0 {
+                return Err(ConditionError::UnexpectedToken(
+                    rest.chars().next().unwrap().to_string(),
+                ));
+            }
+
+            let word = &rest[..end];
+            tokens.push(parse_word(word)?);
+            rest = &rest[end..];
+        }
+
+        rest = rest.trim_start();
+    }
+
+    Ok(tokens)
+}
+
+fn parse_word(word: &str) -> Result<Token, ConditionError> {
+    if let Ok(register) = word.parse() {
+        return Ok(Token::Register(register));
+    }
+
+    let number = if let Some(hex) = word.strip_prefix("0x").or_else(|| word.strip_prefix("0X")) {
+        u32::from_str_radix(hex, 16)
+    } else if word.starts_with(|c: char| c.is_ascii_digit()) {
+        word.parse()
+    } else {
+        return Err(ConditionError::UnexpectedToken(word.to_string()));
+    };
+
+    number
+        .map(Token::Number)
+        .map_err(|_| ConditionError::InvalidNumber(word.to_string()))
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Result<Token, ConditionError> {
+        let token = self
+            .tokens
+            .get(self.pos)
+            .cloned()
+            .ok_or(ConditionError::UnexpectedEnd)?;
+        self.pos += 1;
+        Ok(token)
+    }
+
+    fn eat(&mut self, op: &str) -> bool {
+        if matches!(self.peek(), Some(Token::Op(token)) if *token == op) {
+            self.pos += 1;
+            return true;
+        }
+        false
+    }
+
+    fn expect(&mut self, op: &str) -> Result<(), ConditionError> {
+        if self.eat(op) {
+            return Ok(());
+        }
+        match self.peek() {
+            Some(token) => Err(ConditionError::UnexpectedToken(token.to_string())),
+            None => Err(ConditionError::UnexpectedEnd),
+        }
+    }
+
+    fn or(&mut self) -> Result<Expr, ConditionError> {
+        let mut lhs = self.and()?;
+        while self.eat("||") {
+            lhs = Expr::Binary(BinaryOp::Or, Box::new(lhs), Box::new(self.and()?));
+        }
+        Ok(lhs)
+    }
+
+    fn and(&mut self) -> Result<Expr, ConditionError> {
+        let mut lhs = self.comparison()?;
+        while self.eat("&&") {
+            lhs = Expr::Binary(BinaryOp::And, Box::new(lhs), Box::new(self.comparison()?));
+        }
+        Ok(lhs)
+    }
+
+    fn comparison(&mut self) -> Result<Expr, ConditionError> {
+        let lhs = self.sum()?;
+
+        let op = [
+            ("==", BinaryOp::Eq),
+            ("!=", BinaryOp::Ne),
+            ("<=", BinaryOp::Le),
+            (">=", BinaryOp::Ge),
+            ("<", BinaryOp::Lt),
+            (">", BinaryOp::Gt),
+        ]
+        .into_iter()
+        .find(|(token, _)| self.eat(token));
+
+        match op {
+            Some((_, op)) => Ok(Expr::Binary(op, Box::new(lhs), Box::new(self.sum()?))),
+            None => Ok(lhs),
+        }
+    }
+
+    fn sum(&mut self) -> Result<Expr, ConditionError> {
+        let mut lhs = self.unary()?;
+
+        loop {
+            let op = [
+                ("+", BinaryOp::Add),
+                ("-", BinaryOp::Sub),
+                ("&", BinaryOp::BitAnd),
+                ("|", BinaryOp::BitOr),
+            ]
+            .into_iter()
+            .find(|(token, _)| self.eat(token));
+
+            match op {
+                Some((_, op)) => lhs = Expr::Binary(op, Box::new(lhs), Box::new(self.unary()?)),
+                None => return Ok(lhs),
+            }
+        }
+    }
+
+    fn unary(&mut self) -> Result<Expr, ConditionError> {
+        if self.eat("!") {
+            return Ok(Expr::Not(Box::new(self.unary()?)));
+        }
+
+        match self.next()? {
+            Token::Number(value) => Ok(Expr::Number(value)),
+            Token::Register(register) => Ok(Expr::Register(register)),
+            Token::Op("(") => {
+                let expr = self.or()?;
+                self.expect(")")?;
+                Ok(expr)
+            }
+            Token::Op("[") => {
+                let expr = self.or()?;
+                self.expect("]")?;
+                Ok(Expr::Memory(Box::new(expr)))
+            }
+            token => Err(ConditionError::UnexpectedToken(token.to_string())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// V0 = 5, I = 0x300 and memory 0x301 = 7.
+    fn machine() -> (Cpu, Memory) {
+        let mut cpu = Cpu::new();
+        cpu.set_register(Register::V(0), 5);
+        cpu.set_register(Register::I, 0x300);
+
+        let mut memory = Memory::new();
+        memory.write(0x301, 7).unwrap();
+        memory.set_recording(true);
+        memory.clear_accesses();
+
+        (cpu, memory)
+    }
+
+    fn holds(text: &str) -> bool {
+        let (cpu, memory) = machine();
+        text.parse::<Condition>().unwrap().is_met(&cpu, &memory)
+    }
+
+    #[test]
+    fn and_binds_tighter_than_or() {
+        assert!(holds("1 || 0 && 0"));
+        assert!(holds("0 && 1 || 1"));
+        assert!(!holds("(1 || 0) && 0"));
+        assert!(holds("v0 == 5 && v1 == 0 || 0"));
+    }
+
+    #[test]
+    fn and_and_or_short_circuit() {
+        // How many memory reads evaluating `text` made
+        let evaluate = |text: &str| {
+            let (cpu, memory) = machine();
+            text.parse::<Condition>().unwrap().is_met(&cpu, &memory);
+            memory.accesses().len()
+        };
+
+        assert_eq!(evaluate("0 && [i] == 0"), 0);
+        assert_eq!(evaluate("1 || [i] == 0"), 0);
+        assert_eq!(evaluate("1 && [i] == 0"), 1);
+        assert_eq!(evaluate("0 || [i] == 0"), 1);
+    }
+
+    #[test]
+    fn hex_and_decimal_literals() {
+        assert!(holds("0x2A0 == 672"));
+        assert!(holds("0X1f == 31"));
+        assert!(holds("i == 0x300 && i == 768"));
+    }
+
+    #[test]
+    fn memory_dereference() {
+        assert!(holds("[i + 1] == 7"));
+        assert!(holds("[i] == 0"));
+        assert!(holds("[0x301] - [i] == 7"));
+        // Past the end of memory reads as 0
+        assert!(holds("[0x10000] == 0"));
+    }
+
+    #[test]
+    fn unary_not() {
+        assert!(holds("!0"));
+        assert!(!holds("!v0"));
+        assert!(holds("!!v0"));
+        assert!(holds("!(v0 == 4)"));
+    }
+
+    #[test]
+    fn comparisons() {
+        for (text, expected) in [
+            ("v0 == 5", true),
+            ("v0 == 4", false),
+            ("v0 != 4", true),
+            ("v0 != 5", false),
+            ("v0 < 6", true),
+            ("v0 < 5", false),
+            ("v0 <= 5", true),
+            ("v0 <= 4", false),
+            ("v0 > 4", true),
+            ("v0 > 5", false),
+            ("v0 >= 5", true),
+            ("v0 >= 6", false),
+        ] {
+            assert_eq!(holds(text), expected, "{text}");
+        }
+    }
+
+    #[test]
+    fn arithmetic() {
+        assert!(holds("v0 + 1 == 6"));
+        assert!(holds("v0 - 1 == 4"));
+        assert!(holds("v0 & 4 == 4"));
+        assert!(holds("v0 | 2 == 7"));
+    }
+
+    #[test]
+    fn malformed_conditions_are_rejected() {
+        let error = |text: &str| text.parse::<Condition>().err().unwrap();
+
+        assert!(matches!(error("v0 > 5 &&"), ConditionError::UnexpectedEnd));
+        assert!(matches!(error("v0 =="), ConditionError::UnexpectedEnd));
+        assert!(matches!(error("(v0"), ConditionError::UnexpectedEnd));
+        assert!(matches!(error(""), ConditionError::UnexpectedEnd));
+        assert!(matches!(error("vg > 1"), ConditionError::UnexpectedToken(t) if t == "vg"));
+        assert!(matches!(error("v0 5"), ConditionError::UnexpectedToken(t) if t == "5"));
+        assert!(matches!(error("v0 # 1"), ConditionError::UnexpectedToken(t) if t == "#"));
+        assert!(matches!(error("v0 == 0xZZ"), ConditionError::InvalidNumber(n) if n == "0xZZ"));
+        assert!(matches!(error("12ab"), ConditionError::InvalidNumber(_)));
+    }
+
+    #[test]
+    fn displays_the_source() {
+        let condition: Condition = "  v0 > 5 ".parse().unwrap();
+        assert_eq!(condition.to_string(), "v0 > 5");
+    }
+}
@@ -0,0 +1,171 @@
+use std::fmt::Write as _;
+
+use crate::memory::{AccessKind, MEMORY_SIZE, MemoryAccess};
+
+pub const EXECUTED: u8 = 1 << 0;
+pub const READ: u8 = 1 << 1;
+pub const WRITTEN: u8 = 1 << 2;
+
+/// How each byte of memory has been used since recording started.
+#[derive(Clone)]
+pub struct Coverage {
+    flags: Vec<u8>,
+}
+
+impl Coverage {
+    pub fn new() -> Self {
+        Self {
+            flags: vec![0; MEMORY_SIZE],
+        }
+    }
+
+    /// Marks both bytes of the instruction at `pc`, plus whatever data it touched.
+    pub fn record(&mut self, pc: u16, accesses: &[MemoryAccess]) {
+        self.mark(pc, 2, EXECUTED);
+
+        for access in accesses {
+            let flag = match access.kind {
+                AccessKind::Read => READ,
+                AccessKind::Write => WRITTEN,
+            };
+            self.mark(access.addr, access.len, flag);
+        }
+    }
+
+    fn mark(&mut self, addr: u16, len: u16, flag: u8) {
+        let start = (addr as usize).min(MEMORY_SIZE);
+        let end = (addr as usize + len as usize).min(MEMORY_SIZE);
+
+        for flags in &mut self.flags[start..end] {
+            *flags |= flag;
+        }
+    }
+
+    pub fn flags(&self, addr: u16) -> u8 {
+        self.flags.get(addr as usize).copied().unwrap_or(0)
+    }
+
+    pub fn is_executed(&self, addr: u16) -> bool {
+        self.flags(addr) & EXECUTED != 0
+    }
+
+    /// Read as data but never executed.
+    pub fn is_data(&self, addr: u16) -> bool {
+        self.flags(addr) & (EXECUTED | READ) == READ
+    }
+
+    pub fn count(&self, flag: u8) -> usize {
+        self.flags
+            .iter()
+            .filter(|&&flags| flags & flag != 0)
+            .count()
+    }
+
+    /// Totals, then one line per run of addresses used the same way.
+    pub fn report(&self) -> String {
+        let mut report = String::new();
+        let _ = writeln!(report, "executed {} bytes", self.count(EXECUTED));
+        let _ = writeln!(report, "read     {} bytes", self.count(READ));
+        let _ = writeln!(report, "written  {} bytes", self.count(WRITTEN));
+        report.push('\n');
+
+        let mut start = 0;
+        while start < MEMORY_SIZE {
+            let flags = self.flags[start];
+            let end = self.flags[start..]
+                .iter()
+                .position(|&other| other != flags)
+                .map_or(MEMORY_SIZE, |len| start + len);
+
+            if flags != 0 {
+                let _ = writeln!(report, "{start:03X}-{:03X} {}", end - 1, describe(flags));
+            }
+            start = end;
+        }
+
+        report
+    }
+}
+
+impl Default for Coverage {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+pub fn describe(flags: u8) -> String {
+    [(EXECUTED, "code"), (READ, "read"), (WRITTEN, "written")]
+        .into_iter()
+        .filter(|&(flag, _)| flags & flag != 0)
+        .map(|(_, name)| name)
+        .collect::<Vec<_>>()
+        .join("+")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+    use crate::{chip8::Chip8, key_matrix::KeyMatrix, memory::ROM_START_ADDR};
+
+    /// LD I, 0x300; LD V0, 42; LD B, V0; LD V2, [I]
+    const ROM: [u8; 8] = [0xA3, 0x00, 0x60, 0x2A, 0xF0, 0x33, 0xF2, 0x65];
+
+    fn run(recording: bool) -> Chip8 {
+        let mut chip8 = Chip8::new(Arc::new(Mutex::new(KeyMatrix::new())));
+        for (offset, &byte) in ROM.iter().enumerate() {
+            chip8
+                .write_memory(ROM_START_ADDR + offset as u16, byte)
+                .unwrap();
+        }
+
+        chip8.set_recording_coverage(recording);
+        for _ in 0..ROM.len() / 2 {
+            chip8.tick().unwrap();
+        }
+        chip8
+    }
+
+    #[test]
+    fn marks_executed_read_and_written_bytes() {
+        let coverage = run(true).debug_snapshot().coverage;
+
+        assert!((0x200..0x208).all(|addr| coverage.is_executed(addr)));
+        assert!(!coverage.is_executed(0x208));
+
+        // BCD wrote the digits, then they were loaded back
+        for addr in 0x300..=0x302 {
+            assert_eq!(coverage.flags(addr), READ | WRITTEN);
+            assert!(coverage.is_data(addr));
+        }
+        assert_eq!(coverage.flags(0x303), 0);
+        assert!(!coverage.is_data(0x200));
+
+        assert_eq!(
+            coverage.report(),
+            "executed 8 bytes\n\
+             read     3 bytes\n\
+             written  3 bytes\n\
+             \n\
+             200-207 code\n\
+             300-302 read+written\n"
+        );
+    }
+
+    #[test]
+    fn reset_clears_what_was_recorded() {
+        let mut chip8 = run(true);
+        chip8.reset_coverage();
+
+        let coverage = chip8.debug_snapshot().coverage;
+        assert_eq!(coverage.count(EXECUTED | READ | WRITTEN), 0);
+        assert_eq!(coverage.report().lines().count(), 4);
+    }
+
+    #[test]
+    fn nothing_is_marked_unless_recording() {
+        let coverage = run(false).debug_snapshot().coverage;
+        assert_eq!(coverage.count(EXECUTED | READ | WRITTEN), 0);
+    }
+}
@@ -1,4 +1,9 @@
-use std::sync::{Arc, Mutex};
+use std::{
+    collections::VecDeque,
+    fmt,
+    str::FromStr,
+    sync::{Arc, Mutex},
+};
 
 use serde::{Deserialize, Serialize};
 
@@ -6,8 +11,70 @@ use crate::error::{Chip8Error, Result};
 use crate::frame_buffer::FrameBuffer;
 use crate::instruction::Instruction;
 use crate::key_matrix::{Chip8Key, KeyMatrix};
+use crate::key_wait::{KeyEvent, KeyWait, KeyWaitMode};
 use crate::memory::{FONT_START_ADDR, Memory, ROM_START_ADDR};
 
+#[derive(Clone, Copy, PartialEq)]
+pub enum Register {
+    V(u8),
+    I,
+    Pc,
+    Sp,
+    Dt,
+    St,
+}
+
+impl Register {
+    pub const ALL: [Register; 21] = [
+        Register::V(0x0),
+        Register::V(0x1),
+        Register::V(0x2),
+        Register::V(0x3),
+        Register::V(0x4),
+        Register::V(0x5),
+        Register::V(0x6),
+        Register::V(0x7),
+        Register::V(0x8),
+        Register::V(0x9),
+        Register::V(0xA),
+        Register::V(0xB),
+        Register::V(0xC),
+        Register::V(0xD),
+        Register::V(0xE),
+        Register::V(0xF),
+        Register::I,
+        Register::Pc,
+        Register::Sp,
+        Register::Dt,
+        Register::St,
+    ];
+}
+
+impl fmt::Display for Register {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Register::V(x) => write!(f, "V{x:X}"),
+            Register::I => write!(f, "I"),
+            Register::Pc => write!(f, "PC"),
+            Register::Sp => write!(f, "SP"),
+            Register::Dt => write!(f, "DT"),
+            Register::St => write!(f, "ST"),
+        }
+    }
+}
+
+/// Case-insensitive register names: `v0`..`vf`, `i`, `pc`, `sp`, `dt`, `st`.
+impl FromStr for Register {
+    type Err = ();
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        Register::ALL
+            .into_iter()
+            .find(|register| register.to_string().eq_ignore_ascii_case(s))
+            .ok_or(())
+    }
+}
+
 #[derive(Serialize, Deserialize, Clone)]
 pub struct Cpu {
     v: [u8; 16],
@@ -17,6 +84,7 @@ pub struct Cpu {
     dt: u8,
     st: u8,
     stack: [u16; 16],
+    key_wait: KeyWait,
 }
 
 impl Default for Cpu {
@@ -35,6 +103,58 @@ impl Cpu {
             sp: 0,
             dt: 0,
             st: 0,
+            key_wait: KeyWait::Idle,
+        }
+    }
+
+    pub fn pc(&self) -> u16 {
+        self.pc
+    }
+
+    pub fn i(&self) -> u16 {
+        self.i
+    }
+
+    pub fn v(&self) -> &[u8; 16] {
+        &self.v
+    }
+
+    pub fn sp(&self) -> u8 {
+        self.sp
+    }
+
+    pub fn dt(&self) -> u8 {
+        self.dt
+    }
+
+    pub fn st(&self) -> u8 {
+        self.st
+    }
+
+    pub fn stack(&self) -> &[u16; 16] {
+        &self.stack
+    }
+
+    pub fn register(&self, register: Register) -> u16 {
+        match register {
+            Register::V(x) => self.v[x as usize] as u16,
+            Register::I => self.i,
+            Register::Pc => self.pc,
+            Register::Sp => self.sp as u16,
+            Register::Dt => self.dt as u16,
+            Register::St => self.st as u16,
+        }
+    }
+
+    /// Values wider than the register are truncated.
+    pub fn set_register(&mut self, register: Register, value: u16) {
+        match register {
+            Register::V(x) => self.v[x as usize] = value as u8,
+            Register::I => self.i = value,
+            Register::Pc => self.pc = value,
+            Register::Sp => self.sp = value as u8,
+            Register::Dt => self.dt = value as u8,
+            Register::St => self.st = value as u8,
         }
     }
 
@@ -51,9 +171,10 @@ impl Cpu {
     pub fn tick(
         &mut self,
         memory: &mut Memory,
-        frame_buffer: Arc<Mutex<FrameBuffer>>,
+        frame_buffer: &mut FrameBuffer,
         key_matrix: Arc<Mutex<KeyMatrix>>,
-        last_released_key: Option<Chip8Key>,
+        key_events: &mut VecDeque<KeyEvent>,
+        key_wait_mode: KeyWaitMode,
     ) -> Result<bool> {
         let instruction = self.get_next_instruction(memory)?;
         self.execute(
@@ -61,7 +182,8 @@ impl Cpu {
             memory,
             frame_buffer,
             key_matrix,
-            last_released_key,
+            key_events,
+            key_wait_mode,
         )
     }
 
@@ -69,16 +191,14 @@ impl Cpu {
         &mut self,
         instruction: Instruction,
         memory: &mut Memory,
-        frame_buffer: Arc<Mutex<FrameBuffer>>,
+        frame_buffer: &mut FrameBuffer,
         key_matrix: Arc<Mutex<KeyMatrix>>,
-        last_released_key: Option<Chip8Key>,
+        key_events: &mut VecDeque<KeyEvent>,
+        key_wait_mode: KeyWaitMode,
     ) -> Result<bool> {
         match instruction {
             Instruction::Cls => {
-                {
-                    let mut frame_buffer = frame_buffer.lock().unwrap();
-                    *frame_buffer = FrameBuffer::new();
-                }
+                frame_buffer.clear();
 
                 return Ok(true);
             }
@@ -112,34 +232,18 @@ impl Cpu {
                 self.v[vx as usize] = self.v[vx as usize].wrapping_add(byte)
             }
             Instruction::Drw { vx, vy, nibble } => {
-                let mut sprite_buffer = Vec::with_capacity(nibble as usize);
+                let mut sprite = [0; 15];
 
-                for offset in 0..nibble {
-                    let addr = self.i + offset as u16;
-                    sprite_buffer.push(memory.read(addr)?);
+                for (offset, row) in sprite[..nibble as usize].iter_mut().enumerate() {
+                    *row = memory.read(self.i + offset as u16)?;
                 }
 
                 let x = self.v[vx as usize] % 64;
                 let y = self.v[vy as usize] % 32;
 
-                self.v[0xF] = 0;
-
-                for cy in y..(y + nibble).min(32) {
-                    for cx in x..(x + 8).min(64) {
-                        let byte_index = cy - y;
-                        let bit_offset = cx - x;
-
-                        let turned_off = frame_buffer.lock().unwrap().xor(
-                            cx as usize,
-                            cy as usize,
-                            ((sprite_buffer[byte_index as usize] >> (7 - bit_offset)) & 1) == 1,
-                        );
-
-                        if turned_off {
-                            self.v[0xF] = 1;
-                        }
-                    }
-                }
+                let collision =
+                    frame_buffer.draw_sprite(x as usize, y as usize, &sprite[..nibble as usize]);
+                self.v[0xF] = collision as u8;
 
                 return Ok(true);
             }
@@ -222,14 +326,15 @@ impl Cpu {
                     self.v[0xF] = 0
                 }
             }
-            Instruction::KeyWait { vx } => match last_released_key {
-                Some(key) => {
-                    self.v[vx as usize] = key.into();
-                }
-                _ => {
-                    self.pc -= 2;
+            Instruction::KeyWait { vx } => {
+                let key_matrix = key_matrix.lock().unwrap();
+
+                match self.wait_for_key(&key_matrix, key_events, key_wait_mode) {
+                    Some(key) => self.v[vx as usize] = key.into(),
+                    // Run FX0A again next tick; timers keep counting meanwhile
+                    None => self.pc -= 2,
                 }
-            },
+            }
             Instruction::Store { vx } => {
                 let i = self.i;
 
@@ -290,17 +395,207 @@ impl Cpu {
                 println!("unknown instruction: 0x{instruction:4X}");
             }
         }
-        Ok(true)
+        Ok(false)
+    }
+
+    pub fn key_wait(&self) -> KeyWait {
+        self.key_wait
+    }
+
+    /// Advances an `FX0A` wait, returning the key once it's done.
+    ///
+    /// Queued events are looked at first so a tap between two ticks still counts, then
+    /// the key matrix, so a key that is already held counts as pressed.
+    fn wait_for_key(
+        &mut self,
+        key_matrix: &KeyMatrix,
+        key_events: &mut VecDeque<KeyEvent>,
+        mode: KeyWaitMode,
+    ) -> Option<Chip8Key> {
+        if let KeyWait::Idle | KeyWait::Press = self.key_wait {
+            let mut pressed = None;
+            while let Some(event) = key_events.pop_front() {
+                if let KeyEvent::Press(key) = event {
+                    pressed = Some(key);
+                    break;
+                }
+            }
+
+            match pressed.or_else(|| key_matrix.first_pressed()) {
+                Some(key) if mode == KeyWaitMode::Press => {
+                    self.key_wait = KeyWait::Idle;
+                    return Some(key);
+                }
+                Some(key) => self.key_wait = KeyWait::Release(key),
+                None => {
+                    self.key_wait = KeyWait::Press;
+                    return None;
+                }
+            }
+        }
+
+        let KeyWait::Release(key) = self.key_wait else {
+            return None;
+        };
+
+        let released = key_events
+            .iter()
+            .any(|&event| event == KeyEvent::Release(key))
+            || !key_matrix.is_pressed(key);
+
+        if released {
+            key_events.clear();
+            self.key_wait = KeyWait::Idle;
+            Some(key)
+        } else {
+            if mode == KeyWaitMode::Vip {
+                self.st = self.st.max(1);
+            }
+            None
+        }
+    }
+
+    pub fn peek_instruction(&self, memory: &Memory) -> Result<Instruction> {
+        memory.instruction(self.pc)
     }
 
     fn get_next_instruction(&mut self, memory: &Memory) -> Result<Instruction> {
-        let msb = memory.read(self.pc)?;
-        let lsb = memory.read(self.pc + 1)?;
+        let instruction = self.peek_instruction(memory)?;
 
         self.pc += 2;
 
-        let opcode = ((msb as u16) << 8) | lsb as u16;
+        Ok(instruction)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chip8_state::Chip8State;
+
+    /// A CPU sitting on `F30A`, with its own keypad and event queue.
+    struct Fixture {
+        cpu: Cpu,
+        memory: Memory,
+        frame_buffer: FrameBuffer,
+        key_matrix: Arc<Mutex<KeyMatrix>>,
+        key_events: VecDeque<KeyEvent>,
+    }
+
+    impl Fixture {
+        fn new() -> Self {
+            let mut memory = Memory::new();
+            memory.load_rom(&[0xF3, 0x0A]);
+
+            Self {
+                cpu: Cpu::new(),
+                memory,
+                frame_buffer: FrameBuffer::new(),
+                key_matrix: Arc::new(Mutex::new(KeyMatrix::new())),
+                key_events: VecDeque::new(),
+            }
+        }
+
+        /// Runs one tick, returning V3 if the wait finished.
+        fn step(&mut self, mode: KeyWaitMode) -> Option<u8> {
+            self.cpu
+                .tick(
+                    &mut self.memory,
+                    &mut self.frame_buffer,
+                    self.key_matrix.clone(),
+                    &mut self.key_events,
+                    mode,
+                )
+                .unwrap();
+
+            (self.cpu.pc() != ROM_START_ADDR).then_some(self.cpu.v()[3])
+        }
+    }
+
+    #[test]
+    fn tap_within_one_frame_is_not_lost() {
+        let mut fixture = Fixture::new();
+        assert_eq!(fixture.step(KeyWaitMode::Release), None);
+
+        // Down and up again between two ticks; the matrix never shows it
+        fixture.key_events.push_back(KeyEvent::Press(Chip8Key::K7));
+        fixture
+            .key_events
+            .push_back(KeyEvent::Release(Chip8Key::K7));
+
+        assert_eq!(fixture.step(KeyWaitMode::Release), Some(7));
+        assert!(fixture.cpu.key_wait() == KeyWait::Idle);
+        assert!(fixture.key_events.is_empty());
+    }
+
+    #[test]
+    fn key_held_before_the_wait_counts_once_released() {
+        let mut fixture = Fixture::new();
+        fixture.key_matrix.lock().unwrap().press(Chip8Key::KA);
+
+        assert_eq!(fixture.step(KeyWaitMode::Release), None);
+        assert!(fixture.cpu.key_wait() == KeyWait::Release(Chip8Key::KA));
+        assert_eq!(fixture.step(KeyWaitMode::Release), None);
+
+        fixture.key_matrix.lock().unwrap().release(Chip8Key::KA);
+        assert_eq!(fixture.step(KeyWaitMode::Release), Some(0xA));
+    }
+
+    #[test]
+    fn press_mode_finishes_on_the_press() {
+        let mut fixture = Fixture::new();
+        fixture.key_events.push_back(KeyEvent::Press(Chip8Key::K2));
+
+        assert_eq!(fixture.step(KeyWaitMode::Press), Some(2));
+        assert!(fixture.cpu.key_wait() == KeyWait::Idle);
+    }
+
+    #[test]
+    fn vip_mode_sounds_while_held() {
+        let mut fixture = Fixture::new();
+        fixture.key_matrix.lock().unwrap().press(Chip8Key::K5);
+
+        for _ in 0..3 {
+            assert_eq!(fixture.step(KeyWaitMode::Vip), None);
+            assert!(fixture.cpu.st() > 0);
+            fixture.cpu.tick_60hz();
+        }
+
+        fixture.key_matrix.lock().unwrap().release(Chip8Key::K5);
+        assert_eq!(fixture.step(KeyWaitMode::Vip), Some(5));
+        assert_eq!(fixture.cpu.st(), 0);
+
+        // The other modes stay silent
+        let mut fixture = Fixture::new();
+        fixture.key_matrix.lock().unwrap().press(Chip8Key::K5);
+        assert_eq!(fixture.step(KeyWaitMode::Release), None);
+        assert_eq!(fixture.cpu.st(), 0);
+    }
 
-        Ok(opcode.into())
+    #[test]
+    fn wait_survives_a_save_state() {
+        let mut fixture = Fixture::new();
+        fixture.key_matrix.lock().unwrap().press(Chip8Key::KC);
+        assert_eq!(fixture.step(KeyWaitMode::Release), None);
+
+        let state = Chip8State {
+            cpu: fixture.cpu.clone(),
+            memory: fixture.memory.clone(),
+            frame_buffer: FrameBuffer::new(),
+            key_matrix: KeyMatrix::new(),
+        };
+        let mut bytes = Vec::new();
+        state.write_to(&mut bytes).unwrap();
+        let state = Chip8State::read_from(bytes.as_slice()).unwrap();
+
+        fixture.cpu = state.cpu;
+        assert!(fixture.cpu.key_wait() == KeyWait::Release(Chip8Key::KC));
+        // Still held, so still waiting
+        assert_eq!(fixture.step(KeyWaitMode::Release), None);
+
+        fixture
+            .key_events
+            .push_back(KeyEvent::Release(Chip8Key::KC));
+        assert_eq!(fixture.step(KeyWaitMode::Release), Some(0xC));
     }
 }
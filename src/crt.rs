@@ -0,0 +1,242 @@
+use std::f32::consts::PI;
+
+/// Output pixels per emulated pixel when any effect is enabled.
+pub const CRT_SCALE: usize = 6;
+
+#[derive(Clone, Copy, PartialEq)]
+pub struct CrtConfig {
+    pub scanlines: f32,
+    pub bloom: f32,
+    pub curvature: f32,
+    pub vignette: f32,
+}
+
+impl CrtConfig {
+    pub fn is_off(&self) -> bool {
+        *self == CrtPreset::Off.config()
+    }
+}
+
+impl Default for CrtConfig {
+    fn default() -> Self {
+        CrtPreset::Off.config()
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum CrtPreset {
+    Off,
+    Subtle,
+    Arcade,
+    Heavy,
+}
+
+impl CrtPreset {
+    pub const ALL: [CrtPreset; 4] = [
+        CrtPreset::Off,
+        CrtPreset::Subtle,
+        CrtPreset::Arcade,
+        CrtPreset::Heavy,
+    ];
+
+    pub fn config(self) -> CrtConfig {
+        match self {
+            CrtPreset::Off => CrtConfig {
+                scanlines: 0.0,
+                bloom: 0.0,
+                curvature: 0.0,
+                vignette: 0.0,
+            },
+            CrtPreset::Subtle => CrtConfig {
+                scanlines: 0.25,
+                bloom: 0.15,
+                curvature: 0.0,
+                vignette: 0.2,
+            },
+            CrtPreset::Arcade => CrtConfig {
+                scanlines: 0.5,
+                bloom: 0.35,
+                curvature: 0.4,
+                vignette: 0.35,
+            },
+            CrtPreset::Heavy => CrtConfig {
+                scanlines: 0.8,
+                bloom: 0.6,
+                curvature: 0.8,
+                vignette: 0.6,
+            },
+        }
+    }
+
+    pub fn matching(config: &CrtConfig) -> Option<CrtPreset> {
+        Self::ALL
+            .into_iter()
+            .find(|preset| preset.config() == *config)
+    }
+}
+
+impl From<CrtPreset> for &'static str {
+    fn from(preset: CrtPreset) -> Self {
+        match preset {
+            CrtPreset::Off => "Off",
+            CrtPreset::Subtle => "Subtle",
+            CrtPreset::Arcade => "Arcade",
+            CrtPreset::Heavy => "Heavy",
+        }
+    }
+}
+
+/// Upscales `pixels` (`width` x `height` RGB) by `CRT_SCALE` and applies the configured effects.
+pub fn apply(config: &CrtConfig, pixels: &[[u8; 3]], width: usize, height: usize) -> Vec<u8> {
+    let out_width = width * CRT_SCALE;
+    let out_height = height * CRT_SCALE;
+
+    let glow = blur(&blur(pixels, width, height), width, height);
+
+    let mut out = Vec::with_capacity(out_width * out_height * 3);
+
+    for oy in 0..out_height {
+        for ox in 0..out_width {
+            // Normalized screen position in [-1, 1], bent outwards for curvature
+            let u = (ox as f32 + 0.5) / out_width as f32 * 2.0 - 1.0;
+            let v = (oy as f32 + 0.5) / out_height as f32 * 2.0 - 1.0;
+
+            let bend = 1.0 + config.curvature * 0.2 * (u * u + v * v);
+            let (u, v) = (u * bend, v * bend);
+
+            if u.abs() > 1.0 || v.abs() > 1.0 {
+                out.extend([0, 0, 0]);
+                continue;
+            }
+
+            let sx = (u + 1.0) * 0.5 * width as f32;
+            let sy = (v + 1.0) * 0.5 * height as f32;
+
+            let x = (sx as usize).min(width - 1);
+            let y = (sy as usize).min(height - 1);
+
+            let base = pixels[y * width + x].map(f32::from);
+            let halo = sample(&glow, width, height, sx - 0.5, sy - 0.5);
+
+            let scanline = 1.0 - config.scanlines * (1.0 - (sy.fract() * PI).sin());
+            let vignette = 1.0 - config.vignette * (u * u + v * v) * 0.5;
+            let shade = scanline * vignette;
+
+            for channel in 0..3 {
+                let value = (base[channel] + halo[channel] * config.bloom) * shade;
+                out.push(value.clamp(0.0, 255.0) as u8);
+            }
+        }
+    }
+
+    out
+}
+
+fn blur(pixels: &[[u8; 3]], width: usize, height: usize) -> Vec<[u8; 3]> {
+    let mut out = Vec::with_capacity(pixels.len());
+
+    for y in 0..height {
+        for x in 0..width {
+            let mut sum = [0u32; 3];
+            let mut count = 0;
+
+            for ny in y.saturating_sub(1)..(y + 2).min(height) {
+                for nx in x.saturating_sub(1)..(x + 2).min(width) {
+                    let pixel = pixels[ny * width + nx];
+                    for channel in 0..3 {
+                        sum[channel] += pixel[channel] as u32;
+                    }
+                    count += 1;
+                }
+            }
+
+            out.push(sum.map(|total| (total / count) as u8));
+        }
+    }
+
+    out
+}
+
+fn sample(pixels: &[[u8; 3]], width: usize, height: usize, x: f32, y: f32) -> [f32; 3] {
+    let x = x.clamp(0.0, (width - 1) as f32);
+    let y = y.clamp(0.0, (height - 1) as f32);
+
+    let (x0, y0) = (x as usize, y as usize);
+    let (x1, y1) = ((x0 + 1).min(width - 1), (y0 + 1).min(height - 1));
+    let (fx, fy) = (x.fract(), y.fract());
+
+    let at = |x: usize, y: usize| pixels[y * width + x].map(f32::from);
+    let lerp = |a: [f32; 3], b: [f32; 3], t: f32| [0, 1, 2].map(|c| a[c] + (b[c] - a[c]) * t);
+
+    lerp(
+        lerp(at(x0, y0), at(x1, y0), fx),
+        lerp(at(x0, y1), at(x1, y1), fx),
+        fy,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WIDTH: usize = 8;
+    const HEIGHT: usize = 4;
+
+    /// A different color for every pixel.
+    fn pixels() -> Vec<[u8; 3]> {
+        (0..WIDTH * HEIGHT)
+            .map(|index| {
+                [
+                    index as u8 * 8,
+                    255 - index as u8 * 4,
+                    (index % 3) as u8 * 100,
+                ]
+            })
+            .collect()
+    }
+
+    fn output_pixel(out: &[u8], x: usize, y: usize) -> [u8; 3] {
+        let offset = (y * WIDTH * CRT_SCALE + x) * 3;
+        [out[offset], out[offset + 1], out[offset + 2]]
+    }
+
+    #[test]
+    fn scales_up_by_crt_scale() {
+        for preset in CrtPreset::ALL {
+            let out = apply(&preset.config(), &pixels(), WIDTH, HEIGHT);
+            assert_eq!(out.len(), WIDTH * CRT_SCALE * HEIGHT * CRT_SCALE * 3);
+        }
+    }
+
+    #[test]
+    fn off_only_scales_up() {
+        let config = CrtConfig::default();
+        assert!(config.is_off());
+
+        let pixels = pixels();
+        let out = apply(&config, &pixels, WIDTH, HEIGHT);
+
+        for y in 0..HEIGHT * CRT_SCALE {
+            for x in 0..WIDTH * CRT_SCALE {
+                let source = pixels[y / CRT_SCALE * WIDTH + x / CRT_SCALE];
+                assert_eq!(output_pixel(&out, x, y), source, "at {x},{y}");
+            }
+        }
+    }
+
+    #[test]
+    fn effects_change_the_picture() {
+        let pixels = vec![[200; 3]; WIDTH * HEIGHT];
+        let config = CrtPreset::Heavy.config();
+        assert!(!config.is_off());
+
+        let out = apply(&config, &pixels, WIDTH, HEIGHT);
+
+        // Curvature pushes the corners off the screen
+        assert_eq!(output_pixel(&out, 0, 0), [0; 3]);
+        // Scanlines darken the edges of each row
+        let center = output_pixel(&out, WIDTH * CRT_SCALE / 2, CRT_SCALE * 3 / 2);
+        let edge = output_pixel(&out, WIDTH * CRT_SCALE / 2, CRT_SCALE);
+        assert!(edge[0] < center[0]);
+    }
+}
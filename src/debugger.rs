@@ -0,0 +1,388 @@
+use std::{collections::HashMap, fmt};
+
+use crate::{
+    condition::Condition,
+    cpu::{Cpu, Register},
+    memory::{AccessKind, Memory, MemoryAccess},
+};
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum WatchKind {
+    Read,
+    Write,
+    ReadWrite,
+}
+
+impl WatchKind {
+    pub const ALL: [WatchKind; 3] = [WatchKind::Read, WatchKind::Write, WatchKind::ReadWrite];
+
+    fn matches(self, kind: AccessKind) -> bool {
+        match self {
+            WatchKind::Read => kind == AccessKind::Read,
+            WatchKind::Write => kind == AccessKind::Write,
+            WatchKind::ReadWrite => true,
+        }
+    }
+}
+
+impl From<WatchKind> for &'static str {
+    fn from(kind: WatchKind) -> Self {
+        match kind {
+            WatchKind::Read => "read",
+            WatchKind::Write => "write",
+            WatchKind::ReadWrite => "read/write",
+        }
+    }
+}
+
+/// Stops when an instruction reads or writes any byte in `start..=end`.
+#[derive(Clone, Copy)]
+pub struct Watchpoint {
+    pub start: u16,
+    pub end: u16,
+    pub kind: WatchKind,
+}
+
+impl fmt::Display for Watchpoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {:03X}..={:03X}",
+            <&'static str>::from(self.kind),
+            self.start,
+            self.end
+        )
+    }
+}
+
+#[derive(Clone, Copy)]
+pub enum RegisterWatch {
+    Changed(Register),
+    OutOfRange {
+        register: Register,
+        min: u16,
+        max: u16,
+    },
+}
+
+impl fmt::Display for RegisterWatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RegisterWatch::Changed(register) => write!(f, "{register} changes"),
+            RegisterWatch::OutOfRange { register, min, max } => {
+                write!(f, "{register} outside {min:03X}..={max:03X}")
+            }
+        }
+    }
+}
+
+pub enum BreakReason {
+    Breakpoint(u16),
+    RunTo(u16),
+    Condition {
+        pc: u16,
+        condition: String,
+    },
+    Watchpoint {
+        pc: u16,
+        addr: u16,
+        kind: AccessKind,
+    },
+    Register {
+        pc: u16,
+        watch: String,
+        value: u16,
+    },
+}
+
+impl BreakReason {
+    /// The instruction execution stopped at or because of.
+    pub fn pc(&self) -> u16 {
+        match *self {
+            BreakReason::Breakpoint(pc)
+            | BreakReason::RunTo(pc)
+            | BreakReason::Condition { pc, .. }
+            | BreakReason::Watchpoint { pc, .. }
+            | BreakReason::Register { pc, .. } => pc,
+        }
+    }
+}
+
+impl fmt::Display for BreakReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BreakReason::Breakpoint(pc) => write!(f, "breakpoint at {pc:03X}"),
+            BreakReason::RunTo(pc) => write!(f, "reached {pc:03X}"),
+            BreakReason::Condition { pc, condition } => {
+                write!(f, "`{condition}` at {pc:03X}")
+            }
+            BreakReason::Watchpoint { pc, addr, kind } => {
+                let action = match kind {
+                    AccessKind::Read => "read",
+                    AccessKind::Write => "write",
+                };
+                write!(f, "{action} of {addr:03X} by instruction at {pc:03X}")
+            }
+            BreakReason::Register { pc, watch, value } => {
+                write!(f, "{watch} ({value:03X}) after instruction at {pc:03X}")
+            }
+        }
+    }
+}
+
+/// Breakpoints and watchpoints, evaluated by the emulator thread around every instruction.
+#[derive(Clone, Default)]
+pub struct Debugger {
+    /// Each breakpoint stops only while its condition, if it has one, holds.
+    breakpoints: HashMap<u16, Option<Condition>>,
+    watchpoints: Vec<Watchpoint>,
+    register_watches: Vec<RegisterWatch>,
+    /// Conditions checked before every instruction, wherever it is.
+    conditions: Vec<Condition>,
+
+    run_to: Option<u16>,
+    stopped_at: Option<u16>,
+}
+
+impl Debugger {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn breakpoints(&self) -> &HashMap<u16, Option<Condition>> {
+        &self.breakpoints
+    }
+
+    pub fn watchpoints(&self) -> &[Watchpoint] {
+        &self.watchpoints
+    }
+
+    pub fn register_watches(&self) -> &[RegisterWatch] {
+        &self.register_watches
+    }
+
+    pub fn conditions(&self) -> &[Condition] {
+        &self.conditions
+    }
+
+    pub fn toggle_breakpoint(&mut self, addr: u16) {
+        if self.breakpoints.remove(&addr).is_none() {
+            self.breakpoints.insert(addr, None);
+        }
+    }
+
+    /// Adds an unconditional breakpoint, keeping the condition of one already at `addr`.
+    pub fn add_breakpoint(&mut self, addr: u16) {
+        self.breakpoints.entry(addr).or_insert(None);
+    }
+
+    /// Adds a breakpoint, or replaces the condition of the one at `addr`.
+    pub fn set_breakpoint(&mut self, addr: u16, condition: Option<Condition>) {
+        self.breakpoints.insert(addr, condition);
+    }
+
+    pub fn remove_breakpoint(&mut self, addr: u16) {
+        self.breakpoints.remove(&addr);
+    }
+
+    pub fn add_watchpoint(&mut self, watchpoint: Watchpoint) {
+        self.watchpoints.push(watchpoint);
+    }
+
+    pub fn remove_watchpoint(&mut self, index: usize) {
+        if index < self.watchpoints.len() {
+            self.watchpoints.remove(index);
+        }
+    }
+
+    pub fn add_register_watch(&mut self, watch: RegisterWatch) {
+        self.register_watches.push(watch);
+    }
+
+    pub fn remove_register_watch(&mut self, index: usize) {
+        if index < self.register_watches.len() {
+            self.register_watches.remove(index);
+        }
+    }
+
+    pub fn add_condition(&mut self, condition: Condition) {
+        self.conditions.push(condition);
+    }
+
+    pub fn remove_condition(&mut self, index: usize) {
+        if index < self.conditions.len() {
+            self.conditions.remove(index);
+        }
+    }
+
+    pub fn run_to(&mut self, addr: u16) {
+        self.run_to = Some(addr);
+    }
+
+    /// Whether `after_tick` needs a copy of the registers from before the instruction.
+    pub fn watches_registers(&self) -> bool {
+        !self.register_watches.is_empty()
+    }
+
+    pub fn before_tick(&mut self, cpu: &Cpu, memory: &Memory) -> Option<BreakReason> {
+        let pc = cpu.pc();
+
+        // The instruction we last stopped on gets to run once execution resumes;
+        // `after_tick` re-arms the check, so a loop that jumps to itself stops again
+        if self.stopped_at == Some(pc) {
+            return None;
+        }
+
+        let breakpoint = self.breakpoints.get(&pc);
+
+        let reason = if self.run_to == Some(pc) {
+            self.run_to = None;
+            BreakReason::RunTo(pc)
+        } else if let Some(None) = breakpoint {
+            BreakReason::Breakpoint(pc)
+        } else if let Some(Some(condition)) = breakpoint
+            && condition.is_met(cpu, memory)
+        {
+            BreakReason::Condition {
+                pc,
+                condition: condition.to_string(),
+            }
+        } else {
+            let condition = self
+                .conditions
+                .iter()
+                .find(|condition| condition.is_met(cpu, memory))?;
+
+            BreakReason::Condition {
+                pc,
+                condition: condition.to_string(),
+            }
+        };
+
+        self.stopped_at = Some(pc);
+        Some(reason)
+    }
+
+    pub fn after_tick(
+        &mut self,
+        pc: u16,
+        accesses: &[MemoryAccess],
+        before: Option<&Cpu>,
+        after: &Cpu,
+    ) -> Option<BreakReason> {
+        self.stopped_at = None;
+
+        for access in accesses {
+            for watchpoint in &self.watchpoints {
+                if watchpoint.kind.matches(access.kind)
+                    && access.overlaps(watchpoint.start, watchpoint.end)
+                {
+                    return Some(BreakReason::Watchpoint {
+                        pc,
+                        addr: access.addr.max(watchpoint.start),
+                        kind: access.kind,
+                    });
+                }
+            }
+        }
+
+        let before = before?;
+
+        self.register_watches.iter().find_map(|watch| {
+            let (register, triggered) = match *watch {
+                RegisterWatch::Changed(register) => (
+                    register,
+                    before.register(register) != after.register(register),
+                ),
+                RegisterWatch::OutOfRange { register, min, max } => {
+                    let value = after.register(register);
+                    (
+                        register,
+                        before.register(register) != value && !(min..=max).contains(&value),
+                    )
+                }
+            };
+
+            triggered.then(|| BreakReason::Register {
+                pc,
+                watch: watch.to_string(),
+                value: after.register(register),
+            })
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn breakpoint_on_a_jump_to_itself_fires_every_time() {
+        let (cpu, memory) = (Cpu::new(), Memory::new());
+        let mut debugger = Debugger::new();
+        debugger.add_breakpoint(cpu.pc());
+
+        assert!(debugger.before_tick(&cpu, &memory).is_some());
+        // Resuming runs the instruction once...
+        assert!(debugger.before_tick(&cpu, &memory).is_none());
+        assert!(debugger.after_tick(cpu.pc(), &[], None, &cpu).is_none());
+        // ...and the PC is back on the breakpoint
+        assert!(debugger.before_tick(&cpu, &memory).is_some());
+    }
+
+    #[test]
+    fn conditional_breakpoint_stops_only_when_met() {
+        let mut cpu = Cpu::new();
+        let memory = Memory::new();
+        let mut debugger = Debugger::new();
+        debugger.set_breakpoint(cpu.pc(), Some("v0 > 5".parse().unwrap()));
+
+        assert!(debugger.before_tick(&cpu, &memory).is_none());
+
+        cpu.set_register(Register::V(0), 6);
+        let reason = debugger.before_tick(&cpu, &memory);
+        assert!(matches!(
+            reason,
+            Some(BreakReason::Condition { pc: 0x200, ref condition }) if condition == "v0 > 5"
+        ));
+
+        // Only at its own address
+        cpu.set_register(Register::Pc, 0x202);
+        assert!(debugger.before_tick(&cpu, &memory).is_none());
+    }
+
+    #[test]
+    fn watchpoint_sees_recorded_accesses() {
+        let cpu = Cpu::new();
+        let mut memory = Memory::new();
+        memory.set_recording(true);
+        let mut debugger = Debugger::new();
+        debugger.add_watchpoint(Watchpoint {
+            start: 0x302,
+            end: 0x302,
+            kind: WatchKind::Write,
+        });
+
+        memory.clear_accesses();
+        let _ = memory.read(0x302);
+        assert!(
+            debugger
+                .after_tick(0x200, &memory.accesses(), None, &cpu)
+                .is_none()
+        );
+
+        memory.clear_accesses();
+        for addr in 0x300..0x303 {
+            let _ = memory.write(addr, 0);
+        }
+        let reason = debugger.after_tick(0x200, &memory.accesses(), None, &cpu);
+        assert!(matches!(
+            reason,
+            Some(BreakReason::Watchpoint {
+                addr: 0x302,
+                kind: AccessKind::Write,
+                ..
+            })
+        ));
+    }
+}
@@ -0,0 +1,296 @@
+use eframe::egui::{self, Ui};
+
+use crate::{
+    Message,
+    chip8::DebugSnapshot,
+    condition::Condition,
+    cpu::Register,
+    debugger::{BreakReason, RegisterWatch, WatchKind, Watchpoint},
+    handle::Chip8Handle,
+    memory::parse_addr,
+};
+
+pub struct DebuggerView {
+    pub open: bool,
+    pub last_break: Option<BreakReason>,
+    pub error: Option<String>,
+
+    breakpoint_text: String,
+    breakpoint_condition: String,
+
+    watch_start: String,
+    watch_end: String,
+    watch_kind: WatchKind,
+
+    register: Register,
+    out_of_range: bool,
+    range_min: String,
+    range_max: String,
+
+    condition_text: String,
+}
+
+impl DebuggerView {
+    pub fn new() -> Self {
+        Self {
+            open: false,
+            last_break: None,
+            breakpoint_text: String::new(),
+            breakpoint_condition: String::new(),
+            watch_start: String::new(),
+            watch_end: String::new(),
+            watch_kind: WatchKind::Write,
+            register: Register::V(0),
+            out_of_range: false,
+            range_min: String::new(),
+            range_max: String::new(),
+            condition_text: String::new(),
+            error: None,
+        }
+    }
+
+    pub fn ui(&mut self, ui: &mut Ui, snapshot: &DebugSnapshot, handle: &Chip8Handle) {
+        let debugger = &snapshot.debugger;
+        let symbols = &snapshot.symbols;
+
+        ui.horizontal(|ui| {
+            if snapshot.paused {
+                if ui.button("Resume").clicked() {
+                    handle.send_unpause_message();
+                }
+                if ui.button("Step").clicked() {
+                    handle.step();
+                }
+            } else if ui.button("Pause").clicked() {
+                handle.send_pause_message();
+            }
+
+            ui.separator();
+            match (&self.last_break, snapshot.paused) {
+                (Some(reason), true) => match symbols.nearest(reason.pc()) {
+                    Some(label) => ui.label(format!("Stopped: {reason} ({label})")),
+                    None => ui.label(format!("Stopped: {reason}")),
+                },
+                (_, true) => ui.label("Paused"),
+                (_, false) => ui.label("Running"),
+            };
+        });
+
+        if let Some(ref error) = self.error {
+            ui.colored_label(ui.visuals().error_fg_color, error);
+        }
+
+        egui::ScrollArea::vertical().show(ui, |ui| {
+            ui.heading("Call stack");
+            let cpu = &snapshot.cpu;
+            ui.monospace(format!(
+                "{:03X} {}",
+                cpu.pc(),
+                symbols.nearest(cpu.pc()).unwrap_or_default()
+            ));
+            // Return addresses, innermost first
+            for &ret in cpu.stack()[..(cpu.sp() as usize).min(cpu.stack().len())]
+                .iter()
+                .rev()
+            {
+                ui.monospace(format!(
+                    "{ret:03X} {}",
+                    symbols.nearest(ret).unwrap_or_default()
+                ));
+            }
+            if !symbols.is_empty() {
+                ui.label(egui::RichText::new(format!("{} symbols loaded", symbols.len())).weak());
+            }
+
+            ui.separator();
+            ui.heading("Breakpoints");
+            let mut breakpoints = debugger.breakpoints().iter().collect::<Vec<_>>();
+            breakpoints.sort_by_key(|&(&addr, _)| addr);
+            for (&addr, condition) in breakpoints {
+                ui.horizontal(|ui| {
+                    ui.monospace(format!("{addr:03X}"));
+                    if let Some(name) = symbols.name(addr) {
+                        ui.label(name);
+                    }
+                    if let Some(condition) = condition {
+                        ui.monospace(format!("if {condition}"));
+                    }
+                    if ui.small_button("Remove").clicked() {
+                        handle.toggle_breakpoint(addr);
+                    }
+                });
+            }
+            ui.horizontal(|ui| {
+                ui.add(
+                    egui::TextEdit::singleline(&mut self.breakpoint_text)
+                        .hint_text("2A0 or label")
+                        .desired_width(80.0),
+                );
+                ui.label("if");
+                ui.add(
+                    egui::TextEdit::singleline(&mut self.breakpoint_condition)
+                        .hint_text("always")
+                        .desired_width(120.0),
+                );
+                if ui.button("Add").clicked() {
+                    // Labels first, so ones that look like hex (`add`, `beef`) still work
+                    let addr = symbols
+                        .addr(self.breakpoint_text.trim())
+                        .or_else(|| parse_addr(&self.breakpoint_text));
+                    let condition = match self.breakpoint_condition.trim() {
+                        "" => Ok(None),
+                        text => text.parse::<Condition>().map(Some),
+                    };
+
+                    match (addr, condition) {
+                        (Some(addr), Ok(condition)) => {
+                            handle.send(Message::SetBreakpoint(addr, condition));
+                            self.breakpoint_condition.clear();
+                            self.error = None;
+                        }
+                        (None, _) => self.error = Some("Invalid breakpoint address".to_string()),
+                        (_, Err(error)) => self.error = Some(format!("Invalid condition: {error}")),
+                    }
+                }
+            });
+
+            ui.separator();
+            ui.heading("Memory watchpoints");
+            for (index, watchpoint) in debugger.watchpoints().iter().enumerate() {
+                ui.horizontal(|ui| {
+                    ui.monospace(watchpoint.to_string());
+                    if ui.small_button("Remove").clicked() {
+                        handle.send(Message::RemoveWatchpoint(index));
+                    }
+                });
+            }
+            ui.horizontal(|ui| {
+                egui::ComboBox::from_id_salt("watch kind")
+                    .selected_text(<&'static str>::from(self.watch_kind))
+                    .show_ui(ui, |ui| {
+                        for kind in WatchKind::ALL {
+                            ui.selectable_value(
+                                &mut self.watch_kind,
+                                kind,
+                                <&'static str>::from(kind),
+                            );
+                        }
+                    });
+                ui.add(egui::TextEdit::singleline(&mut self.watch_start).desired_width(40.0));
+                ui.label("..=");
+                ui.add(egui::TextEdit::singleline(&mut self.watch_end).desired_width(40.0));
+                if ui.button("Add").clicked() {
+                    let start = parse_addr(&self.watch_start);
+                    // A single address is fine, the end defaults to the start
+                    let end = if self.watch_end.trim().is_empty() {
+                        start
+                    } else {
+                        parse_addr(&self.watch_end)
+                    };
+
+                    match (start, end) {
+                        (Some(start), Some(end)) if start <= end => {
+                            handle.send(Message::AddWatchpoint(Watchpoint {
+                                start,
+                                end,
+                                kind: self.watch_kind,
+                            }));
+                            self.error = None;
+                        }
+                        _ => self.error = Some("Invalid watchpoint range".to_string()),
+                    }
+                }
+            });
+
+            ui.separator();
+            ui.heading("Register watches");
+            for (index, watch) in debugger.register_watches().iter().enumerate() {
+                ui.horizontal(|ui| {
+                    ui.monospace(watch.to_string());
+                    if ui.small_button("Remove").clicked() {
+                        handle.send(Message::RemoveRegisterWatch(index));
+                    }
+                });
+            }
+            ui.horizontal(|ui| {
+                egui::ComboBox::from_id_salt("watch register")
+                    .selected_text(self.register.to_string())
+                    .width(50.0)
+                    .show_ui(ui, |ui| {
+                        for register in Register::ALL {
+                            ui.selectable_value(&mut self.register, register, register.to_string());
+                        }
+                    });
+                ui.radio_value(&mut self.out_of_range, false, "changes");
+                ui.radio_value(&mut self.out_of_range, true, "outside");
+                ui.add_enabled(
+                    self.out_of_range,
+                    egui::TextEdit::singleline(&mut self.range_min).desired_width(40.0),
+                );
+                ui.label("..=");
+                ui.add_enabled(
+                    self.out_of_range,
+                    egui::TextEdit::singleline(&mut self.range_max).desired_width(40.0),
+                );
+                if ui.button("Add").clicked() {
+                    let watch = if self.out_of_range {
+                        match (parse_addr(&self.range_min), parse_addr(&self.range_max)) {
+                            (Some(min), Some(max)) if min <= max => {
+                                Some(RegisterWatch::OutOfRange {
+                                    register: self.register,
+                                    min,
+                                    max,
+                                })
+                            }
+                            _ => None,
+                        }
+                    } else {
+                        Some(RegisterWatch::Changed(self.register))
+                    };
+
+                    match watch {
+                        Some(watch) => {
+                            handle.send(Message::AddRegisterWatch(watch));
+                            self.error = None;
+                        }
+                        None => self.error = Some("Invalid register range".to_string()),
+                    }
+                }
+            });
+
+            ui.separator();
+            ui.heading("Conditions at any address");
+            for (index, condition) in debugger.conditions().iter().enumerate() {
+                ui.horizontal(|ui| {
+                    ui.monospace(condition.to_string());
+                    if ui.small_button("Remove").clicked() {
+                        handle.send(Message::RemoveCondition(index));
+                    }
+                });
+            }
+            ui.horizontal(|ui| {
+                ui.add(
+                    egui::TextEdit::singleline(&mut self.condition_text)
+                        .hint_text("v0 > 5 && [i] != 0")
+                        .desired_width(200.0),
+                );
+                if ui.button("Add").clicked() {
+                    match self.condition_text.parse::<Condition>() {
+                        Ok(condition) => {
+                            handle.send(Message::AddCondition(condition));
+                            self.condition_text.clear();
+                            self.error = None;
+                        }
+                        Err(error) => self.error = Some(format!("Invalid condition: {error}")),
+                    }
+                }
+            });
+        });
+    }
+}
+
+impl Default for DebuggerView {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,54 @@
+use std::fmt;
+
+use crate::instruction::Instruction;
+
+/// One decoded instruction word.
+pub struct Line {
+    pub addr: u16,
+    pub opcode: u16,
+    pub instruction: Instruction,
+}
+
+impl fmt::Display for Line {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:03X}: {:04X}  {}",
+            self.addr, self.opcode, self.instruction
+        )
+    }
+}
+
+impl Line {
+    /// The same word listed as data, for bytes known not to be code.
+    pub fn as_data(&self) -> String {
+        let [msb, lsb] = self.opcode.to_be_bytes();
+        format!(
+            "{:03X}: {:04X}  DB 0x{msb:02X}, 0x{lsb:02X}",
+            self.addr, self.opcode
+        )
+    }
+}
+
+/// Decodes the two bytes at `addr`, or `None` if they run past the end of `bytes`.
+pub fn decode_at(bytes: &[u8], addr: u16) -> Option<Line> {
+    let msb = *bytes.get(addr as usize)?;
+    let lsb = *bytes.get(addr as usize + 1)?;
+
+    let opcode = ((msb as u16) << 8) | lsb as u16;
+
+    Some(Line {
+        addr,
+        opcode,
+        instruction: opcode.into(),
+    })
+}
+
+/// Linear sweep over `bytes` as if they were loaded at `origin`.
+pub fn disassemble(bytes: &[u8], origin: u16) -> impl Iterator<Item = Line> + '_ {
+    (0..bytes.len() / 2).map(move |index| {
+        let mut line = decode_at(bytes, (index * 2) as u16).unwrap();
+        line.addr += origin;
+        line
+    })
+}
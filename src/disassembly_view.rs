@@ -0,0 +1,124 @@
+use eframe::egui::{self, Color32, RichText, Sense, Ui};
+
+use crate::{
+    chip8::DebugSnapshot, disassembler::decode_at, handle::Chip8Handle, memory::MEMORY_SIZE,
+};
+
+const LINE_COUNT: usize = MEMORY_SIZE / 2;
+
+const PC_COLOR: Color32 = Color32::from_rgb(40, 90, 160);
+const BREAKPOINT_COLOR: Color32 = Color32::from_rgb(200, 60, 60);
+
+pub struct DisassemblyView {
+    pub open: bool,
+    follow_pc: bool,
+    last_pc: Option<u16>,
+}
+
+impl DisassemblyView {
+    pub fn new() -> Self {
+        Self {
+            open: false,
+            follow_pc: true,
+            last_pc: None,
+        }
+    }
+
+    pub fn ui(&mut self, ui: &mut Ui, snapshot: &DebugSnapshot, handle: &Chip8Handle) {
+        let memory = snapshot.memory.get_ref();
+        let pc = snapshot.cpu.pc();
+
+        ui.horizontal(|ui| {
+            if snapshot.paused {
+                if ui.button("Resume").clicked() {
+                    handle.send_unpause_message();
+                }
+                if ui.button("Step").clicked() {
+                    handle.step();
+                }
+            } else if ui.button("Pause").clicked() {
+                handle.send_pause_message();
+            }
+
+            ui.separator();
+            ui.checkbox(&mut self.follow_pc, "Follow PC");
+            ui.label(format!("PC {pc:03X}"));
+        });
+        ui.label(
+            RichText::new("Click a line to toggle a breakpoint, right-click to run to it").weak(),
+        );
+
+        ui.separator();
+
+        // Instructions are two bytes wide, so line up the listing with whichever parity PC is on
+        let parity = pc & 1;
+
+        let row_height = ui.text_style_height(&egui::TextStyle::Monospace);
+        let mut scroll_area = egui::ScrollArea::vertical().auto_shrink(false);
+        if self.follow_pc && self.last_pc != Some(pc) {
+            let row = pc as usize / 2;
+            let spacing = row_height + ui.spacing().item_spacing.y;
+            let centered = row as f32 * spacing - ui.available_height() / 2.0;
+            scroll_area = scroll_area.vertical_scroll_offset(centered.max(0.0));
+        }
+        self.last_pc = Some(pc);
+
+        scroll_area.show_rows(ui, row_height, LINE_COUNT, |ui, rows| {
+            for row in rows {
+                let addr = row as u16 * 2 + parity;
+                let Some(line) = decode_at(memory, addr) else {
+                    continue;
+                };
+
+                let breakpoint = snapshot.debugger.breakpoints().contains_key(&addr);
+
+                // With coverage recorded, words only ever read as data are listed as such
+                let coverage = &snapshot.coverage;
+                let data = !coverage.is_executed(addr)
+                    && (coverage.is_data(addr) || coverage.is_data(addr + 1));
+
+                let marker = if breakpoint { "●" } else { " " };
+                let mut text = if data {
+                    RichText::new(format!("{marker} {}", line.as_data()))
+                        .monospace()
+                        .weak()
+                } else {
+                    match snapshot.symbols.annotate(&line) {
+                        Some(comment) => {
+                            RichText::new(format!("{marker} {:<32} ; {comment}", line.to_string()))
+                                .monospace()
+                        }
+                        None => RichText::new(format!("{marker} {line}")).monospace(),
+                    }
+                };
+                if addr == pc {
+                    text = text.background_color(PC_COLOR);
+                }
+                if breakpoint {
+                    text = text.color(BREAKPOINT_COLOR);
+                }
+
+                let response = ui.add(egui::Label::new(text).sense(Sense::click()));
+                if response.clicked() {
+                    handle.toggle_breakpoint(addr);
+                }
+                response.context_menu(|ui| {
+                    if ui.button("Run to here").clicked() {
+                        handle.run_to(addr);
+                        ui.close();
+                    }
+                    if ui.button("Toggle breakpoint").clicked() {
+                        handle.toggle_breakpoint(addr);
+                        ui.close();
+                    }
+                });
+            }
+        });
+    }
+}
+
+impl Default for DisassemblyView {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -1,7 +1,20 @@
+use std::fmt;
+
+#[derive(Debug)]
 pub enum Chip8Error {
     OutOfBoundsAccess,
     StackUnderflow,
     StackOverflow,
 }
 
+impl fmt::Display for Chip8Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Chip8Error::OutOfBoundsAccess => write!(f, "memory access out of bounds"),
+            Chip8Error::StackUnderflow => write!(f, "stack underflow"),
+            Chip8Error::StackOverflow => write!(f, "stack overflow"),
+        }
+    }
+}
+
 pub type Result<T> = std::result::Result<T, Chip8Error>;
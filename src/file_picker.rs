@@ -7,12 +7,22 @@ pub enum Config {
     ROM,
     Load,
     Save,
+    Trace,
+    ProfileCsv,
+    ProfileFolded,
+    CoverageReport,
+    Symbols,
 }
 
 pub enum FilePickerResult {
     ROM(String),
     Load(String),
     Save(String),
+    Trace(String),
+    ProfileCsv(String),
+    ProfileFolded(String),
+    CoverageReport(String),
+    Symbols(String),
     None,
 }
 
@@ -67,6 +77,58 @@ impl FilePicker {
                     let _ = sender.send(FilePickerResult::None);
                 }
             }
+            Config::Trace => {
+                if let Some(path) = rfd::FileDialog::new()
+                    .add_filter("log", &["log"])
+                    .save_file()
+                {
+                    let _ = sender.send(FilePickerResult::Trace(path.display().to_string()));
+                } else {
+                    let _ = sender.send(FilePickerResult::None);
+                }
+            }
+            Config::ProfileCsv => {
+                if let Some(path) = rfd::FileDialog::new()
+                    .add_filter("csv", &["csv"])
+                    .save_file()
+                {
+                    let _ = sender.send(FilePickerResult::ProfileCsv(path.display().to_string()));
+                } else {
+                    let _ = sender.send(FilePickerResult::None);
+                }
+            }
+            Config::Symbols => {
+                if let Some(path) = rfd::FileDialog::new()
+                    .add_filter("symbols", &["sym", "json", "txt"])
+                    .pick_file()
+                {
+                    let _ = sender.send(FilePickerResult::Symbols(path.display().to_string()));
+                } else {
+                    let _ = sender.send(FilePickerResult::None);
+                }
+            }
+            Config::CoverageReport => {
+                if let Some(path) = rfd::FileDialog::new()
+                    .add_filter("txt", &["txt"])
+                    .save_file()
+                {
+                    let _ =
+                        sender.send(FilePickerResult::CoverageReport(path.display().to_string()));
+                } else {
+                    let _ = sender.send(FilePickerResult::None);
+                }
+            }
+            Config::ProfileFolded => {
+                if let Some(path) = rfd::FileDialog::new()
+                    .add_filter("folded", &["folded"])
+                    .save_file()
+                {
+                    let _ =
+                        sender.send(FilePickerResult::ProfileFolded(path.display().to_string()));
+                } else {
+                    let _ = sender.send(FilePickerResult::None);
+                }
+            }
         }));
 
         self.receiver = Some(receiver);
@@ -0,0 +1,136 @@
+use crate::frame_buffer::{FRAME_BUFFER_SIZE, FrameBuffer};
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum FlickerMode {
+    Off,
+    VBlank,
+    Ghosting,
+    DisplayWait,
+}
+
+impl FlickerMode {
+    pub const ALL: [FlickerMode; 4] = [
+        FlickerMode::Off,
+        FlickerMode::VBlank,
+        FlickerMode::Ghosting,
+        FlickerMode::DisplayWait,
+    ];
+
+    /// Whether a frame should be published as soon as `Drw`/`Cls` touches it,
+    /// instead of waiting for the next 60 Hz vblank.
+    pub fn draws_immediately(self) -> bool {
+        self == FlickerMode::Off
+    }
+
+    /// Ghosting keeps fading pixels out, so it needs a new frame on every vblank
+    /// even if the program didn't draw anything.
+    pub fn redraws_every_frame(self) -> bool {
+        self == FlickerMode::Ghosting
+    }
+}
+
+impl From<FlickerMode> for &'static str {
+    fn from(mode: FlickerMode) -> Self {
+        match mode {
+            FlickerMode::Off => "Off",
+            FlickerMode::VBlank => "Render on vblank",
+            FlickerMode::Ghosting => "Phosphor ghosting",
+            FlickerMode::DisplayWait => "Display wait",
+        }
+    }
+}
+
+/// Per-pixel brightness that decays over a few frames after a pixel is turned off.
+pub struct Phosphor {
+    levels: [f32; FRAME_BUFFER_SIZE],
+}
+
+impl Phosphor {
+    pub fn new() -> Self {
+        Self {
+            levels: [0.0; FRAME_BUFFER_SIZE],
+        }
+    }
+
+    pub fn update(&mut self, frame_buffer: &FrameBuffer, persistence: f32) {
+        for (level, on) in self.levels.iter_mut().zip(frame_buffer.pixels()) {
+            *level = if on { 1.0 } else { *level * persistence };
+        }
+    }
+
+    pub fn levels(&self) -> &[f32; FRAME_BUFFER_SIZE] {
+        &self.levels
+    }
+}
+
+impl Default for Phosphor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The color of a pixel at phosphor `level`, from `off` at 0 to `on` at 1.
+pub fn blend(off: [u8; 3], on: [u8; 3], level: f32) -> [u8; 3] {
+    let mix = |off: u8, on: u8| (off as f32 + (on as f32 - off as f32) * level).round() as u8;
+
+    [mix(off[0], on[0]), mix(off[1], on[1]), mix(off[2], on[2])]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A frame with only the top-left pixel lit, or nothing lit.
+    fn frame(lit: bool) -> FrameBuffer {
+        let mut frame_buffer = FrameBuffer::new();
+        frame_buffer.xor(0, 0, lit);
+        frame_buffer
+    }
+
+    #[test]
+    fn pixels_fade_by_persistence_each_frame() {
+        let mut phosphor = Phosphor::new();
+
+        phosphor.update(&frame(true), 0.5);
+        assert_eq!(phosphor.levels()[0], 1.0);
+        assert_eq!(phosphor.levels()[1], 0.0);
+
+        let fading: Vec<f32> = (0..3)
+            .map(|_| {
+                phosphor.update(&frame(false), 0.5);
+                phosphor.levels()[0]
+            })
+            .collect();
+        assert_eq!(fading, [0.5, 0.25, 0.125]);
+
+        // Lit again, it's back at full brightness
+        phosphor.update(&frame(true), 0.5);
+        assert_eq!(phosphor.levels()[0], 1.0);
+    }
+
+    #[test]
+    fn no_persistence_turns_pixels_straight_off() {
+        let mut phosphor = Phosphor::new();
+
+        phosphor.update(&frame(true), 0.0);
+        phosphor.update(&frame(false), 0.0);
+        assert_eq!(phosphor.levels()[0], 0.0);
+    }
+
+    #[test]
+    fn blends_between_off_and_on() {
+        let (off, on) = ([0, 100, 255], [255, 200, 0]);
+
+        assert_eq!(blend(off, on, 0.0), off);
+        assert_eq!(blend(off, on, 1.0), on);
+        assert_eq!(blend(off, on, 0.5), [128, 150, 128]);
+    }
+
+    #[test]
+    fn only_off_draws_immediately() {
+        for mode in FlickerMode::ALL {
+            assert_eq!(mode.draws_immediately(), mode == FlickerMode::Off);
+            assert_eq!(mode.redraws_every_frame(), mode == FlickerMode::Ghosting);
+        }
+    }
+}
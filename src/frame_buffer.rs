@@ -1,15 +1,15 @@
 use serde::{Deserialize, Serialize};
-use serde_big_array::BigArray;
 
 pub const FRAME_BUFFER_ROWS: usize = 32;
 pub const FRAME_BUFFER_COLS: usize = 64;
 
 pub const FRAME_BUFFER_SIZE: usize = FRAME_BUFFER_COLS * FRAME_BUFFER_ROWS;
 
-#[derive(Serialize, Deserialize, Clone)]
+/// One bit per pixel, a `u64` per row with column 0 in the most significant bit, so a
+/// sprite row is drawn with a single shift and XOR.
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
 pub struct FrameBuffer {
-    #[serde(with = "BigArray")]
-    buffer: [bool; FRAME_BUFFER_SIZE],
+    rows: [u64; FRAME_BUFFER_ROWS],
 }
 
 impl Default for FrameBuffer {
@@ -21,25 +21,153 @@ impl Default for FrameBuffer {
 impl FrameBuffer {
     pub fn new() -> Self {
         Self {
-            buffer: [false; FRAME_BUFFER_SIZE],
+            rows: [0; FRAME_BUFFER_ROWS],
         }
     }
 
+    /// A frame from packed rows, as returned by `rows`.
+    pub(crate) fn from_rows(rows: [u64; FRAME_BUFFER_ROWS]) -> Self {
+        Self { rows }
+    }
+
+    pub(crate) fn rows(&self) -> &[u64; FRAME_BUFFER_ROWS] {
+        &self.rows
+    }
+
     pub fn load(&mut self, frame_buffer: FrameBuffer) {
-        self.buffer = frame_buffer.buffer;
+        self.rows = frame_buffer.rows;
+    }
+
+    pub fn clear(&mut self) {
+        self.rows = [0; FRAME_BUFFER_ROWS];
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> bool {
+        self.rows[y] & (1 << (FRAME_BUFFER_COLS - 1 - x)) != 0
     }
 
     pub fn xor(&mut self, x: usize, y: usize, value: bool) -> bool {
-        let pixel_pos = y * FRAME_BUFFER_COLS + x;
-        let old_val = self.buffer[pixel_pos];
-        let new_val = old_val ^ value;
+        let mask = (value as u64) << (FRAME_BUFFER_COLS - 1 - x);
+        let turned_off = self.rows[y] & mask != 0;
+
+        self.rows[y] ^= mask;
+
+        turned_off
+    }
+
+    /// XORs `sprite` in with its top-left corner at (`x`, `y`), one byte per row, clipping
+    /// at the right and bottom edges. Returns whether any pixel was turned off.
+    pub fn draw_sprite(&mut self, x: usize, y: usize, sprite: &[u8]) -> bool {
+        let mut collision = 0;
+
+        for (row, &byte) in self.rows[y..].iter_mut().zip(sprite) {
+            // Bits shifted past column 63 fall off the end, which is the clipping
+            let bits = ((byte as u64) << (FRAME_BUFFER_COLS - 8)) >> x;
+            collision |= *row & bits;
+            *row ^= bits;
+        }
+
+        collision != 0
+    }
+
+    /// FNV-1a over the rows, stable across platforms and versions so it can be checked
+    /// into regression tests.
+    pub fn hash(&self) -> u64 {
+        self.rows
+            .iter()
+            .flat_map(|row| row.to_be_bytes())
+            .fold(0xCBF2_9CE4_8422_2325, |hash, byte| {
+                (hash ^ byte as u64).wrapping_mul(0x0000_0100_0000_01B3)
+            })
+    }
+
+    /// Every pixel, row by row.
+    pub fn pixels(&self) -> impl Iterator<Item = bool> + '_ {
+        self.rows.iter().flat_map(|row| {
+            (0..FRAME_BUFFER_COLS)
+                .rev()
+                .map(move |bit| row >> bit & 1 == 1)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SPRITE: [u8; 15] = [
+        0xFF, 0x81, 0xA5, 0x3C, 0x00, 0x18, 0xF0, 0x0F, 0x80, 0x01, 0xAA, 0x55, 0xC3, 0x7E, 0xFF,
+    ];
+
+    /// Something to collide with: a checkerboard over the right half.
+    fn patterned() -> FrameBuffer {
+        let mut frame_buffer = FrameBuffer::new();
+        for y in 0..FRAME_BUFFER_ROWS {
+            for x in FRAME_BUFFER_COLS / 2..FRAME_BUFFER_COLS {
+                frame_buffer.xor(x, y, (x + y) % 2 == 0);
+            }
+        }
+        frame_buffer
+    }
 
-        self.buffer[pixel_pos] = new_val;
+    /// `draw_sprite` done a pixel at a time, the way it was before rows were packed.
+    fn draw_with_xor(frame_buffer: &mut FrameBuffer, x: usize, y: usize, sprite: &[u8]) -> bool {
+        let mut collision = false;
 
-        old_val && !new_val
+        for (row, &byte) in sprite.iter().enumerate() {
+            for col in 0..8 {
+                if x + col < FRAME_BUFFER_COLS && y + row < FRAME_BUFFER_ROWS {
+                    collision |= frame_buffer.xor(x + col, y + row, byte & (0x80 >> col) != 0);
+                }
+            }
+        }
+
+        collision
     }
 
-    pub fn get_ref(&self) -> &[bool; FRAME_BUFFER_SIZE] {
-        &self.buffer
+    #[test]
+    fn draw_sprite_matches_xor() {
+        let xs = [0, 3, 28, 33, 56, 60, 61, 62, 63];
+        let ys = [0, 9, 17, 27, 31];
+
+        for x in xs {
+            for y in ys {
+                for len in 0..=SPRITE.len() {
+                    let sprite = &SPRITE[..len];
+                    let mut expected = patterned();
+                    let mut actual = patterned();
+
+                    let expected_collision = draw_with_xor(&mut expected, x, y, sprite);
+                    let actual_collision = actual.draw_sprite(x, y, sprite);
+
+                    assert!(
+                        actual == expected,
+                        "pixels differ at ({x}, {y}), {len} rows"
+                    );
+                    assert_eq!(
+                        actual_collision, expected_collision,
+                        "collision differs at ({x}, {y}), {len} rows"
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn redrawing_erases_and_collides() {
+        let mut frame_buffer = FrameBuffer::new();
+
+        assert!(!frame_buffer.draw_sprite(62, 31, &SPRITE));
+        assert!(frame_buffer.get(62, 31) && frame_buffer.get(63, 31));
+        assert!(frame_buffer.draw_sprite(62, 31, &SPRITE));
+        assert!(frame_buffer == FrameBuffer::new());
+    }
+
+    #[test]
+    fn empty_sprite_draws_nothing() {
+        let mut frame_buffer = patterned();
+
+        assert!(!frame_buffer.draw_sprite(40, 5, &[]));
+        assert!(frame_buffer == patterned());
     }
 }
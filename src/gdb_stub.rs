@@ -0,0 +1,532 @@
+use std::{
+    fmt::Write as _,
+    io::{self, ErrorKind, Read, Write},
+    net::{TcpListener, TcpStream},
+};
+
+use crate::{chip8::Chip8, cpu::Register, memory::MEMORY_SIZE};
+
+pub const SIGINT: u8 = 2;
+pub const SIGTRAP: u8 = 5;
+
+const TARGET_XML: &str = r#"<?xml version="1.0"?>
+<!DOCTYPE target SYSTEM "gdb-target.dtd">
+<target version="1.0">
+  <feature name="org.chip8.core">
+    <reg name="v0" bitsize="8" type="uint8"/>
+    <reg name="v1" bitsize="8" type="uint8"/>
+    <reg name="v2" bitsize="8" type="uint8"/>
+    <reg name="v3" bitsize="8" type="uint8"/>
+    <reg name="v4" bitsize="8" type="uint8"/>
+    <reg name="v5" bitsize="8" type="uint8"/>
+    <reg name="v6" bitsize="8" type="uint8"/>
+    <reg name="v7" bitsize="8" type="uint8"/>
+    <reg name="v8" bitsize="8" type="uint8"/>
+    <reg name="v9" bitsize="8" type="uint8"/>
+    <reg name="va" bitsize="8" type="uint8"/>
+    <reg name="vb" bitsize="8" type="uint8"/>
+    <reg name="vc" bitsize="8" type="uint8"/>
+    <reg name="vd" bitsize="8" type="uint8"/>
+    <reg name="ve" bitsize="8" type="uint8"/>
+    <reg name="vf" bitsize="8" type="uint8"/>
+    <reg name="i" bitsize="16" type="data_ptr"/>
+    <reg name="pc" bitsize="16" type="code_ptr"/>
+    <reg name="sp" bitsize="8" type="uint8"/>
+    <reg name="dt" bitsize="8" type="uint8"/>
+    <reg name="st" bitsize="8" type="uint8"/>
+  </feature>
+</target>
+"#;
+
+/// What the emulator thread has to do after handling the client's packets.
+pub enum GdbAction {
+    None,
+    /// A client connected; the target should halt.
+    Attach,
+    Continue,
+    Step,
+    Interrupt,
+    /// The client detached or went away; the target should resume.
+    Detach,
+}
+
+/// Minimal GDB remote serial protocol server on localhost.
+///
+/// Registers are numbered in `Register::ALL` order (V0–VF, I, PC, SP, DT, ST) and sent
+/// little-endian; I and PC are 16 bits wide, the rest 8. The layout is also served as
+/// `target.xml` for clients that ask for it.
+pub struct GdbServer {
+    listener: TcpListener,
+    client: Option<TcpStream>,
+    buffer: Vec<u8>,
+    /// The client resumed the target and is waiting for a stop reply.
+    running: bool,
+}
+
+impl GdbServer {
+    pub fn bind(port: u16) -> io::Result<Self> {
+        let listener = TcpListener::bind(("127.0.0.1", port))?;
+        listener.set_nonblocking(true)?;
+
+        Ok(Self {
+            listener,
+            client: None,
+            buffer: Vec::new(),
+            running: false,
+        })
+    }
+
+    pub fn port(&self) -> u16 {
+        self.listener
+            .local_addr()
+            .map(|addr| addr.port())
+            .unwrap_or(0)
+    }
+
+    /// Accepts a new client or handles whatever the current one sent. Never blocks.
+    pub fn poll(&mut self, chip8: &mut Chip8) -> GdbAction {
+        let Some(ref mut client) = self.client else {
+            return match self.listener.accept() {
+                Ok((stream, _)) if stream.set_nonblocking(true).is_ok() => {
+                    let _ = stream.set_nodelay(true);
+                    self.client = Some(stream);
+                    self.buffer.clear();
+                    self.running = false;
+                    GdbAction::Attach
+                }
+                _ => GdbAction::None,
+            };
+        };
+
+        let mut chunk = [0u8; 1024];
+        loop {
+            match client.read(&mut chunk) {
+                Ok(0) => return self.disconnect(),
+                Ok(len) => self.buffer.extend_from_slice(&chunk[..len]),
+                Err(err) if err.kind() == ErrorKind::WouldBlock => break,
+                Err(err) if err.kind() == ErrorKind::Interrupted => continue,
+                Err(_) => return self.disconnect(),
+            }
+        }
+
+        self.process(chip8)
+    }
+
+    /// Tells a client waiting on `c` or `s` that the target stopped.
+    pub fn report_stop(&mut self, signal: u8) {
+        if self.running {
+            self.running = false;
+            self.send_packet(&format!("S{signal:02x}"));
+        }
+    }
+
+    fn disconnect(&mut self) -> GdbAction {
+        self.client = None;
+        self.buffer.clear();
+        self.running = false;
+        GdbAction::Detach
+    }
+
+    fn process(&mut self, chip8: &mut Chip8) -> GdbAction {
+        while let Some(&first) = self.buffer.first() {
+            match first {
+                0x03 => {
+                    self.buffer.remove(0);
+                    if self.running {
+                        return GdbAction::Interrupt;
+                    }
+                }
+                b'$' => {
+                    let Some(end) = self.buffer.iter().position(|&byte| byte == b'#') else {
+                        return GdbAction::None;
+                    };
+                    if self.buffer.len() < end + 3 {
+                        return GdbAction::None;
+                    }
+
+                    let packet = self.buffer.drain(..end + 3).collect::<Vec<_>>();
+                    let data = &packet[1..end];
+                    let checksum = std::str::from_utf8(&packet[end + 1..])
+                        .ok()
+                        .and_then(|text| u8::from_str_radix(text, 16).ok());
+
+                    if checksum != Some(checksum_of(data)) {
+                        self.send_raw(b"-");
+                        continue;
+                    }
+                    self.send_raw(b"+");
+
+                    let data = String::from_utf8_lossy(data).into_owned();
+                    let action = self.handle_packet(&data, chip8);
+                    if !matches!(action, GdbAction::None) {
+                        return action;
+                    }
+                }
+                // Acks, and anything else outside a packet
+                _ => {
+                    self.buffer.remove(0);
+                }
+            }
+        }
+
+        GdbAction::None
+    }
+
+    fn handle_packet(&mut self, packet: &str, chip8: &mut Chip8) -> GdbAction {
+        let (command, args) = packet.split_at(packet.len().min(1));
+
+        let reply = match command {
+            "?" => format!("S{SIGTRAP:02x}"),
+            "g" => {
+                let cpu = chip8.cpu();
+                Register::ALL
+                    .into_iter()
+                    .map(|register| encode_register(register, cpu.register(register)))
+                    .collect()
+            }
+            "G" => match decode_registers(args) {
+                Some(values) => {
+                    for (register, value) in values {
+                        chip8.cpu_mut().set_register(register, value);
+                    }
+                    "OK".to_string()
+                }
+                None => "E01".to_string(),
+            },
+            "p" => match register_at(args) {
+                Some(register) => encode_register(register, chip8.cpu().register(register)),
+                None => "E01".to_string(),
+            },
+            "P" => {
+                let value = args.split_once('=').and_then(|(index, value)| {
+                    let register = register_at(index)?;
+                    Some((register, decode_value(value)?))
+                });
+                match value {
+                    Some((register, value)) => {
+                        chip8.cpu_mut().set_register(register, value);
+                        "OK".to_string()
+                    }
+                    None => "E01".to_string(),
+                }
+            }
+            "m" => match parse_range(args) {
+                Some((addr, len)) => {
+                    let memory = chip8.memory().get_ref();
+                    memory[addr..addr + len]
+                        .iter()
+                        .fold(String::new(), |mut hex, byte| {
+                            let _ = write!(hex, "{byte:02x}");
+                            hex
+                        })
+                }
+                None => "E01".to_string(),
+            },
+            "M" => {
+                let write = args.split_once(':').and_then(|(range, data)| {
+                    let (addr, len) = parse_range(range)?;
+                    let bytes = decode_hex(data)?;
+                    (bytes.len() == len).then_some((addr, bytes))
+                });
+                match write {
+                    Some((addr, bytes)) => {
+                        for (offset, byte) in bytes.into_iter().enumerate() {
+                            let _ = chip8.write_memory((addr + offset) as u16, byte);
+                        }
+                        "OK".to_string()
+                    }
+                    None => "E01".to_string(),
+                }
+            }
+            "c" | "s" => {
+                if let Ok(addr) = u16::from_str_radix(args, 16) {
+                    chip8.cpu_mut().set_register(Register::Pc, addr);
+                }
+                self.running = true;
+                return if command == "c" {
+                    GdbAction::Continue
+                } else {
+                    GdbAction::Step
+                };
+            }
+            "Z" | "z" => match parse_breakpoint(args) {
+                Some(addr) => {
+                    if command == "Z" {
+                        chip8.debugger_mut().add_breakpoint(addr);
+                    } else {
+                        chip8.debugger_mut().remove_breakpoint(addr);
+                    }
+                    "OK".to_string()
+                }
+                // Watchpoints aren't supported over the wire
+                None => String::new(),
+            },
+            "q" => query(args),
+            "H" | "T" => "OK".to_string(),
+            "D" => {
+                self.send_packet("OK");
+                return self.disconnect();
+            }
+            "k" => return self.disconnect(),
+            _ => String::new(),
+        };
+
+        self.send_packet(&reply);
+        GdbAction::None
+    }
+
+    fn send_packet(&mut self, data: &str) {
+        let packet = format!("${data}#{:02x}", checksum_of(data.as_bytes()));
+        self.send_raw(packet.as_bytes());
+    }
+
+    fn send_raw(&mut self, bytes: &[u8]) {
+        let Some(ref mut client) = self.client else {
+            return;
+        };
+
+        if client.write_all(bytes).is_err() {
+            self.disconnect();
+        }
+    }
+}
+
+fn query(args: &str) -> String {
+    if args.starts_with("Supported") {
+        return "PacketSize=1000;qXfer:features:read+".to_string();
+    }
+    if let Some(range) = args.strip_prefix("Xfer:features:read:target.xml:") {
+        let Some((offset, len)) = range.split_once(',').and_then(|(offset, len)| {
+            Some((
+                usize::from_str_radix(offset, 16).ok()?,
+                usize::from_str_radix(len, 16).ok()?,
+            ))
+        }) else {
+            return "E01".to_string();
+        };
+
+        let start = offset.min(TARGET_XML.len());
+        let end = (start + len).min(TARGET_XML.len());
+        let prefix = if end == TARGET_XML.len() { 'l' } else { 'm' };
+        return format!("{prefix}{}", &TARGET_XML[start..end]);
+    }
+
+    match args {
+        "Attached" => "1".to_string(),
+        "fThreadInfo" => "m1".to_string(),
+        "sThreadInfo" => "l".to_string(),
+        "C" => "QC1".to_string(),
+        _ => String::new(),
+    }
+}
+
+fn checksum_of(data: &[u8]) -> u8 {
+    data.iter().fold(0u8, |sum, &byte| sum.wrapping_add(byte))
+}
+
+fn register_size(register: Register) -> usize {
+    match register {
+        Register::I | Register::Pc => 2,
+        _ => 1,
+    }
+}
+
+fn register_at(index: &str) -> Option<Register> {
+    let index = usize::from_str_radix(index, 16).ok()?;
+    Register::ALL.get(index).copied()
+}
+
+fn encode_register(register: Register, value: u16) -> String {
+    value.to_le_bytes()[..register_size(register)]
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+/// Little-endian hex, as sent by `P`.
+fn decode_value(hex: &str) -> Option<u16> {
+    let bytes = decode_hex(hex)?;
+    if bytes.is_empty() || bytes.len() > 2 {
+        return None;
+    }
+
+    Some(
+        bytes
+            .iter()
+            .rev()
+            .fold(0u16, |value, &byte| (value << 8) | byte as u16),
+    )
+}
+
+fn decode_registers(hex: &str) -> Option<Vec<(Register, u16)>> {
+    let bytes = decode_hex(hex)?;
+    let mut offset = 0;
+    let mut values = Vec::with_capacity(Register::ALL.len());
+
+    for register in Register::ALL {
+        let size = register_size(register);
+        let chunk = bytes.get(offset..offset + size)?;
+        let value = chunk
+            .iter()
+            .rev()
+            .fold(0u16, |value, &byte| (value << 8) | byte as u16);
+        values.push((register, value));
+        offset += size;
+    }
+
+    Some(values)
+}
+
+fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) {
+        return None;
+    }
+
+    (0..hex.len())
+        .step_by(2)
+        .map(|index| u8::from_str_radix(hex.get(index..index + 2)?, 16).ok())
+        .collect()
+}
+
+/// `addr,len` within memory.
+fn parse_range(args: &str) -> Option<(usize, usize)> {
+    let (addr, len) = args.split_once(',')?;
+    let addr = usize::from_str_radix(addr, 16).ok()?;
+    let len = usize::from_str_radix(len, 16).ok()?;
+
+    (addr + len <= MEMORY_SIZE).then_some((addr, len))
+}
+
+/// `type,addr,kind` for software (0) and hardware (1) breakpoints.
+fn parse_breakpoint(args: &str) -> Option<u16> {
+    let mut parts = args.split(',');
+    let kind = parts.next()?;
+    let addr = u16::from_str_radix(parts.next()?, 16).ok()?;
+
+    matches!(kind, "0" | "1").then_some(addr)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::{Arc, Mutex},
+        time::Duration,
+    };
+
+    use super::*;
+    use crate::key_matrix::KeyMatrix;
+
+    /// A GDB client on a real socket, polling the server between reads since both run
+    /// on the test thread.
+    struct Client {
+        stream: TcpStream,
+        buffer: Vec<u8>,
+    }
+
+    impl Client {
+        fn connect(server: &mut GdbServer, chip8: &mut Chip8) -> Self {
+            let stream = TcpStream::connect(("127.0.0.1", server.port())).unwrap();
+            stream
+                .set_read_timeout(Some(Duration::from_millis(1)))
+                .unwrap();
+
+            let attached = (0..1000).any(|_| {
+                std::thread::sleep(Duration::from_millis(1));
+                matches!(server.poll(chip8), GdbAction::Attach)
+            });
+            assert!(attached, "server never accepted the client");
+
+            Self {
+                stream,
+                buffer: Vec::new(),
+            }
+        }
+
+        fn send(&mut self, packet: &str) {
+            let checksum = checksum_of(packet.as_bytes());
+            self.stream
+                .write_all(format!("${packet}#{checksum:02x}").as_bytes())
+                .unwrap();
+        }
+
+        /// Polls until the server sends a whole packet; returns it with the last action
+        /// `poll` asked for.
+        fn reply(&mut self, server: &mut GdbServer, chip8: &mut Chip8) -> (String, GdbAction) {
+            let mut action = GdbAction::None;
+
+            for _ in 0..1000 {
+                match server.poll(chip8) {
+                    GdbAction::None => {}
+                    other => action = other,
+                }
+
+                let mut chunk = [0u8; 256];
+                if let Ok(len) = self.stream.read(&mut chunk) {
+                    self.buffer.extend_from_slice(&chunk[..len]);
+                }
+
+                let start = self.buffer.iter().position(|&byte| byte == b'$');
+                let end = self.buffer.iter().position(|&byte| byte == b'#');
+                if let (Some(start), Some(end)) = (start, end)
+                    && self.buffer.len() >= end + 3
+                {
+                    let packet = String::from_utf8_lossy(&self.buffer[start + 1..end]).into();
+                    self.buffer.drain(..end + 3);
+                    return (packet, action);
+                }
+            }
+
+            panic!("no reply from the server");
+        }
+
+        fn exchange(&mut self, server: &mut GdbServer, chip8: &mut Chip8, packet: &str) -> String {
+            self.send(packet);
+            self.reply(server, chip8).0
+        }
+    }
+
+    #[test]
+    fn scripted_session() {
+        let mut chip8 = Chip8::new(Arc::new(Mutex::new(KeyMatrix::new())));
+        // 200: V0 = 5; 202: V0 += 1; 204: jump to 202
+        for (offset, byte) in [0x60, 0x05, 0x70, 0x01, 0x12, 0x02].into_iter().enumerate() {
+            chip8.write_memory(0x200 + offset as u16, byte).unwrap();
+        }
+
+        let mut server = GdbServer::bind(0).unwrap();
+        assert_ne!(server.port(), 0);
+        let mut client = Client::connect(&mut server, &mut chip8);
+
+        assert_eq!(client.exchange(&mut server, &mut chip8, "?"), "S05");
+
+        let registers = client.exchange(&mut server, &mut chip8, "g");
+        // 16 V registers, I and PC as two bytes each, then SP, DT and ST
+        assert_eq!(registers.len(), (16 + 2 + 2 + 3) * 2);
+        assert_eq!(&registers[36..40], "0002");
+
+        assert_eq!(
+            client.exchange(&mut server, &mut chip8, "m200,6"),
+            "600570011202"
+        );
+        assert_eq!(client.exchange(&mut server, &mut chip8, "Z0,204,2"), "OK");
+
+        // `c` has no reply until the target stops
+        client.send("c");
+        let continued = (0..1000).any(|_| matches!(server.poll(&mut chip8), GdbAction::Continue));
+        assert!(continued);
+        while chip8.check_break().is_none() {
+            chip8.tick().unwrap();
+        }
+        server.report_stop(SIGTRAP);
+        assert_eq!(client.reply(&mut server, &mut chip8).0, "S05");
+
+        let registers = client.exchange(&mut server, &mut chip8, "g");
+        assert_eq!(&registers[0..2], "06");
+        assert_eq!(&registers[36..40], "0402");
+
+        client.send("D");
+        let (reply, action) = client.reply(&mut server, &mut chip8);
+        assert_eq!(reply, "OK");
+        assert!(matches!(action, GdbAction::Detach));
+    }
+}
@@ -0,0 +1,50 @@
+use eframe::egui::{self, Ui};
+
+use crate::{Message, handle::Chip8Handle};
+
+pub struct GdbView {
+    pub open: bool,
+    pub listening: bool,
+    pub status: String,
+    port: u16,
+}
+
+impl GdbView {
+    pub fn new() -> Self {
+        Self {
+            open: false,
+            listening: false,
+            status: "Stopped".to_string(),
+            port: 1234,
+        }
+    }
+
+    pub fn ui(&mut self, ui: &mut Ui, handle: &Chip8Handle) {
+        ui.horizontal(|ui| {
+            ui.label("Port");
+            ui.add_enabled(
+                !self.listening,
+                egui::DragValue::new(&mut self.port).range(1..=u16::MAX),
+            );
+
+            if self.listening {
+                if ui.button("Stop").clicked() {
+                    handle.send(Message::StopGdbServer);
+                }
+            } else if ui.button("Start").clicked() {
+                handle.send(Message::StartGdbServer(self.port));
+            }
+        });
+
+        ui.label(&self.status);
+        ui.label(
+            egui::RichText::new(format!("Connect with `target remote :{}`", self.port)).weak(),
+        );
+    }
+}
+
+impl Default for GdbView {
+    fn default() -> Self {
+        Self::new()
+    }
+}
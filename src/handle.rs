@@ -1,6 +1,7 @@
 use std::{
-    hint::spin_loop,
-    sync::{Arc, Mutex, mpsc::TryRecvError},
+    collections::VecDeque,
+    io,
+    sync::{Arc, Mutex, mpsc::RecvTimeoutError},
     thread::{self, JoinHandle},
     time::{Duration, Instant},
 };
@@ -10,13 +11,36 @@ use crate::{
     channel::Channel,
     chip8::Chip8,
     chip8_state::Chip8State,
+    flicker::FlickerMode,
     frame_buffer::FrameBuffer,
+    gdb_stub::{GdbAction, GdbServer, SIGINT, SIGTRAP},
     key_matrix::{Chip8Key, KeyMatrix},
+    key_wait::KeyEvent,
+    scheduler::FrameScheduler,
+    symbols::Symbols,
+    triple_buffer::{FrameReader, FrameWriter, triple_buffer},
 };
 
+/// How many times faster than real time fast-forward runs.
+const FAST_FORWARD_SPEED: u32 = 4;
+/// Frames between rewind snapshots.
+const REWIND_INTERVAL: u32 = 6;
+/// Snapshots kept for rewinding; a minute of play at one every 6 frames.
+const REWIND_CAPACITY: usize = 600;
+/// How often a paused emulator wakes up to serve a connected debugger.
+const GDB_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Hands the UI a copy of the display. The emulator thread only does this between
+/// instructions, so the UI never sees a half-drawn sprite.
+fn publish_frame(frames: &mut FrameWriter, channel: &Channel, chip8: &Chip8) {
+    frames.write(chip8.frame_buffer());
+    channel.wake();
+}
+
 pub struct Chip8Handle {
     handle: Option<JoinHandle<()>>,
     channel: Option<Channel>,
+    frames: FrameReader,
 }
 
 pub enum Chip8Source {
@@ -25,90 +49,357 @@ pub enum Chip8Source {
 }
 
 impl Chip8Handle {
-    pub fn new(
-        key_matrix: Arc<Mutex<KeyMatrix>>,
-        frame_buffer: Arc<Mutex<FrameBuffer>>,
-
-        source: Chip8Source,
-    ) -> Self {
-        let (channel_1, channel_2) = Channel::new();
-
+    /// Loads the ROM or save state and starts the emulator thread.
+    pub fn new(key_matrix: Arc<Mutex<KeyMatrix>>, source: Chip8Source) -> io::Result<Self> {
         let mut chip8 = match source {
             Chip8Source::ROM(path) => {
-                let mut chip8 = Chip8::new(frame_buffer, key_matrix);
-                chip8.load_rom(&path).unwrap();
+                let mut chip8 = Chip8::new(key_matrix);
+                chip8.load_rom(&path)?;
+                if let Some(symbols) = Symbols::discover(&path) {
+                    chip8.set_symbols(Arc::new(symbols));
+                }
                 chip8
             }
             Chip8Source::SaveState(path) => {
-                Chip8::new_from_save_state(frame_buffer, key_matrix, Chip8State::load(&path))
+                Chip8::new_from_save_state(key_matrix, Chip8State::load(&path)?)
             }
         };
 
+        let (mut channel_1, channel_2) = Channel::new();
+        let (mut published, frames) = triple_buffer();
+
         let handle = thread::spawn(move || {
-            let tick_rate = Duration::from_millis(2);
-            let tick_60hz = Duration::from_millis(17);
+            let mut scheduler = FrameScheduler::new();
 
-            let mut last_update_60hz = Instant::now();
-            let mut pause_delta: Duration = Duration::ZERO;
+            let mut flicker_mode = FlickerMode::Off;
+            let mut frame_dirty = false;
+
+            let mut gdb: Option<GdbServer> = None;
+            // Instruction total of the last profile sent to the UI
+            let mut profile_sent: Option<u64> = None;
+
+            let mut rewinding = false;
+            let mut history: VecDeque<Chip8State> = VecDeque::with_capacity(REWIND_CAPACITY);
+            let mut frames: u32 = 0;
+
+            // A save state starts with something on screen
+            publish_frame(&mut published, &channel_1, &chip8);
 
             loop {
-                match channel_1.try_recv() {
-                    Ok(Message::Shutdown) | Err(TryRecvError::Disconnected) => break,
+                // Sleep until the next frame is due or a message arrives; while paused
+                // nothing is due, so only messages (and a debugger, if any) wake us up
+                let timeout = if !chip8.is_paused() {
+                    scheduler
+                        .deadline()
+                        .saturating_duration_since(Instant::now())
+                } else if gdb.is_some() {
+                    GDB_POLL_INTERVAL
+                } else {
+                    Duration::MAX
+                };
+
+                #[allow(clippy::collapsible_match)]
+                match channel_1.recv_timeout(timeout) {
+                    Ok(Message::Shutdown) | Err(RecvTimeoutError::Disconnected) => break,
                     Ok(Message::Pause) => {
                         if !chip8.is_paused() {
-                            pause_delta = Instant::now() - last_update_60hz;
                             chip8.pause();
+                            if let Some(ref mut server) = gdb {
+                                server.report_stop(SIGINT);
+                            }
                         }
                     }
                     Ok(Message::Unpause) => {
                         if chip8.is_paused() {
-                            last_update_60hz = Instant::now() - pause_delta;
+                            scheduler.resync(Instant::now());
+                            chip8.unpause();
+                        }
+                    }
+                    Ok(Message::TogglePause) => {
+                        if chip8.is_paused() {
+                            scheduler.resync(Instant::now());
                             chip8.unpause();
+                        } else {
+                            chip8.pause();
+                            if let Some(ref mut server) = gdb {
+                                server.report_stop(SIGINT);
+                            }
+                        }
+                    }
+                    Ok(Message::Reset) => {
+                        chip8.reset();
+                        publish_frame(&mut published, &channel_1, &chip8);
+                    }
+                    Ok(Message::HardReset) => {
+                        chip8.hard_reset();
+                        publish_frame(&mut published, &channel_1, &chip8);
+                    }
+                    Ok(Message::SetFastForward(enabled)) => {
+                        scheduler.set_speed(if enabled { FAST_FORWARD_SPEED } else { 1 });
+                    }
+                    Ok(Message::SetIps(ips)) => {
+                        scheduler.set_ips(ips);
+                    }
+                    Ok(Message::SetFramePolicy(policy)) => {
+                        scheduler.set_policy(policy);
+                    }
+                    Ok(Message::SetTimingModel(model)) => {
+                        scheduler.set_model(model);
+                    }
+                    Ok(Message::SetRewinding(enabled)) => {
+                        rewinding = enabled;
+                    }
+                    Ok(Message::KeyPressed(key)) => {
+                        if !chip8.is_paused() {
+                            chip8.key_event(KeyEvent::Press(key));
                         }
                     }
-                    Ok(Message::KeyReleased(val)) => {
+                    Ok(Message::KeyReleased(key)) => {
                         if !chip8.is_paused() {
-                            chip8.set_last_released_key(val);
+                            chip8.key_event(KeyEvent::Release(key));
                         }
                     }
+                    Ok(Message::SetKeyWaitMode(mode)) => {
+                        chip8.set_key_wait_mode(mode);
+                    }
                     Ok(Message::Save(path)) => {
-                        chip8.to_chip8_state().save(&path);
+                        if let Err(err) = chip8.to_chip8_state().write(&path) {
+                            channel_1.send(Message::Status(format!("Couldn't save {path}: {err}")));
+                        }
+                    }
+                    Ok(Message::SetWaker(waker)) => {
+                        channel_1.set_waker(waker);
+                    }
+                    Ok(Message::SetFlickerMode(mode)) => {
+                        flicker_mode = mode;
+                        chip8.set_display_wait(mode == FlickerMode::DisplayWait);
+                    }
+                    Ok(Message::RequestSnapshot) => {
+                        channel_1.send(Message::Snapshot(Box::new(chip8.debug_snapshot())));
+                    }
+                    Ok(Message::WriteMemory(addr, byte)) if chip8.is_paused() => {
+                        let _ = chip8.write_memory(addr, byte);
+                    }
+                    Ok(Message::ToggleBreakpoint(addr)) => {
+                        chip8.debugger_mut().toggle_breakpoint(addr);
+                    }
+                    Ok(Message::SetBreakpoint(addr, condition)) => {
+                        chip8.debugger_mut().set_breakpoint(addr, condition);
+                    }
+                    Ok(Message::AddWatchpoint(watchpoint)) => {
+                        chip8.debugger_mut().add_watchpoint(watchpoint);
+                    }
+                    Ok(Message::RemoveWatchpoint(index)) => {
+                        chip8.debugger_mut().remove_watchpoint(index);
+                    }
+                    Ok(Message::AddRegisterWatch(watch)) => {
+                        chip8.debugger_mut().add_register_watch(watch);
+                    }
+                    Ok(Message::RemoveRegisterWatch(index)) => {
+                        chip8.debugger_mut().remove_register_watch(index);
+                    }
+                    Ok(Message::AddCondition(condition)) => {
+                        chip8.debugger_mut().add_condition(condition);
+                    }
+                    Ok(Message::RemoveCondition(index)) => {
+                        chip8.debugger_mut().remove_condition(index);
+                    }
+                    Ok(Message::RunTo(addr)) => {
+                        if chip8.is_paused() {
+                            scheduler.resync(Instant::now());
+                        }
+                        chip8.run_to(addr);
+                    }
+                    Ok(Message::StartTrace(config)) => {
+                        let error = chip8.start_trace(config).err().map(|err| err.to_string());
+                        channel_1.send(Message::TraceStatus(chip8.is_tracing(), error));
+                    }
+                    Ok(Message::StopTrace) => {
+                        let error = chip8.stop_trace().err().map(|err| err.to_string());
+                        channel_1.send(Message::TraceStatus(false, error));
+                    }
+                    Ok(Message::StartGdbServer(port)) => match GdbServer::bind(port) {
+                        Ok(server) => {
+                            let status = format!("Listening on 127.0.0.1:{}", server.port());
+                            gdb = Some(server);
+                            channel_1.send(Message::GdbStatus(true, status));
+                        }
+                        Err(err) => {
+                            let status = format!("Couldn't listen on port {port}: {err}");
+                            channel_1.send(Message::GdbStatus(false, status));
+                        }
+                    },
+                    Ok(Message::StopGdbServer) => {
+                        gdb = None;
+                        channel_1.send(Message::GdbStatus(false, "Stopped".to_string()));
+                    }
+                    Ok(Message::SetProfiling(profiling)) => {
+                        chip8.set_profiling(profiling);
+                    }
+                    Ok(Message::ResetProfile) => {
+                        chip8.reset_profile();
+                        profile_sent = None;
+                    }
+                    Ok(Message::RequestProfile) => {
+                        // Copying the profile isn't free, so only when there's something new
+                        let total = chip8.profile().total();
+                        if profile_sent != Some(total) {
+                            profile_sent = Some(total);
+                            channel_1.send(Message::Profile(
+                                Box::new(chip8.profile().clone()),
+                                chip8.symbols().clone(),
+                            ));
+                        }
+                    }
+                    Ok(Message::SetCoverage(recording)) => {
+                        chip8.set_recording_coverage(recording);
+                    }
+                    Ok(Message::ResetCoverage) => {
+                        chip8.reset_coverage();
+                    }
+                    Ok(Message::SetSymbols(symbols)) => {
+                        chip8.set_symbols(symbols);
+                        profile_sent = None;
+                    }
+                    Ok(Message::Step) if chip8.is_paused() => {
+                        if let Ok(true) = chip8.step() {
+                            publish_frame(&mut published, &channel_1, &chip8);
+                        }
+                        if let Some(reason) = chip8.check_break() {
+                            channel_1.send(Message::Break(reason));
+                        }
                     }
                     _ => {}
                 }
 
+                if let Some(ref mut server) = gdb {
+                    match server.poll(&mut chip8) {
+                        GdbAction::None => {}
+                        GdbAction::Attach => {
+                            if !chip8.is_paused() {
+                                chip8.pause();
+                            }
+                            channel_1.send(Message::GdbStatus(true, "Client attached".to_string()));
+                        }
+                        GdbAction::Continue => {
+                            if chip8.is_paused() {
+                                scheduler.resync(Instant::now());
+                                chip8.unpause();
+                            }
+                        }
+                        GdbAction::Step => {
+                            if let Ok(true) = chip8.step() {
+                                publish_frame(&mut published, &channel_1, &chip8);
+                            }
+                            if let Some(reason) = chip8.check_break() {
+                                channel_1.send(Message::Break(reason));
+                            }
+                            server.report_stop(SIGTRAP);
+                        }
+                        GdbAction::Interrupt => {
+                            if !chip8.is_paused() {
+                                chip8.pause();
+                            }
+                            server.report_stop(SIGINT);
+                        }
+                        GdbAction::Detach => {
+                            if chip8.is_paused() {
+                                scheduler.resync(Instant::now());
+                                chip8.unpause();
+                            }
+                            let status = format!("Listening on 127.0.0.1:{}", server.port());
+                            channel_1.send(Message::GdbStatus(true, status));
+                        }
+                    }
+                }
+
                 if !chip8.is_paused() {
-                    let now = Instant::now();
+                    let due = scheduler.due_frames(Instant::now());
 
-                    if last_update_60hz.elapsed() >= tick_60hz {
-                        chip8.tick_60hz();
-                        last_update_60hz = Instant::now();
-                    }
+                    'frames: for _ in 0..due {
+                        if rewinding {
+                            if let Some(state) = history.pop_back() {
+                                chip8.load_state(state);
+                                frame_dirty = true;
+                            }
+                        } else {
+                            scheduler.begin_frame();
 
-                    if let Ok(true) = chip8.tick() {
-                        channel_1.send(Message::Draw);
-                    }
+                            while let Ok(instruction) = chip8.next_instruction()
+                                && scheduler.admit(&instruction, chip8.cpu())
+                            {
+                                if let Some(reason) = chip8.check_break() {
+                                    chip8.pause();
+                                    // Show sprites drawn earlier in this frame, which would
+                                    // otherwise only be published at its vblank
+                                    publish_frame(&mut published, &channel_1, &chip8);
+                                    frame_dirty = false;
+                                    channel_1.send(Message::Break(reason));
+                                    if let Some(ref mut server) = gdb {
+                                        server.report_stop(SIGTRAP);
+                                    }
+                                    break 'frames;
+                                }
+
+                                if let Ok(true) = chip8.tick() {
+                                    if flicker_mode.draws_immediately() {
+                                        publish_frame(&mut published, &channel_1, &chip8);
+                                    } else {
+                                        frame_dirty = true;
+                                    }
+                                }
+                            }
+
+                            // Timers count down once per frame, at vblank
+                            chip8.tick_60hz();
 
-                    while now.elapsed() < tick_rate {
-                        spin_loop();
+                            frames = frames.wrapping_add(1);
+                            if frames.is_multiple_of(REWIND_INTERVAL) {
+                                if history.len() == REWIND_CAPACITY {
+                                    history.pop_front();
+                                }
+                                history.push_back(chip8.to_chip8_state());
+                            }
+                        }
+
+                        if frame_dirty || flicker_mode.redraws_every_frame() {
+                            publish_frame(&mut published, &channel_1, &chip8);
+                            frame_dirty = false;
+                        }
                     }
                 }
+
+                if let Some(err) = chip8.take_trace_error() {
+                    channel_1.send(Message::TraceStatus(false, Some(err.to_string())));
+                }
             }
         });
 
-        Self {
+        Ok(Self {
             handle: Some(handle),
             channel: Some(channel_2),
-        }
+            frames,
+        })
+    }
+
+    pub fn try_recv(&self) -> Option<Message> {
+        self.channel.as_ref()?.try_recv().ok()
+    }
+
+    /// The display, if it changed since the last call.
+    pub fn take_frame(&mut self) -> Option<FrameBuffer> {
+        self.frames.read()
     }
 
-    pub fn check_draw_message(&self) -> bool {
+    /// Has the emulator thread call `waker` whenever there's a new frame or message.
+    pub fn set_waker(&self, waker: impl Fn() + Send + 'static) {
+        self.send(Message::SetWaker(Box::new(waker)));
+    }
+
+    pub fn send_key_press_message(&self, key: Chip8Key) {
         if let Some(ref channel) = self.channel {
-            if let Ok(Message::Draw) = channel.try_recv() {
-                return true;
-            }
+            channel.send(Message::KeyPressed(key));
         }
-        false
     }
 
     pub fn send_key_release_message(&self, key: Chip8Key) {
@@ -129,6 +420,48 @@ impl Chip8Handle {
         }
     }
 
+    pub fn send_flicker_mode_message(&self, mode: FlickerMode) {
+        if let Some(ref channel) = self.channel {
+            channel.send(Message::SetFlickerMode(mode));
+        }
+    }
+
+    pub fn request_snapshot(&self) {
+        if let Some(ref channel) = self.channel {
+            channel.send(Message::RequestSnapshot);
+        }
+    }
+
+    pub fn write_memory(&self, addr: u16, byte: u8) {
+        if let Some(ref channel) = self.channel {
+            channel.send(Message::WriteMemory(addr, byte));
+        }
+    }
+
+    pub fn toggle_breakpoint(&self, addr: u16) {
+        if let Some(ref channel) = self.channel {
+            channel.send(Message::ToggleBreakpoint(addr));
+        }
+    }
+
+    pub fn run_to(&self, addr: u16) {
+        if let Some(ref channel) = self.channel {
+            channel.send(Message::RunTo(addr));
+        }
+    }
+
+    pub fn send(&self, message: Message) {
+        if let Some(ref channel) = self.channel {
+            channel.send(message);
+        }
+    }
+
+    pub fn step(&self) {
+        if let Some(ref channel) = self.channel {
+            channel.send(Message::Step);
+        }
+    }
+
     pub fn save(&self, path: String) {
         if let Some(ref channel) = self.channel {
             channel.send(Message::Save(path));
@@ -0,0 +1,437 @@
+use std::{
+    fmt::Write as _,
+    fs,
+    path::Path,
+    sync::{Arc, Mutex},
+};
+
+use crate::{
+    chip8::Chip8,
+    cpu::Cpu,
+    frame_buffer::{FRAME_BUFFER_COLS, FRAME_BUFFER_ROWS},
+    instruction::Instruction,
+    key_matrix::{Chip8Key, KeyMatrix},
+    key_wait::KeyEvent,
+    scheduler::{DEFAULT_IPS, FrameScheduler},
+    screenshot,
+    symbols::Symbols,
+    tracer::TraceConfig,
+};
+
+pub const USAGE: &str = "usage: chip8 run <rom.ch8> [--frames N] [--ips N] [--keys script.txt] \
+[--dump-frame out.png] [--dump-state out.sav] [--expect-hash HASH] [--trace out.log]";
+
+pub const EXIT_OK: i32 = 0;
+/// The program hit an unknown instruction or a CPU error.
+pub const EXIT_FAULT: i32 = 1;
+/// Bad arguments, or an input or output file couldn't be used.
+pub const EXIT_USAGE: i32 = 2;
+/// The program ran, but the final frame didn't have the expected hash.
+pub const EXIT_MISMATCH: i32 = 3;
+
+const DEFAULT_FRAMES: u32 = 300;
+
+/// Options for `chip8 run`.
+pub struct RunOptions {
+    pub rom: String,
+    pub frames: u32,
+    pub ips: u32,
+    pub keys: Option<String>,
+    pub dump_frame: Option<String>,
+    pub dump_state: Option<String>,
+    pub expect_hash: Option<u64>,
+    pub trace: Option<String>,
+}
+
+impl RunOptions {
+    /// Parses the arguments after `run`.
+    pub fn parse(args: &[String]) -> Result<Self, String> {
+        let mut rom = None;
+        let mut options = Self {
+            rom: String::new(),
+            frames: DEFAULT_FRAMES,
+            ips: DEFAULT_IPS,
+            keys: None,
+            dump_frame: None,
+            dump_state: None,
+            expect_hash: None,
+            trace: None,
+        };
+
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            let mut value = || {
+                args.next()
+                    .cloned()
+                    .ok_or_else(|| format!("{arg} needs a value"))
+            };
+
+            match arg.as_str() {
+                "--frames" => options.frames = parse_number(arg, &value()?)?,
+                "--ips" => options.ips = parse_number(arg, &value()?)?,
+                "--keys" => options.keys = Some(value()?),
+                "--dump-frame" => options.dump_frame = Some(value()?),
+                "--dump-state" => options.dump_state = Some(value()?),
+                "--expect-hash" => {
+                    let hash = value()?;
+                    let digits = hash.trim_start_matches("0x");
+                    options.expect_hash = Some(
+                        u64::from_str_radix(digits, 16)
+                            .map_err(|_| format!("--expect-hash: `{hash}` isn't a hex hash"))?,
+                    );
+                }
+                "--trace" => options.trace = Some(value()?),
+                _ if arg.starts_with("--") => return Err(format!("unknown option {arg}")),
+                _ if rom.is_none() => rom = Some(arg.clone()),
+                _ => return Err(format!("unexpected argument {arg}")),
+            }
+        }
+
+        options.rom = rom.ok_or("no ROM given")?;
+        Ok(options)
+    }
+}
+
+fn parse_number(option: &str, value: &str) -> Result<u32, String> {
+    value
+        .parse()
+        .map_err(|_| format!("{option}: `{value}` isn't a number"))
+}
+
+/// Parses a key script: one `<frame> press|release <key>` per line, with the key as a
+/// hex digit, e.g. `120 press 5`. Events apply at the start of their frame, counting from
+/// 0. Blank lines and lines starting with `#` are skipped.
+pub fn parse_key_script(text: &str) -> Result<Vec<(u32, KeyEvent)>, String> {
+    let mut events = Vec::new();
+
+    for (index, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let error = |message: &str| format!("line {}: {message}", index + 1);
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        let [frame, action, key] = fields[..] else {
+            return Err(error("expected `<frame> press|release <key>`"));
+        };
+
+        let frame = frame.parse().map_err(|_| error("bad frame number"))?;
+        let key = u8::from_str_radix(key, 16)
+            .ok()
+            .and_then(|key| Chip8Key::try_from(key).ok())
+            .ok_or_else(|| error("key must be a hex digit 0-F"))?;
+        let event = match action {
+            "press" => KeyEvent::Press(key),
+            "release" => KeyEvent::Release(key),
+            _ => return Err(error("action must be `press` or `release`")),
+        };
+
+        events.push((frame, event));
+    }
+
+    events.sort_by_key(|&(frame, _)| frame);
+    Ok(events)
+}
+
+/// Entry point for `chip8 run`; returns the process exit code.
+pub fn main(args: &[String]) -> i32 {
+    match RunOptions::parse(args) {
+        Ok(options) => run(&options),
+        Err(err) => {
+            eprintln!("{err}\n{USAGE}");
+            EXIT_USAGE
+        }
+    }
+}
+
+/// Runs the ROM for the configured number of frames as fast as possible, then prints a
+/// register dump and writes the requested outputs.
+pub fn run(options: &RunOptions) -> i32 {
+    let key_matrix = Arc::new(Mutex::new(KeyMatrix::new()));
+    let mut chip8 = Chip8::new(key_matrix.clone());
+
+    if let Err(err) = chip8.load_rom(&options.rom) {
+        eprintln!("Couldn't read {}: {err}", options.rom);
+        return EXIT_USAGE;
+    }
+    if let Some(symbols) = Symbols::discover(&options.rom) {
+        chip8.set_symbols(Arc::new(symbols));
+    }
+
+    let keys = match options.keys.as_ref().map(|path| {
+        fs::read_to_string(path)
+            .map_err(|err| err.to_string())
+            .and_then(|text| parse_key_script(&text))
+            .map_err(|err| format!("{path}: {err}"))
+    }) {
+        Some(Ok(keys)) => keys,
+        Some(Err(err)) => {
+            eprintln!("Couldn't load key script {err}");
+            return EXIT_USAGE;
+        }
+        None => Vec::new(),
+    };
+
+    if let Some(ref path) = options.trace {
+        let config = TraceConfig {
+            path: path.clone(),
+            range: None,
+            ring_size: None,
+        };
+        if let Err(err) = chip8.start_trace(config) {
+            eprintln!("Couldn't write {path}: {err}");
+            return EXIT_USAGE;
+        }
+    }
+
+    let mut scheduler = FrameScheduler::new();
+    scheduler.set_ips(options.ips);
+
+    let mut keys = keys.into_iter().peekable();
+    let mut frames = 0;
+    let mut instructions: u64 = 0;
+    let mut fault = None;
+
+    'frames: while frames < options.frames {
+        while let Some((_, event)) = keys.next_if(|&(frame, _)| frame <= frames) {
+            match event {
+                KeyEvent::Press(key) => key_matrix.lock().unwrap().press(key),
+                KeyEvent::Release(key) => key_matrix.lock().unwrap().release(key),
+            }
+            chip8.key_event(event);
+        }
+
+        scheduler.begin_frame();
+
+        loop {
+            let pc = chip8.cpu().pc();
+            let instruction = match chip8.next_instruction() {
+                Ok(instruction) => instruction,
+                Err(err) => {
+                    fault = Some(format!("{err} fetching at {pc:03X}"));
+                    break 'frames;
+                }
+            };
+            if !scheduler.admit(&instruction, chip8.cpu()) {
+                break;
+            }
+            if let Instruction::Unknown { instruction } = instruction {
+                fault = Some(format!("unknown instruction {instruction:04X} at {pc:03X}"));
+                break 'frames;
+            }
+            if let Err(err) = chip8.tick() {
+                fault = Some(format!("{err} at {pc:03X} ({instruction})"));
+                break 'frames;
+            }
+            instructions += 1;
+        }
+
+        chip8.tick_60hz();
+        frames += 1;
+    }
+
+    if let Some(err) = chip8.take_trace_error() {
+        eprintln!("Trace stopped early: {err}");
+    }
+    if let Err(err) = chip8.stop_trace() {
+        eprintln!("Couldn't finish trace: {err}");
+    }
+
+    let hash = chip8.frame_buffer().hash();
+    print!("{}", register_dump(chip8.cpu()));
+    println!("frames: {frames}  instructions: {instructions}");
+    println!("frame hash: {hash:016x}");
+
+    let mut code = EXIT_OK;
+
+    if let Some(ref path) = options.dump_frame {
+        let rgb: Vec<u8> = chip8
+            .frame_buffer()
+            .pixels()
+            .flat_map(|on| if on { [0xFF; 3] } else { [0x00; 3] })
+            .collect();
+        let (width, height) = (FRAME_BUFFER_COLS, FRAME_BUFFER_ROWS);
+        if let Err(err) = screenshot::write_png(Path::new(path), width, height, &rgb) {
+            eprintln!("Couldn't write {path}: {err}");
+            code = EXIT_USAGE;
+        }
+    }
+    if let Some(ref path) = options.dump_state
+        && let Err(err) = chip8.to_chip8_state().write(path)
+    {
+        eprintln!("Couldn't write {path}: {err}");
+        code = EXIT_USAGE;
+    }
+
+    if let Some(expected) = options.expect_hash
+        && expected != hash
+    {
+        eprintln!("Frame hash mismatch: expected {expected:016x}, got {hash:016x}");
+        code = EXIT_MISMATCH;
+    }
+    if let Some(fault) = fault {
+        eprintln!("Fault: {fault}");
+        code = EXIT_FAULT;
+    }
+
+    code
+}
+
+fn register_dump(cpu: &Cpu) -> String {
+    let mut dump = format!(
+        "PC={:03X} I={:03X} SP={:X} DT={:02X} ST={:02X}\n",
+        cpu.pc(),
+        cpu.i(),
+        cpu.sp(),
+        cpu.dt(),
+        cpu.st()
+    );
+
+    for (index, value) in cpu.v().iter().enumerate() {
+        let separator = if index % 8 == 7 { '\n' } else { ' ' };
+        let _ = write!(dump, "V{index:X}={value:02X}{separator}");
+    }
+
+    let _ = write!(dump, "stack:");
+    for addr in &cpu.stack()[..(cpu.sp() as usize).min(cpu.stack().len())] {
+        let _ = write!(dump, " {addr:03X}");
+    }
+    dump.push('\n');
+
+    dump
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{chip8_state::Chip8State, frame_buffer::FrameBuffer};
+
+    const IBM_LOGO: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/rom/ibm_logo.ch8");
+
+    fn args(text: &str) -> Vec<String> {
+        text.split_whitespace().map(str::to_string).collect()
+    }
+
+    /// A path in the temp directory that other test runs won't touch.
+    fn scratch(name: &str) -> String {
+        let dir = std::env::temp_dir();
+        let path = dir.join(format!("chip8-headless-{}-{name}", std::process::id()));
+        path.display().to_string()
+    }
+
+    fn options(rom: &str) -> RunOptions {
+        RunOptions::parse(&[rom.to_string(), "--frames".to_string(), "30".to_string()]).unwrap()
+    }
+
+    #[test]
+    fn parses_options() {
+        let options = RunOptions::parse(&args(
+            "game.ch8 --frames 60 --ips 1000 --keys k.txt --dump-frame f.png \
+             --dump-state s.sav --expect-hash 0xDEADBEEF --trace t.log",
+        ))
+        .unwrap();
+
+        assert_eq!(options.rom, "game.ch8");
+        assert_eq!(options.frames, 60);
+        assert_eq!(options.ips, 1000);
+        assert_eq!(options.keys.as_deref(), Some("k.txt"));
+        assert_eq!(options.dump_frame.as_deref(), Some("f.png"));
+        assert_eq!(options.dump_state.as_deref(), Some("s.sav"));
+        assert_eq!(options.expect_hash, Some(0xDEAD_BEEF));
+        assert_eq!(options.trace.as_deref(), Some("t.log"));
+
+        let options = RunOptions::parse(&args("game.ch8")).unwrap();
+        assert_eq!(options.frames, DEFAULT_FRAMES);
+        assert_eq!(options.ips, DEFAULT_IPS);
+        assert!(options.expect_hash.is_none());
+    }
+
+    #[test]
+    fn rejects_bad_options() {
+        let error = |text| RunOptions::parse(&args(text)).err().unwrap();
+
+        assert_eq!(error(""), "no ROM given");
+        assert_eq!(error("a.ch8 b.ch8"), "unexpected argument b.ch8");
+        assert_eq!(error("a.ch8 --fast"), "unknown option --fast");
+        assert_eq!(error("a.ch8 --frames"), "--frames needs a value");
+        assert_eq!(error("a.ch8 --ips many"), "--ips: `many` isn't a number");
+        assert_eq!(
+            error("a.ch8 --expect-hash xyz"),
+            "--expect-hash: `xyz` isn't a hex hash"
+        );
+    }
+
+    #[test]
+    fn parses_key_scripts() {
+        let events = parse_key_script(
+            "# jump, then fire\n\
+             \n\
+             120 release 5\n\
+             60 press 5\n\
+             60 press a\n",
+        )
+        .unwrap();
+
+        assert!(
+            events
+                == [
+                    (60, KeyEvent::Press(Chip8Key::K5)),
+                    (60, KeyEvent::Press(Chip8Key::KA)),
+                    (120, KeyEvent::Release(Chip8Key::K5)),
+                ]
+        );
+    }
+
+    #[test]
+    fn key_script_errors_name_the_line() {
+        let error = |text| parse_key_script(text).err().unwrap();
+
+        assert_eq!(
+            error("1 press 1\n2 press"),
+            "line 2: expected `<frame> press|release <key>`"
+        );
+        assert_eq!(error("soon press 1"), "line 1: bad frame number");
+        assert_eq!(
+            error("# comment\n1 press G"),
+            "line 2: key must be a hex digit 0-F"
+        );
+        assert_eq!(
+            error("1 hold 1"),
+            "line 1: action must be `press` or `release`"
+        );
+    }
+
+    #[test]
+    fn exit_codes() {
+        // The hash to expect, read back from the state the first run dumps
+        let state = scratch("ibm.sav");
+        let mut first = options(IBM_LOGO);
+        first.dump_state = Some(state.clone());
+        assert_eq!(run(&first), EXIT_OK);
+        let hash = Chip8State::load(&state).unwrap().frame_buffer.hash();
+        let _ = fs::remove_file(&state);
+        assert_ne!(
+            hash,
+            FrameBuffer::new().hash(),
+            "the logo should be on screen"
+        );
+
+        let mut matching = options(IBM_LOGO);
+        matching.expect_hash = Some(hash);
+        assert_eq!(run(&matching), EXIT_OK);
+
+        let mut mismatched = options(IBM_LOGO);
+        mismatched.expect_hash = Some(hash ^ 1);
+        assert_eq!(run(&mismatched), EXIT_MISMATCH);
+
+        // `FFFF` isn't an instruction
+        let rom = scratch("fault.ch8");
+        fs::write(&rom, [0xFF, 0xFF]).unwrap();
+        let code = run(&options(&rom));
+        let _ = fs::remove_file(&rom);
+        assert_eq!(code, EXIT_FAULT);
+
+        assert_eq!(run(&options(&scratch("missing.ch8"))), EXIT_USAGE);
+    }
+}
@@ -1,4 +1,6 @@
-#[derive(Debug)]
+use std::fmt;
+
+#[derive(Debug, Clone, Copy)]
 pub enum Instruction {
     Cls,
     Ret,
@@ -37,160 +39,182 @@ pub enum Instruction {
     Unknown { instruction: u16 },
 }
 
+/// Decodes by nibbles in a single `match`, which compiles to a jump table on the top nibble.
 impl From<u16> for Instruction {
     fn from(opcode: u16) -> Self {
-        if opcode == 0x00E0 {
-            Instruction::Cls
-        } else if opcode == 0x00EE {
-            Instruction::Ret
-        } else if opcode >> 12 == 0x1 {
-            let addr = opcode & 0xFFF;
-
-            Instruction::Jp { addr }
-        } else if opcode >> 12 == 0xB {
-            let addr = opcode & 0xFFF;
-
-            Instruction::JpV0 { addr }
-        } else if opcode >> 12 == 0x2 {
-            let addr = opcode & 0xFFF;
-
-            Instruction::Call { addr }
-        } else if opcode >> 12 == 0x6 {
-            let vx = ((opcode >> 8) & 0x0F) as u8;
-            let byte = (opcode & 0xFF) as u8;
-
-            Instruction::LdByte { vx, byte }
-        } else if opcode >> 12 == 0x7 {
-            let vx = ((opcode >> 8) & 0x0F) as u8;
-            let byte = (opcode & 0xFF) as u8;
-
-            Instruction::AddByte { vx, byte }
-        } else if opcode >> 12 == 0xF && opcode & 0xFF == 0x1E {
-            let vx = ((opcode >> 8) & 0x0F) as u8;
-
-            Instruction::AddI { vx }
-        } else if opcode >> 12 == 0xA {
-            let addr = opcode & 0xFFF;
-
-            Instruction::LdI { addr }
-        } else if opcode >> 12 == 0xD {
-            let vx = ((opcode >> 8) & 0x0F) as u8;
-            let vy = ((opcode >> 4) & 0x0F) as u8;
-            let nibble = (opcode & 0x0F) as u8;
-
-            Instruction::Drw { vx, vy, nibble }
-        } else if opcode >> 12 == 0x3 {
-            let vx = ((opcode >> 8) & 0x0F) as u8;
-            let byte = (opcode & 0xFF) as u8;
-
-            Instruction::SeByte { vx, byte }
-        } else if opcode >> 12 == 0x4 {
-            let vx = ((opcode >> 8) & 0x0F) as u8;
-            let byte = (opcode & 0xFF) as u8;
-
-            Instruction::SneByte { vx, byte }
-        } else if opcode >> 12 == 0xC {
-            let vx = ((opcode >> 8) & 0x0F) as u8;
-            let byte = (opcode & 0xFF) as u8;
-
-            Instruction::Rnd { vx, byte }
-        } else if opcode >> 12 == 0x5 && opcode & 0x0F == 0x0 {
-            let vx = ((opcode >> 8) & 0x0F) as u8;
-            let vy = ((opcode >> 4) & 0x0F) as u8;
-
-            Instruction::SeReg { vx, vy }
-        } else if opcode >> 12 == 0x9 && opcode & 0x0F == 0x0 {
-            let vx = ((opcode >> 8) & 0x0F) as u8;
-            let vy = ((opcode >> 4) & 0x0F) as u8;
-
-            Instruction::SneReg { vx, vy }
-        } else if opcode >> 12 == 0x8 && opcode & 0x0F == 0x0 {
-            let vx = ((opcode >> 8) & 0x0F) as u8;
-            let vy = ((opcode >> 4) & 0x0F) as u8;
-
-            Instruction::LdReg { vx, vy }
-        } else if opcode >> 12 == 0x8 && opcode & 0x0F == 0x1 {
-            let vx = ((opcode >> 8) & 0x0F) as u8;
-            let vy = ((opcode >> 4) & 0x0F) as u8;
-
-            Instruction::OrReg { vx, vy }
-        } else if opcode >> 12 == 0x8 && opcode & 0x0F == 0x2 {
-            let vx = ((opcode >> 8) & 0x0F) as u8;
-            let vy = ((opcode >> 4) & 0x0F) as u8;
-
-            Instruction::AndReg { vx, vy }
-        } else if opcode >> 12 == 0x8 && opcode & 0x0F == 0x3 {
-            let vx = ((opcode >> 8) & 0x0F) as u8;
-            let vy = ((opcode >> 4) & 0x0F) as u8;
-
-            Instruction::XorReg { vx, vy }
-        } else if opcode >> 12 == 0x8 && opcode & 0x0F == 0x4 {
-            let vx = ((opcode >> 8) & 0x0F) as u8;
-            let vy = ((opcode >> 4) & 0x0F) as u8;
-
-            Instruction::AddRegCarry { vx, vy }
-        } else if opcode >> 12 == 0x8 && opcode & 0x0F == 0x5 {
-            let vx = ((opcode >> 8) & 0x0F) as u8;
-            let vy = ((opcode >> 4) & 0x0F) as u8;
-
-            Instruction::SubReg { vx, vy }
-        } else if opcode >> 12 == 0x8 && opcode & 0x0F == 0x6 {
-            let vx = ((opcode >> 8) & 0x0F) as u8;
-
-            Instruction::Shr { vx }
-        } else if opcode >> 12 == 0x8 && opcode & 0x0F == 0x7 {
-            let vx = ((opcode >> 8) & 0x0F) as u8;
-            let vy = ((opcode >> 4) & 0x0F) as u8;
-
-            Instruction::SubNReg { vx, vy }
-        } else if opcode >> 12 == 0x8 && opcode & 0x0F == 0xE {
-            let vx = ((opcode >> 8) & 0x0F) as u8;
-
-            Instruction::Shl { vx }
-        } else if opcode >> 12 == 0xE && opcode & 0xFF == 0x9E {
-            let vx = ((opcode >> 8) & 0x0F) as u8;
-
-            Instruction::Skp { vx }
-        } else if opcode >> 12 == 0xE && opcode & 0xFF == 0xA1 {
-            let vx = ((opcode >> 8) & 0x0F) as u8;
-
-            Instruction::Sknp { vx }
-        } else if opcode >> 12 == 0xF && opcode & 0xFF == 0x0A {
-            let vx = ((opcode >> 8) & 0x0F) as u8;
-
-            Instruction::KeyWait { vx }
-        } else if opcode >> 12 == 0xF && opcode & 0xFF == 0x55 {
-            let vx = ((opcode >> 8) & 0x0F) as u8;
+        let addr = opcode & 0xFFF;
+        let vx = ((opcode >> 8) & 0x0F) as u8;
+        let vy = ((opcode >> 4) & 0x0F) as u8;
+        let nibble = (opcode & 0x0F) as u8;
+        let byte = (opcode & 0xFF) as u8;
+
+        match (opcode >> 12, nibble, byte) {
+            (0x0, _, 0xE0) if vx == 0 => Instruction::Cls,
+            (0x0, _, 0xEE) if vx == 0 => Instruction::Ret,
+            (0x1, _, _) => Instruction::Jp { addr },
+            (0x2, _, _) => Instruction::Call { addr },
+            (0x3, _, _) => Instruction::SeByte { vx, byte },
+            (0x4, _, _) => Instruction::SneByte { vx, byte },
+            (0x5, 0x0, _) => Instruction::SeReg { vx, vy },
+            (0x6, _, _) => Instruction::LdByte { vx, byte },
+            (0x7, _, _) => Instruction::AddByte { vx, byte },
+            (0x8, 0x0, _) => Instruction::LdReg { vx, vy },
+            (0x8, 0x1, _) => Instruction::OrReg { vx, vy },
+            (0x8, 0x2, _) => Instruction::AndReg { vx, vy },
+            (0x8, 0x3, _) => Instruction::XorReg { vx, vy },
+            (0x8, 0x4, _) => Instruction::AddRegCarry { vx, vy },
+            (0x8, 0x5, _) => Instruction::SubReg { vx, vy },
+            (0x8, 0x6, _) => Instruction::Shr { vx },
+            (0x8, 0x7, _) => Instruction::SubNReg { vx, vy },
+            (0x8, 0xE, _) => Instruction::Shl { vx },
+            (0x9, 0x0, _) => Instruction::SneReg { vx, vy },
+            (0xA, _, _) => Instruction::LdI { addr },
+            (0xB, _, _) => Instruction::JpV0 { addr },
+            (0xC, _, _) => Instruction::Rnd { vx, byte },
+            (0xD, _, _) => Instruction::Drw { vx, vy, nibble },
+            (0xE, _, 0x9E) => Instruction::Skp { vx },
+            (0xE, _, 0xA1) => Instruction::Sknp { vx },
+            (0xF, _, 0x07) => Instruction::LdRegDt { vx },
+            (0xF, _, 0x0A) => Instruction::KeyWait { vx },
+            (0xF, _, 0x15) => Instruction::LdDt { vx },
+            (0xF, _, 0x18) => Instruction::LdSt { vx },
+            (0xF, _, 0x1E) => Instruction::AddI { vx },
+            (0xF, _, 0x29) => Instruction::LdFont { vx },
+            (0xF, _, 0x33) => Instruction::StoreBcd { vx },
+            (0xF, _, 0x55) => Instruction::Store { vx },
+            (0xF, _, 0x65) => Instruction::Read { vx },
+            _ => Instruction::Unknown {
+                instruction: opcode,
+            },
+        }
+    }
+}
 
-            Instruction::Store { vx }
-        } else if opcode >> 12 == 0xF && opcode & 0xFF == 0x65 {
-            let vx = ((opcode >> 8) & 0x0F) as u8;
+/// Cowgod-style mnemonics, e.g. `LD VA, 0x02` or `DRW V0, V1, 5`.
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Instruction::Cls => write!(f, "CLS"),
+            Instruction::Ret => write!(f, "RET"),
+            Instruction::Jp { addr } => write!(f, "JP 0x{addr:03X}"),
+            Instruction::JpV0 { addr } => write!(f, "JP V0, 0x{addr:03X}"),
+            Instruction::LdI { addr } => write!(f, "LD I, 0x{addr:03X}"),
+            Instruction::LdByte { vx, byte } => write!(f, "LD V{vx:X}, 0x{byte:02X}"),
+            Instruction::LdReg { vx, vy } => write!(f, "LD V{vx:X}, V{vy:X}"),
+            Instruction::LdRegDt { vx } => write!(f, "LD V{vx:X}, DT"),
+            Instruction::LdDt { vx } => write!(f, "LD DT, V{vx:X}"),
+            Instruction::LdSt { vx } => write!(f, "LD ST, V{vx:X}"),
+            Instruction::LdFont { vx } => write!(f, "LD F, V{vx:X}"),
+            Instruction::Rnd { vx, byte } => write!(f, "RND V{vx:X}, 0x{byte:02X}"),
+            Instruction::AddByte { vx, byte } => write!(f, "ADD V{vx:X}, 0x{byte:02X}"),
+            Instruction::AddI { vx } => write!(f, "ADD I, V{vx:X}"),
+            Instruction::AndReg { vx, vy } => write!(f, "AND V{vx:X}, V{vy:X}"),
+            Instruction::XorReg { vx, vy } => write!(f, "XOR V{vx:X}, V{vy:X}"),
+            Instruction::AddRegCarry { vx, vy } => write!(f, "ADD V{vx:X}, V{vy:X}"),
+            Instruction::SubReg { vx, vy } => write!(f, "SUB V{vx:X}, V{vy:X}"),
+            Instruction::SubNReg { vx, vy } => write!(f, "SUBN V{vx:X}, V{vy:X}"),
+            Instruction::Shr { vx } => write!(f, "SHR V{vx:X}"),
+            Instruction::Shl { vx } => write!(f, "SHL V{vx:X}"),
+            Instruction::OrReg { vx, vy } => write!(f, "OR V{vx:X}, V{vy:X}"),
+            Instruction::SeByte { vx, byte } => write!(f, "SE V{vx:X}, 0x{byte:02X}"),
+            Instruction::SeReg { vx, vy } => write!(f, "SE V{vx:X}, V{vy:X}"),
+            Instruction::SneByte { vx, byte } => write!(f, "SNE V{vx:X}, 0x{byte:02X}"),
+            Instruction::SneReg { vx, vy } => write!(f, "SNE V{vx:X}, V{vy:X}"),
+            Instruction::Skp { vx } => write!(f, "SKP V{vx:X}"),
+            Instruction::Sknp { vx } => write!(f, "SKNP V{vx:X}"),
+            Instruction::KeyWait { vx } => write!(f, "LD V{vx:X}, K"),
+            Instruction::Store { vx } => write!(f, "LD [I], V{vx:X}"),
+            Instruction::StoreBcd { vx } => write!(f, "LD B, V{vx:X}"),
+            Instruction::Read { vx } => write!(f, "LD V{vx:X}, [I]"),
+            Instruction::Call { addr } => write!(f, "CALL 0x{addr:03X}"),
+            Instruction::Drw { vx, vy, nibble } => write!(f, "DRW V{vx:X}, V{vy:X}, {nibble}"),
+            Instruction::Unknown { instruction } => write!(f, "DW 0x{instruction:04X}"),
+        }
+    }
+}
 
-            Instruction::Read { vx }
-        } else if opcode >> 12 == 0xF && opcode & 0xFF == 0x33 {
-            let vx = ((opcode >> 8) & 0x0F) as u8;
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-            Instruction::StoreBcd { vx }
-        } else if opcode >> 12 == 0xF && opcode & 0xFF == 0x07 {
-            let vx = ((opcode >> 8) & 0x0F) as u8;
+    fn x(opcode: u16) -> u8 {
+        (opcode >> 8 & 0xF) as u8
+    }
 
-            Instruction::LdRegDt { vx }
-        } else if opcode >> 12 == 0xF && opcode & 0xFF == 0x29 {
-            let vx = ((opcode >> 8) & 0x0F) as u8;
+    fn y(opcode: u16) -> u8 {
+        (opcode >> 4 & 0xF) as u8
+    }
 
-            Instruction::LdFont { vx }
-        } else if opcode >> 12 == 0xF && opcode & 0xFF == 0x15 {
-            let vx = ((opcode >> 8) & 0x0F) as u8;
+    fn kk(opcode: u16) -> u8 {
+        opcode as u8
+    }
 
-            Instruction::LdDt { vx }
-        } else if opcode >> 12 == 0xF && opcode & 0xFF == 0x18 {
-            let vx = ((opcode >> 8) & 0x0F) as u8;
+    fn nnn(opcode: u16) -> u16 {
+        opcode & 0xFFF
+    }
 
-            Instruction::LdSt { vx }
-        } else {
-            Instruction::Unknown {
-                instruction: opcode,
-            }
+    type Decode = fn(u16) -> Instruction;
+
+    /// Cowgod's opcode list as (mask, value, instruction): an opcode is the instruction
+    /// when `opcode & mask == value`.
+    #[rustfmt::skip]
+    const OPCODES: [(u16, u16, Decode); 34] = [
+        (0xFFFF, 0x00E0, |_| Instruction::Cls),
+        (0xFFFF, 0x00EE, |_| Instruction::Ret),
+        (0xF000, 0x1000, |op| Instruction::Jp { addr: nnn(op) }),
+        (0xF000, 0x2000, |op| Instruction::Call { addr: nnn(op) }),
+        (0xF000, 0x3000, |op| Instruction::SeByte { vx: x(op), byte: kk(op) }),
+        (0xF000, 0x4000, |op| Instruction::SneByte { vx: x(op), byte: kk(op) }),
+        (0xF00F, 0x5000, |op| Instruction::SeReg { vx: x(op), vy: y(op) }),
+        (0xF000, 0x6000, |op| Instruction::LdByte { vx: x(op), byte: kk(op) }),
+        (0xF000, 0x7000, |op| Instruction::AddByte { vx: x(op), byte: kk(op) }),
+        (0xF00F, 0x8000, |op| Instruction::LdReg { vx: x(op), vy: y(op) }),
+        (0xF00F, 0x8001, |op| Instruction::OrReg { vx: x(op), vy: y(op) }),
+        (0xF00F, 0x8002, |op| Instruction::AndReg { vx: x(op), vy: y(op) }),
+        (0xF00F, 0x8003, |op| Instruction::XorReg { vx: x(op), vy: y(op) }),
+        (0xF00F, 0x8004, |op| Instruction::AddRegCarry { vx: x(op), vy: y(op) }),
+        (0xF00F, 0x8005, |op| Instruction::SubReg { vx: x(op), vy: y(op) }),
+        (0xF00F, 0x8006, |op| Instruction::Shr { vx: x(op) }),
+        (0xF00F, 0x8007, |op| Instruction::SubNReg { vx: x(op), vy: y(op) }),
+        (0xF00F, 0x800E, |op| Instruction::Shl { vx: x(op) }),
+        (0xF00F, 0x9000, |op| Instruction::SneReg { vx: x(op), vy: y(op) }),
+        (0xF000, 0xA000, |op| Instruction::LdI { addr: nnn(op) }),
+        (0xF000, 0xB000, |op| Instruction::JpV0 { addr: nnn(op) }),
+        (0xF000, 0xC000, |op| Instruction::Rnd { vx: x(op), byte: kk(op) }),
+        (0xF000, 0xD000, |op| Instruction::Drw { vx: x(op), vy: y(op), nibble: (op & 0xF) as u8 }),
+        (0xF0FF, 0xE09E, |op| Instruction::Skp { vx: x(op) }),
+        (0xF0FF, 0xE0A1, |op| Instruction::Sknp { vx: x(op) }),
+        (0xF0FF, 0xF007, |op| Instruction::LdRegDt { vx: x(op) }),
+        (0xF0FF, 0xF00A, |op| Instruction::KeyWait { vx: x(op) }),
+        (0xF0FF, 0xF015, |op| Instruction::LdDt { vx: x(op) }),
+        (0xF0FF, 0xF018, |op| Instruction::LdSt { vx: x(op) }),
+        (0xF0FF, 0xF01E, |op| Instruction::AddI { vx: x(op) }),
+        (0xF0FF, 0xF029, |op| Instruction::LdFont { vx: x(op) }),
+        (0xF0FF, 0xF033, |op| Instruction::StoreBcd { vx: x(op) }),
+        (0xF0FF, 0xF055, |op| Instruction::Store { vx: x(op) }),
+        (0xF0FF, 0xF065, |op| Instruction::Read { vx: x(op) }),
+    ];
+
+    #[test]
+    fn decodes_every_opcode_like_the_table() {
+        for opcode in 0..=u16::MAX {
+            let mut matches = OPCODES
+                .iter()
+                .filter(|&&(mask, value, _)| opcode & mask == value);
+
+            let expected = match matches.next() {
+                Some((_, _, instruction)) => instruction(opcode),
+                None => Instruction::Unknown {
+                    instruction: opcode,
+                },
+            };
+            assert!(matches.next().is_none(), "{opcode:04X} is ambiguous");
+
+            assert_eq!(
+                format!("{:?}", Instruction::from(opcode)),
+                format!("{expected:?}"),
+                "{opcode:04X}"
+            );
         }
     }
 }
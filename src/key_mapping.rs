@@ -1,22 +1,42 @@
 use std::collections::HashMap;
 
-use eframe::egui::Key;
+use egui::Key;
+use serde::{Deserialize, Serialize};
 
 use crate::key_matrix::Chip8Key;
 
-pub struct KeyMapping {
-    map: HashMap<Key, Chip8Key>,
+/// A named set of bindings, as stored in the settings file.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct KeyProfile {
+    pub name: String,
+    /// Keys are stored by `Key::name` so the file doesn't depend on egui's enum layout.
+    pub bindings: Vec<(String, Chip8Key)>,
 }
 
-impl KeyMapping {
-    pub fn new() -> Self {
-        Self {
-            map: Self::default_map(),
-        }
+#[derive(Clone, Copy, PartialEq)]
+pub enum KeyPreset {
+    Default,
+    Arrows,
+    LeftHand,
+    TwoPlayer,
+}
+
+impl KeyPreset {
+    pub const ALL: [KeyPreset; 4] = [
+        KeyPreset::Default,
+        KeyPreset::Arrows,
+        KeyPreset::LeftHand,
+        KeyPreset::TwoPlayer,
+    ];
+
+    pub fn from_name(name: &str) -> Option<KeyPreset> {
+        Self::ALL
+            .into_iter()
+            .find(|preset| <&'static str>::from(*preset) == name)
     }
 
-    fn default_map() -> HashMap<Key, Chip8Key> {
-        let mut map = HashMap::with_capacity(16);
+    fn map(self) -> HashMap<Key, Chip8Key> {
+        let mut map = HashMap::with_capacity(24);
         map.insert(Key::Num1, Chip8Key::K1);
         map.insert(Key::Num2, Chip8Key::K2);
         map.insert(Key::Num3, Chip8Key::K3);
@@ -33,34 +53,132 @@ impl KeyMapping {
         map.insert(Key::X, Chip8Key::K0);
         map.insert(Key::C, Chip8Key::KB);
         map.insert(Key::V, Chip8Key::KF);
+
+        match self {
+            KeyPreset::Default => {}
+            // Most games move with 2/4/6/8 and act with 5
+            KeyPreset::Arrows => {
+                map.insert(Key::ArrowUp, Chip8Key::K2);
+                map.insert(Key::ArrowLeft, Chip8Key::K4);
+                map.insert(Key::ArrowRight, Chip8Key::K6);
+                map.insert(Key::ArrowDown, Chip8Key::K8);
+                map.insert(Key::Space, Chip8Key::K5);
+            }
+            KeyPreset::LeftHand => {
+                map.insert(Key::W, Chip8Key::K2);
+                map.insert(Key::A, Chip8Key::K4);
+                map.insert(Key::D, Chip8Key::K6);
+                map.insert(Key::S, Chip8Key::K8);
+                map.insert(Key::Space, Chip8Key::K5);
+                map.insert(Key::Q, Chip8Key::K7);
+                map.insert(Key::E, Chip8Key::K9);
+            }
+            // Paddle games: player one on 1/4, player two on C/D
+            KeyPreset::TwoPlayer => {
+                map.insert(Key::W, Chip8Key::K1);
+                map.insert(Key::S, Chip8Key::K4);
+                map.insert(Key::ArrowUp, Chip8Key::KC);
+                map.insert(Key::ArrowDown, Chip8Key::KD);
+            }
+        }
+
         map
     }
 
-    pub fn reset_keymap(&mut self) {
-        self.map = Self::default_map();
+    pub fn profile(self) -> KeyProfile {
+        KeyMapping::from_preset(self).to_profile(<&'static str>::from(self))
     }
+}
 
-    pub fn get_chip8_key(&self, key: &Key) -> Option<Chip8Key> {
-        self.map.get(key).copied()
+impl From<KeyPreset> for &'static str {
+    fn from(preset: KeyPreset) -> Self {
+        match preset {
+            KeyPreset::Default => "default",
+            KeyPreset::Arrows => "arrows",
+            KeyPreset::LeftHand => "left-hand",
+            KeyPreset::TwoPlayer => "two-player",
+        }
     }
+}
 
-    pub fn get_key(&self, chip8_key: Chip8Key) -> Option<Key> {
-        self.map
+/// Physical keys to CHIP-8 keys. A CHIP-8 key can have any number of keys bound to it,
+/// but each physical key drives at most one CHIP-8 key.
+pub struct KeyMapping {
+    map: HashMap<Key, Chip8Key>,
+}
+
+impl KeyMapping {
+    pub fn new() -> Self {
+        Self::from_preset(KeyPreset::Default)
+    }
+
+    pub fn from_preset(preset: KeyPreset) -> Self {
+        Self { map: preset.map() }
+    }
+
+    pub fn from_profile(profile: &KeyProfile) -> Self {
+        let map = profile
+            .bindings
             .iter()
-            .find_map(|(k, v)| if *v == chip8_key { Some(*k) } else { None })
+            .filter_map(|(name, chip8_key)| Some((Key::from_name(name)?, *chip8_key)))
+            .collect();
+
+        Self { map }
+    }
+
+    pub fn to_profile(&self, name: &str) -> KeyProfile {
+        let mut bindings = self
+            .map
+            .iter()
+            .map(|(key, chip8_key)| (key.name().to_string(), *chip8_key))
+            .collect::<Vec<_>>();
+        bindings.sort_by(|a, b| (u8::from(a.1), &a.0).cmp(&(u8::from(b.1), &b.0)));
+
+        KeyProfile {
+            name: name.to_string(),
+            bindings,
+        }
+    }
+
+    pub fn get_chip8_key(&self, key: &Key) -> Option<Chip8Key> {
+        self.map.get(key).copied()
     }
 
-    pub fn remap(&mut self, chip8_key: Chip8Key, key: Key) {
-        if let Some(old_key) = self
+    /// Every key bound to `chip8_key`, sorted by name.
+    pub fn get_keys(&self, chip8_key: Chip8Key) -> Vec<Key> {
+        let mut keys = self
             .map
             .iter()
-            .find_map(|(k, v)| if *v == chip8_key { Some(*k) } else { None })
-        {
-            self.map.remove(&old_key);
+            .filter(|&(_, v)| *v == chip8_key)
+            .map(|(k, _)| *k)
+            .collect::<Vec<_>>();
+        keys.sort_by_key(|k| k.name());
+        keys
+    }
+
+    /// Names of the keys bound to `chip8_key`, or `N/A`.
+    pub fn describe(&self, chip8_key: Chip8Key) -> String {
+        let keys = self.get_keys(chip8_key);
+        if keys.is_empty() {
+            return "N/A".to_string();
         }
 
+        keys.iter().map(|k| k.name()).collect::<Vec<_>>().join(", ")
+    }
+
+    /// The CHIP-8 key `key` already drives, if it isn't `chip8_key`.
+    pub fn conflict(&self, key: Key, chip8_key: Chip8Key) -> Option<Chip8Key> {
+        self.get_chip8_key(&key).filter(|&bound| bound != chip8_key)
+    }
+
+    /// Adds `key` as another binding for `chip8_key`, taking it from any other CHIP-8 key.
+    pub fn bind(&mut self, chip8_key: Chip8Key, key: Key) {
         self.map.insert(key, chip8_key);
     }
+
+    pub fn clear(&mut self, chip8_key: Chip8Key) {
+        self.map.retain(|_, v| *v != chip8_key);
+    }
 }
 
 impl Default for KeyMapping {
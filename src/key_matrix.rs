@@ -20,6 +20,14 @@ pub enum Chip8Key {
     KF,
 }
 
+/// The COSMAC VIP hex keypad, row by row.
+pub const KEY_LAYOUT: [[Chip8Key; 4]; 4] = [
+    [Chip8Key::K1, Chip8Key::K2, Chip8Key::K3, Chip8Key::KC],
+    [Chip8Key::K4, Chip8Key::K5, Chip8Key::K6, Chip8Key::KD],
+    [Chip8Key::K7, Chip8Key::K8, Chip8Key::K9, Chip8Key::KE],
+    [Chip8Key::KA, Chip8Key::K0, Chip8Key::KB, Chip8Key::KF],
+];
+
 impl From<Chip8Key> for u8 {
     fn from(key: Chip8Key) -> Self {
         key as Self
@@ -93,6 +101,14 @@ impl KeyMatrix {
         (self.bitmask >> u8::from(key)) & 1 == 1
     }
 
+    /// The lowest-numbered key held down, if any.
+    pub fn first_pressed(&self) -> Option<Chip8Key> {
+        if self.bitmask == 0 {
+            return None;
+        }
+        Chip8Key::try_from(self.bitmask.trailing_zeros() as u8).ok()
+    }
+
     pub fn press(&mut self, key: Chip8Key) {
         self.bitmask |= 1 << u8::from(key);
     }
@@ -0,0 +1,45 @@
+use serde::{Deserialize, Serialize};
+
+use crate::key_matrix::Chip8Key;
+
+/// A key going down or up, queued so that taps shorter than a frame aren't lost.
+#[derive(Clone, Copy, PartialEq)]
+pub enum KeyEvent {
+    Press(Chip8Key),
+    Release(Chip8Key),
+}
+
+/// Progress of an `FX0A` wait, kept in the CPU so it survives save states.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub enum KeyWait {
+    Idle,
+    /// Waiting for any key to go down.
+    Press,
+    /// Waiting for this key to come back up.
+    Release(Chip8Key),
+}
+
+/// When `FX0A` finishes waiting.
+#[derive(Clone, Copy, PartialEq)]
+pub enum KeyWaitMode {
+    /// On the release of the key that was pressed.
+    Release,
+    /// As soon as a key is pressed, as on the HP48 interpreters.
+    Press,
+    /// Like `Release`, but the buzzer sounds while the key is held, as on the COSMAC VIP.
+    Vip,
+}
+
+impl KeyWaitMode {
+    pub const ALL: [KeyWaitMode; 3] = [KeyWaitMode::Release, KeyWaitMode::Press, KeyWaitMode::Vip];
+}
+
+impl From<KeyWaitMode> for &'static str {
+    fn from(mode: KeyWaitMode) -> Self {
+        match mode {
+            KeyWaitMode::Release => "Press, then release",
+            KeyWaitMode::Press => "On press (SUPER-CHIP)",
+            KeyWaitMode::Vip => "COSMAC VIP (beep while held)",
+        }
+    }
+}
@@ -0,0 +1,87 @@
+use eframe::egui::{self, Button, RichText, Ui};
+
+use crate::{
+    key_mapping::KeyMapping,
+    key_matrix::{Chip8Key, KEY_LAYOUT, KeyMatrix},
+};
+
+pub enum KeypadEvent {
+    Press(Chip8Key),
+    Release(Chip8Key),
+}
+
+/// On-screen hex keypad that can be held down with the mouse or a touchscreen.
+pub struct Keypad {
+    pub open: bool,
+    held: Option<Chip8Key>,
+}
+
+impl Keypad {
+    pub fn new() -> Self {
+        Self {
+            open: false,
+            held: None,
+        }
+    }
+
+    /// Draws the keypad, highlighting keys pressed in `key_matrix`, and reports clicks.
+    pub fn ui(
+        &mut self,
+        ui: &mut Ui,
+        key_matrix: &KeyMatrix,
+        key_mapping: &KeyMapping,
+    ) -> Vec<KeypadEvent> {
+        let mut held = None;
+
+        egui::Grid::new("keypad")
+            .spacing([6.0, 6.0])
+            .show(ui, |ui| {
+                for row in KEY_LAYOUT {
+                    for key in row {
+                        let mapped = key_mapping
+                            .get_keys(key)
+                            .first()
+                            .map(|k| k.name())
+                            .unwrap_or("");
+                        let label =
+                            RichText::new(format!("{}\n{mapped}", <&'static str>::from(key)))
+                                .monospace();
+
+                        let response = ui.add(
+                            Button::new(label)
+                                .selected(key_matrix.is_pressed(key))
+                                .min_size(egui::vec2(48.0, 48.0)),
+                        );
+                        if response.is_pointer_button_down_on() {
+                            held = Some(key);
+                        }
+                    }
+                    ui.end_row();
+                }
+            });
+
+        let mut events = Vec::new();
+        if held != self.held {
+            if let Some(key) = self.held {
+                events.push(KeypadEvent::Release(key));
+            }
+            if let Some(key) = held {
+                events.push(KeypadEvent::Press(key));
+            }
+            self.held = held;
+        }
+
+        events
+    }
+
+    /// Lets go of the key held with the mouse, e.g. when the keypad is hidden.
+    pub fn release(&mut self) -> Option<Chip8Key> {
+        self.held.take()
+    }
+}
+
+impl Default for Keypad {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -1,25 +1,131 @@
+use std::sync::Arc;
+
+use channel::Waker;
+use chip8::DebugSnapshot;
+use condition::Condition;
+use debugger::{BreakReason, RegisterWatch, Watchpoint};
+use flicker::FlickerMode;
 use key_matrix::Chip8Key;
+use key_wait::KeyWaitMode;
+use profiler::Profiler;
+use scheduler::FramePolicy;
+use symbols::Symbols;
+use timing::TimingModel;
+use tracer::TraceConfig;
 
+#[cfg(feature = "gui")]
 pub mod app;
 pub mod channel;
 pub mod chip8;
 pub mod chip8_state;
+pub mod condition;
+pub mod coverage;
 pub mod cpu;
+pub mod crt;
+pub mod debugger;
+#[cfg(feature = "gui")]
+pub mod debugger_view;
+pub mod disassembler;
+#[cfg(feature = "gui")]
+pub mod disassembly_view;
 pub mod error;
+#[cfg(feature = "gui")]
 pub mod file_picker;
+pub mod flicker;
 pub mod frame_buffer;
+pub mod gdb_stub;
+#[cfg(feature = "gui")]
+pub mod gdb_view;
 pub mod handle;
+pub mod headless;
 pub mod instruction;
+#[cfg(feature = "keys")]
 pub mod key_mapping;
 pub mod key_matrix;
+pub mod key_wait;
+#[cfg(feature = "gui")]
+pub mod keypad;
 pub mod memory;
+#[cfg(feature = "gui")]
+pub mod memory_viewer;
+pub mod palette;
+pub mod profiler;
+#[cfg(feature = "gui")]
+pub mod profiler_view;
+#[cfg(feature = "gui")]
 pub mod remap;
+pub mod scheduler;
+pub mod screenshot;
+#[cfg(feature = "keys")]
+pub mod settings;
+#[cfg(feature = "keys")]
+pub mod shortcuts;
+#[cfg(feature = "gui")]
+pub mod shortcuts_view;
+pub mod symbols;
+pub mod timing;
+#[cfg(feature = "gui")]
+pub mod trace_view;
+pub mod tracer;
+pub mod triple_buffer;
+#[cfg(feature = "tui")]
+pub mod tui;
 
 pub enum Message {
-    Draw,
     Shutdown,
     Pause,
     Unpause,
     Save(String),
+    /// A short message for the status bar, e.g. why a save failed.
+    Status(String),
+    /// Called by the emulator thread whenever it publishes a frame or sends a message.
+    SetWaker(Waker),
+    KeyPressed(Chip8Key),
     KeyReleased(Chip8Key),
+    SetKeyWaitMode(KeyWaitMode),
+    SetFlickerMode(FlickerMode),
+    RequestSnapshot,
+    Snapshot(Box<DebugSnapshot>),
+    WriteMemory(u16, u8),
+    ToggleBreakpoint(u16),
+    /// Adds a breakpoint, or replaces the condition of the one at that address.
+    SetBreakpoint(u16, Option<Condition>),
+    RunTo(u16),
+    Step,
+    AddWatchpoint(Watchpoint),
+    RemoveWatchpoint(usize),
+    AddRegisterWatch(RegisterWatch),
+    RemoveRegisterWatch(usize),
+    AddCondition(Condition),
+    RemoveCondition(usize),
+    Break(BreakReason),
+    StartTrace(TraceConfig),
+    StopTrace,
+    /// Sent back when tracing starts or stops; carries the error if it failed.
+    TraceStatus(bool, Option<String>),
+    StartGdbServer(u16),
+    StopGdbServer,
+    /// Whether the GDB server is listening, and a line describing its state.
+    GdbStatus(bool, String),
+    SetProfiling(bool),
+    ResetProfile,
+    RequestProfile,
+    /// The profile, with the symbols to name its subroutines by.
+    Profile(Box<Profiler>, Arc<Symbols>),
+    SetCoverage(bool),
+    ResetCoverage,
+    SetSymbols(Arc<Symbols>),
+    TogglePause,
+    /// Restart the program, keeping memory.
+    Reset,
+    /// Restart from a fresh copy of the ROM.
+    HardReset,
+    /// Run faster than real time while set.
+    SetFastForward(bool),
+    /// Step back through recent history while set.
+    SetRewinding(bool),
+    /// Instructions per second.
+    SetIps(u32),
+    SetFramePolicy(FramePolicy),
+    SetTimingModel(TimingModel),
 }
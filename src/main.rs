@@ -1,14 +1,44 @@
+use std::{env, process};
+
+#[cfg(feature = "gui")]
 use chip8::app::App;
+use chip8::headless;
+#[cfg(feature = "tui")]
+use chip8::tui;
+#[cfg(feature = "gui")]
 use eframe::egui;
 
 // TODO: Make the code more robust (gracefully handle all errors)
 
 // Non Essential Features
-// TODO: Implement color config (via gui), kbd shortcuts (first define what do you mean by kbd shortcuts), cpu speed control, output sizing options
+// TODO: Implement color config (via gui), output sizing options
 
 // TODO: Add tests
 
-fn main() -> eframe::Result {
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    match args.first().map(String::as_str) {
+        Some("run") => process::exit(headless::main(&args[1..])),
+        #[cfg(feature = "tui")]
+        Some("tui") => process::exit(tui::main(&args[1..])),
+        _ => {}
+    }
+
+    #[cfg(feature = "gui")]
+    if let Err(err) = run_gui() {
+        eprintln!("{err}");
+        process::exit(1);
+    }
+
+    #[cfg(not(feature = "gui"))]
+    {
+        eprintln!("Built without the `gui` feature.\n{}", headless::USAGE);
+        process::exit(headless::EXIT_USAGE);
+    }
+}
+
+#[cfg(feature = "gui")]
+fn run_gui() -> eframe::Result {
     let native_options = eframe::NativeOptions {
         viewport: egui::ViewportBuilder::default()
             .with_min_inner_size([640.0, 320.0])
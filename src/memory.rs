@@ -11,15 +11,89 @@ pub const ROM_START_ADDR: u16 = 0x200;
 
 pub const MEMORY_SIZE: usize = 4096;
 
+use std::cell::{Ref, RefCell};
+
 use serde::{Deserialize, Serialize};
 use serde_big_array::BigArray;
 
 use crate::error::{Chip8Error, Result};
+use crate::instruction::Instruction;
+
+/// Parses an address typed in by the user or read from a symbol file: hex, with an
+/// optional `0x` or `$` prefix.
+pub(crate) fn parse_addr(text: &str) -> Option<u16> {
+    let text = text.trim();
+    let hex = text
+        .strip_prefix("0x")
+        .or_else(|| text.strip_prefix("0X"))
+        .or_else(|| text.strip_prefix('$'))
+        .unwrap_or(text);
+
+    u16::from_str_radix(hex, 16).ok()
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum AccessKind {
+    Read,
+    Write,
+}
+
+/// A run of `len` bytes starting at `addr` touched by a single instruction.
+#[derive(Clone, Copy, PartialEq)]
+pub struct MemoryAccess {
+    pub kind: AccessKind,
+    pub addr: u16,
+    pub len: u16,
+}
+
+impl MemoryAccess {
+    pub fn overlaps(&self, start: u16, end: u16) -> bool {
+        self.len > 0 && self.addr <= end && (start as u32) < self.addr as u32 + self.len as u32
+    }
+
+    /// Whether the byte at `addr` continues this access.
+    fn extends_to(&self, kind: AccessKind, addr: u16) -> bool {
+        self.kind == kind && self.addr as u32 + self.len as u32 == addr as u32
+    }
+}
 
 #[derive(Serialize, Deserialize, Clone)]
+#[serde(from = "RawMemory")]
 pub struct Memory {
     #[serde(with = "BigArray")]
     data: [u8; MEMORY_SIZE],
+    /// The instruction starting at each address, re-decoded on every write so fetching
+    /// doesn't decode again. Self-modifying code just rewrites its entries.
+    #[serde(skip_serializing)]
+    decoded: [Instruction; MEMORY_SIZE - 1],
+    /// Every `read` and `write` since `clear_accesses`, with consecutive bytes merged, so
+    /// watchpoints and coverage see exactly what an instruction touched.
+    #[serde(skip_serializing)]
+    accesses: RefCell<Vec<MemoryAccess>>,
+    /// Whether `accesses` is kept at all; off unless something looks at it, since every
+    /// fetch goes through `read`.
+    #[serde(skip_serializing)]
+    recording: bool,
+}
+
+/// What's saved of `Memory`; the decoded instructions are rebuilt on load.
+#[derive(Deserialize)]
+struct RawMemory {
+    #[serde(with = "BigArray")]
+    data: [u8; MEMORY_SIZE],
+}
+
+impl From<RawMemory> for Memory {
+    fn from(raw: RawMemory) -> Self {
+        let mut memory = Memory {
+            data: raw.data,
+            decoded: [Instruction::Unknown { instruction: 0 }; MEMORY_SIZE - 1],
+            accesses: RefCell::new(Vec::new()),
+            recording: false,
+        };
+        memory.decode_all();
+        memory
+    }
 }
 
 impl Default for Memory {
@@ -32,6 +106,9 @@ impl Memory {
     pub fn new() -> Memory {
         let mut memory = Memory {
             data: [0u8; MEMORY_SIZE],
+            decoded: [Instruction::Unknown { instruction: 0 }; MEMORY_SIZE - 1],
+            accesses: RefCell::new(Vec::new()),
+            recording: false,
         };
         memory.load_font();
         memory
@@ -39,7 +116,10 @@ impl Memory {
 
     pub fn read(&self, addr: u16) -> Result<u8> {
         match self.data.get(addr as usize) {
-            Some(value) => Result::Ok(*value),
+            Some(value) => {
+                self.record(AccessKind::Read, addr);
+                Result::Ok(*value)
+            }
             None => Result::Err(Chip8Error::OutOfBoundsAccess),
         }
     }
@@ -48,12 +128,55 @@ impl Memory {
         match self.data.get_mut(addr as usize) {
             Some(value) => {
                 *value = byte;
+                self.record(AccessKind::Write, addr);
+                self.decode(addr as usize);
+                if addr > 0 {
+                    self.decode(addr as usize - 1);
+                }
                 Result::Ok(())
             }
             None => Result::Err(Chip8Error::OutOfBoundsAccess),
         }
     }
 
+    /// The instruction at `addr`, already decoded.
+    pub fn instruction(&self, addr: u16) -> Result<Instruction> {
+        match self.decoded.get(addr as usize) {
+            Some(instruction) => Result::Ok(*instruction),
+            None => Result::Err(Chip8Error::OutOfBoundsAccess),
+        }
+    }
+
+    /// Accesses made through `read` and `write` since the last `clear_accesses`.
+    pub fn accesses(&self) -> Ref<'_, [MemoryAccess]> {
+        Ref::map(self.accesses.borrow(), Vec::as_slice)
+    }
+
+    pub fn clear_accesses(&mut self) {
+        self.accesses.get_mut().clear();
+    }
+
+    pub fn set_recording(&mut self, recording: bool) {
+        self.recording = recording;
+    }
+
+    fn record(&self, kind: AccessKind, addr: u16) {
+        if !self.recording {
+            return;
+        }
+
+        let mut accesses = self.accesses.borrow_mut();
+
+        match accesses.last_mut() {
+            Some(last) if last.extends_to(kind, addr) => last.len += 1,
+            _ => accesses.push(MemoryAccess { kind, addr, len: 1 }),
+        }
+    }
+
+    pub fn get_ref(&self) -> &[u8; MEMORY_SIZE] {
+        &self.data
+    }
+
     pub fn load_rom(&mut self, buffer: &[u8]) {
         for (i, &byte) in buffer.iter().enumerate() {
             match self.data.get_mut(ROM_START_ADDR as usize + i) {
@@ -61,11 +184,119 @@ impl Memory {
                 None => break,
             }
         }
+
+        self.decode_all();
+    }
+
+    fn decode(&mut self, addr: usize) {
+        if let Some(instruction) = self.decoded.get_mut(addr) {
+            *instruction = u16::from_be_bytes([self.data[addr], self.data[addr + 1]]).into();
+        }
+    }
+
+    fn decode_all(&mut self) {
+        for addr in 0..self.decoded.len() {
+            self.decode(addr);
+        }
     }
 
     fn load_font(&mut self) {
         for (offset, &byte) in FONT_DATA.iter().enumerate() {
             let _ = self.write(offset as u16 + FONT_START_ADDR, byte);
         }
+        self.clear_accesses();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn consecutive_accesses_are_merged() {
+        let mut memory = Memory::new();
+        memory.set_recording(true);
+        assert!(memory.accesses().is_empty());
+
+        for addr in 0x300..0x304 {
+            let _ = memory.write(addr, 1);
+        }
+        let _ = memory.read(0x300);
+        let _ = memory.read(0x301);
+
+        let accesses = memory.accesses();
+        assert!(
+            accesses[..]
+                == [
+                    MemoryAccess {
+                        kind: AccessKind::Write,
+                        addr: 0x300,
+                        len: 4
+                    },
+                    MemoryAccess {
+                        kind: AccessKind::Read,
+                        addr: 0x300,
+                        len: 2
+                    },
+                ]
+        );
+    }
+
+    #[test]
+    fn nothing_is_recorded_unless_asked() {
+        let mut memory = Memory::new();
+
+        memory.write(0x300, 1).unwrap();
+        let _ = memory.read(0x300);
+        assert!(memory.accesses().is_empty());
+    }
+
+    fn decoded(memory: &Memory, addr: u16) -> String {
+        format!("{:?}", memory.instruction(addr).unwrap())
+    }
+
+    fn decode(opcode: u16) -> String {
+        format!("{:?}", Instruction::from(opcode))
+    }
+
+    #[test]
+    fn write_re_decodes_both_instructions_over_the_byte() {
+        let mut memory = Memory::new();
+        memory.load_rom(&[0x12, 0x34, 0x56]);
+
+        memory.write(0x201, 0xAB).unwrap();
+        assert_eq!(decoded(&memory, 0x200), decode(0x12AB));
+        assert_eq!(decoded(&memory, 0x201), decode(0xAB56));
+        assert_eq!(decoded(&memory, 0x202), decode(0x5600));
+
+        // Address 0 has no instruction before it, the last byte none after it
+        memory.write(0x000, 0x6A).unwrap();
+        assert_eq!(decoded(&memory, 0x000), decode(0x6A00));
+        memory.write(MEMORY_SIZE as u16 - 1, 0xEE).unwrap();
+        assert_eq!(decoded(&memory, MEMORY_SIZE as u16 - 2), decode(0x00EE));
+        assert!(memory.instruction(MEMORY_SIZE as u16 - 1).is_err());
+    }
+
+    #[test]
+    fn loading_a_state_rebuilds_the_decoded_instructions() {
+        let mut memory = Memory::new();
+        memory.load_rom(&[0xA2, 0x3C]);
+
+        let loaded = Memory::from(RawMemory {
+            data: *memory.get_ref(),
+        });
+        assert_eq!(decoded(&loaded, 0x200), decode(0xA23C));
+    }
+
+    #[test]
+    fn overlap_at_the_top_of_the_address_space() {
+        let access = MemoryAccess {
+            kind: AccessKind::Read,
+            addr: 0xFFFE,
+            len: 15,
+        };
+
+        assert!(access.overlaps(0xFFFF, 0xFFFF));
+        assert!(!access.overlaps(0x000, 0x00D));
     }
 }
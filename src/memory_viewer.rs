@@ -0,0 +1,268 @@
+use eframe::egui::{self, Color32, RichText, Sense, Ui};
+
+use crate::{
+    Message,
+    chip8::DebugSnapshot,
+    coverage::{self, Coverage, EXECUTED, READ, WRITTEN},
+    handle::Chip8Handle,
+    memory::{MEMORY_SIZE, parse_addr},
+};
+
+const BYTES_PER_ROW: usize = 16;
+const ROW_COUNT: usize = MEMORY_SIZE / BYTES_PER_ROW;
+
+const PC_COLOR: Color32 = Color32::from_rgb(40, 90, 160);
+const I_COLOR: Color32 = Color32::from_rgb(150, 110, 20);
+const SELECTED_COLOR: Color32 = Color32::from_rgb(60, 130, 60);
+
+const CODE_COLOR: Color32 = Color32::from_rgb(110, 200, 110);
+const READ_COLOR: Color32 = Color32::from_rgb(100, 170, 240);
+const WRITTEN_COLOR: Color32 = Color32::from_rgb(240, 130, 90);
+
+pub struct MemoryViewer {
+    pub open: bool,
+    selected: Option<u16>,
+    edit_text: String,
+    jump_text: String,
+    scroll_to: Option<u16>,
+    sprite_rows: u8,
+    show_coverage: bool,
+    pub error: Option<String>,
+}
+
+impl MemoryViewer {
+    pub fn new() -> Self {
+        Self {
+            open: false,
+            selected: None,
+            edit_text: String::new(),
+            jump_text: String::new(),
+            scroll_to: None,
+            sprite_rows: 5,
+            show_coverage: true,
+            error: None,
+        }
+    }
+
+    /// Returns true when the user asked to export the coverage report.
+    pub fn ui(
+        &mut self,
+        ui: &mut Ui,
+        snapshot: &DebugSnapshot,
+        handle: &Chip8Handle,
+        colors: ([u8; 3], [u8; 3]),
+    ) -> bool {
+        let mut export = false;
+        let memory = snapshot.memory.get_ref();
+        let pc = snapshot.cpu.pc();
+        let i = snapshot.cpu.i();
+
+        ui.horizontal(|ui| {
+            if snapshot.paused {
+                if ui.button("Resume").clicked() {
+                    handle.send_unpause_message();
+                }
+            } else if ui.button("Pause").clicked() {
+                handle.send_pause_message();
+            }
+
+            ui.separator();
+            ui.label(RichText::new(format!("PC {pc:03X}")).background_color(PC_COLOR));
+            ui.label(RichText::new(format!("I {i:03X}")).background_color(I_COLOR));
+
+            ui.separator();
+            ui.label("Jump to");
+            let jump = ui.add(egui::TextEdit::singleline(&mut self.jump_text).desired_width(40.0));
+            if (ui.button("Go").clicked() || jump.lost_focus())
+                && let Some(addr) = parse_addr(&self.jump_text)
+                && (addr as usize) < MEMORY_SIZE
+            {
+                self.scroll_to = Some(addr);
+                self.selected = Some(addr);
+            }
+            if ui.button("PC").clicked() {
+                self.scroll_to = Some(pc);
+            }
+            if ui.button("I").clicked() {
+                self.scroll_to = Some(i);
+            }
+        });
+
+        ui.horizontal(|ui| match self.selected {
+            Some(addr) => {
+                ui.label(format!(
+                    "Selected {addr:03X} = {:02X}",
+                    memory[addr as usize]
+                ));
+                ui.add_enabled_ui(snapshot.paused, |ui| {
+                    ui.add(egui::TextEdit::singleline(&mut self.edit_text).desired_width(24.0));
+                    if ui.button("Write").clicked()
+                        && let Ok(byte) = u8::from_str_radix(self.edit_text.trim(), 16)
+                    {
+                        handle.write_memory(addr, byte);
+                        self.selected = Some((addr + 1) % MEMORY_SIZE as u16);
+                        self.edit_text.clear();
+                    }
+                });
+                if !snapshot.paused {
+                    ui.label("(pause to edit)");
+                }
+            }
+            None => {
+                ui.label("Click a byte to select it");
+            }
+        });
+
+        ui.horizontal(|ui| {
+            let mut recording = snapshot.recording_coverage;
+            if ui.checkbox(&mut recording, "Record coverage").changed() {
+                handle.send(Message::SetCoverage(recording));
+            }
+            if ui.button("Reset").clicked() {
+                handle.send(Message::ResetCoverage);
+            }
+            export = ui.button("Export report").clicked();
+
+            ui.separator();
+            ui.checkbox(&mut self.show_coverage, "Overlay");
+            if self.show_coverage {
+                ui.label(RichText::new("code").color(CODE_COLOR));
+                ui.label(RichText::new("read").color(READ_COLOR));
+                ui.label(RichText::new("written").color(WRITTEN_COLOR));
+            }
+        });
+
+        if let Some(ref error) = self.error {
+            ui.colored_label(ui.visuals().error_fg_color, error);
+        }
+
+        ui.separator();
+
+        egui::SidePanel::right("sprite preview")
+            .resizable(false)
+            .show_inside(ui, |ui| {
+                ui.label(format!("Sprite at I ({i:03X})"));
+                ui.add(egui::Slider::new(&mut self.sprite_rows, 1..=15).text("rows"));
+                sprite_preview(ui, memory, i, self.sprite_rows, colors);
+            });
+
+        let row_height = ui.text_style_height(&egui::TextStyle::Monospace);
+        let mut scroll_area = egui::ScrollArea::vertical().auto_shrink(false);
+        if let Some(addr) = self.scroll_to.take() {
+            let row = addr as usize / BYTES_PER_ROW;
+            scroll_area = scroll_area
+                .vertical_scroll_offset(row as f32 * (row_height + ui.spacing().item_spacing.y));
+        }
+
+        scroll_area.show_rows(ui, row_height, ROW_COUNT, |ui, rows| {
+            for row in rows {
+                let base = row * BYTES_PER_ROW;
+
+                ui.horizontal(|ui| {
+                    ui.spacing_mut().item_spacing.x = 4.0;
+                    ui.label(RichText::new(format!("{base:03X}:")).monospace().weak());
+
+                    for offset in 0..BYTES_PER_ROW {
+                        let addr = (base + offset) as u16;
+
+                        let mut text =
+                            RichText::new(format!("{:02X}", memory[addr as usize])).monospace();
+                        if self.show_coverage
+                            && let Some(color) = coverage_color(&snapshot.coverage, addr)
+                        {
+                            text = text.color(color);
+                        }
+                        if self.selected == Some(addr) {
+                            text = text.background_color(SELECTED_COLOR);
+                        } else if addr == pc || addr == pc + 1 {
+                            text = text.background_color(PC_COLOR);
+                        } else if addr == i {
+                            text = text.background_color(I_COLOR);
+                        }
+
+                        let response = ui.add(egui::Label::new(text).sense(Sense::click()));
+                        let flags = snapshot.coverage.flags(addr);
+                        let response = if flags != 0 {
+                            response.on_hover_text(coverage::describe(flags))
+                        } else {
+                            response
+                        };
+                        if response.clicked() {
+                            self.selected = Some(addr);
+                            self.edit_text = format!("{:02X}", memory[addr as usize]);
+                        }
+                    }
+
+                    let ascii = memory[base..base + BYTES_PER_ROW]
+                        .iter()
+                        .map(|&byte| {
+                            if byte.is_ascii_graphic() || byte == b' ' {
+                                byte as char
+                            } else {
+                                '.'
+                            }
+                        })
+                        .collect::<String>();
+                    ui.label(RichText::new(ascii).monospace().weak());
+                });
+            }
+        });
+
+        export
+    }
+}
+
+impl Default for MemoryViewer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn sprite_preview(
+    ui: &mut Ui,
+    memory: &[u8; MEMORY_SIZE],
+    i: u16,
+    rows: u8,
+    (off, on): ([u8; 3], [u8; 3]),
+) {
+    let cell = 12.0;
+    let (response, painter) =
+        ui.allocate_painter(egui::vec2(8.0 * cell, rows as f32 * cell), Sense::hover());
+
+    let off = Color32::from_rgb(off[0], off[1], off[2]);
+    let on = Color32::from_rgb(on[0], on[1], on[2]);
+
+    painter.rect_filled(response.rect, 0.0, off);
+
+    for row in 0..rows as usize {
+        let Some(&byte) = memory.get(i as usize + row) else {
+            break;
+        };
+
+        for bit in 0..8 {
+            if (byte >> (7 - bit)) & 1 == 1 {
+                let min = response.rect.min + egui::vec2(bit as f32 * cell, row as f32 * cell);
+                painter.rect_filled(
+                    egui::Rect::from_min_size(min, egui::vec2(cell, cell)),
+                    0.0,
+                    on,
+                );
+            }
+        }
+    }
+}
+
+/// Writes win over reads, which win over execution.
+fn coverage_color(coverage: &Coverage, addr: u16) -> Option<Color32> {
+    let flags = coverage.flags(addr);
+
+    if flags & WRITTEN != 0 {
+        Some(WRITTEN_COLOR)
+    } else if flags & READ != 0 {
+        Some(READ_COLOR)
+    } else if flags & EXECUTED != 0 {
+        Some(CODE_COLOR)
+    } else {
+        None
+    }
+}
@@ -0,0 +1,229 @@
+use std::{fmt, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+
+/// Number of colors a palette can hold: 2 for plain CHIP-8, 4 or 16 for multi-plane modes.
+pub const PALETTE_SIZES: [usize; 3] = [2, 4, 16];
+
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+#[serde(from = "RawPalette")]
+pub struct Palette {
+    colors: Vec<[u8; 3]>,
+}
+
+/// What's saved of `Palette`; loading goes through `Palette::new` so a hand-edited or
+/// truncated file still has a supported number of colors.
+#[derive(Deserialize)]
+struct RawPalette {
+    colors: Vec<[u8; 3]>,
+}
+
+impl From<RawPalette> for Palette {
+    fn from(raw: RawPalette) -> Self {
+        Palette::new(raw.colors)
+    }
+}
+
+impl Palette {
+    pub fn new(colors: Vec<[u8; 3]>) -> Self {
+        let mut palette = Self { colors };
+        palette.resize(palette.colors.len());
+        palette
+    }
+
+    pub fn off(&self) -> [u8; 3] {
+        self.colors[0]
+    }
+
+    pub fn on(&self) -> [u8; 3] {
+        self.colors[1]
+    }
+
+    pub fn colors(&self) -> &[[u8; 3]] {
+        &self.colors
+    }
+
+    pub fn colors_mut(&mut self) -> &mut [[u8; 3]] {
+        &mut self.colors
+    }
+
+    pub fn len(&self) -> usize {
+        self.colors.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.colors.is_empty()
+    }
+
+    /// Grows to the smallest supported size that fits `len`, repeating the existing colors to fill new slots.
+    pub fn resize(&mut self, len: usize) {
+        let len = PALETTE_SIZES
+            .into_iter()
+            .find(|&size| size >= len)
+            .unwrap_or(PALETTE_SIZES[PALETTE_SIZES.len() - 1]);
+
+        if self.colors.is_empty() {
+            self.colors = PalettePreset::Classic.colors().to_vec();
+        }
+
+        let existing = self.colors.len();
+        for index in existing..len {
+            self.colors.push(self.colors[index % existing]);
+        }
+        self.colors.truncate(len);
+    }
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        PalettePreset::Classic.palette(2)
+    }
+}
+
+/// Formats as `#RRGGBB` strings separated by commas.
+impl fmt::Display for Palette {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (index, [r, g, b]) in self.colors.iter().enumerate() {
+            if index > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "#{r:02X}{g:02X}{b:02X}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Parses hex colors separated by commas, semicolons or whitespace; the `#` is optional.
+impl FromStr for Palette {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let colors = s
+            .split(|c: char| c == ',' || c == ';' || c.is_whitespace())
+            .filter(|part| !part.is_empty())
+            .map(|part| {
+                let hex = part.strip_prefix('#').unwrap_or(part);
+                if hex.len() != 6 || !hex.is_ascii() {
+                    return Err(());
+                }
+
+                let channel = |range| u8::from_str_radix(&hex[range], 16).map_err(|_| ());
+                Ok([channel(0..2)?, channel(2..4)?, channel(4..6)?])
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        if colors.len() < 2 || colors.len() > PALETTE_SIZES[PALETTE_SIZES.len() - 1] {
+            return Err(());
+        }
+
+        Ok(Palette::new(colors))
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum PalettePreset {
+    Classic,
+    GreenPhosphor,
+    Amber,
+    GameBoy,
+    Lcd,
+    HighContrast,
+}
+
+impl PalettePreset {
+    pub const ALL: [PalettePreset; 6] = [
+        PalettePreset::Classic,
+        PalettePreset::GreenPhosphor,
+        PalettePreset::Amber,
+        PalettePreset::GameBoy,
+        PalettePreset::Lcd,
+        PalettePreset::HighContrast,
+    ];
+
+    /// Background, foreground, then the two extra plane colors.
+    fn colors(self) -> [[u8; 3]; 4] {
+        match self {
+            PalettePreset::Classic => [
+                [0x00, 0x00, 0x00],
+                [0xFF, 0xFF, 0xFF],
+                [0xAA, 0xAA, 0xAA],
+                [0x55, 0x55, 0x55],
+            ],
+            PalettePreset::GreenPhosphor => [
+                [0x00, 0x11, 0x00],
+                [0x33, 0xFF, 0x33],
+                [0x1C, 0x8C, 0x1C],
+                [0x99, 0xFF, 0x99],
+            ],
+            PalettePreset::Amber => [
+                [0x1A, 0x0F, 0x00],
+                [0xFF, 0xB0, 0x00],
+                [0x8C, 0x60, 0x00],
+                [0xFF, 0xD4, 0x80],
+            ],
+            PalettePreset::GameBoy => [
+                [0x9B, 0xBC, 0x0F],
+                [0x0F, 0x38, 0x0F],
+                [0x8B, 0xAC, 0x0F],
+                [0x30, 0x62, 0x30],
+            ],
+            PalettePreset::Lcd => [
+                [0xB8, 0xC2, 0xA4],
+                [0x2B, 0x2F, 0x24],
+                [0x7A, 0x82, 0x70],
+                [0x50, 0x57, 0x4A],
+            ],
+            PalettePreset::HighContrast => [
+                [0x00, 0x00, 0x00],
+                [0xFF, 0xFF, 0x00],
+                [0x00, 0xFF, 0xFF],
+                [0xFF, 0xFF, 0xFF],
+            ],
+        }
+    }
+
+    pub fn palette(self, len: usize) -> Palette {
+        let mut palette = Palette::new(self.colors().to_vec());
+        palette.resize(len);
+        palette
+    }
+
+    pub fn matching(palette: &Palette) -> Option<PalettePreset> {
+        Self::ALL
+            .into_iter()
+            .find(|preset| preset.palette(palette.len()) == *palette)
+    }
+}
+
+impl From<PalettePreset> for &'static str {
+    fn from(preset: PalettePreset) -> Self {
+        match preset {
+            PalettePreset::Classic => "Classic",
+            PalettePreset::GreenPhosphor => "Green Phosphor",
+            PalettePreset::Amber => "Amber",
+            PalettePreset::GameBoy => "Game Boy",
+            PalettePreset::Lcd => "LCD",
+            PalettePreset::HighContrast => "High Contrast",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn deserializing_fills_missing_colors() {
+        let empty: Palette = serde_json::from_str(r#"{ "colors": [] }"#).unwrap();
+        assert!(empty == Palette::default());
+
+        let single: Palette = serde_json::from_str(r#"{ "colors": [[1, 2, 3]] }"#).unwrap();
+        assert_eq!(single.colors(), [[1, 2, 3], [1, 2, 3]]);
+    }
+
+    #[test]
+    fn parses_what_it_formats() {
+        let palette = PalettePreset::GameBoy.palette(4);
+        assert!(palette.to_string().parse::<Palette>() == Ok(palette));
+    }
+}
@@ -0,0 +1,246 @@
+use std::{collections::HashMap, fmt::Write as _};
+
+use crate::{
+    cpu::Cpu,
+    instruction::Instruction,
+    memory::{MEMORY_SIZE, Memory},
+    symbols::Symbols,
+};
+
+#[derive(Clone, Copy, Default)]
+pub struct SubroutineCost {
+    pub calls: u64,
+    /// Instructions executed in the subroutine itself.
+    pub flat: u64,
+    /// Instructions executed in the subroutine and everything it called.
+    pub cumulative: u64,
+}
+
+/// Instruction counts per address and per subroutine.
+///
+/// The call path is rebuilt from `Cpu::stack`/`sp` whenever the depth changes: each return
+/// address points just past the `CALL` that pushed it, which names the subroutine.
+#[derive(Clone)]
+pub struct Profiler {
+    counts: Vec<u64>,
+    total: u64,
+    subroutines: HashMap<u16, SubroutineCost>,
+    stacks: HashMap<Vec<u16>, u64>,
+    frames: Vec<u16>,
+}
+
+impl Profiler {
+    pub fn new() -> Self {
+        Self {
+            counts: vec![0; MEMORY_SIZE],
+            total: 0,
+            subroutines: HashMap::new(),
+            stacks: HashMap::new(),
+            frames: Vec::new(),
+        }
+    }
+
+    /// Counts `instruction` at `pc`, called before it executes.
+    pub fn record(&mut self, pc: u16, instruction: &Instruction, cpu: &Cpu, memory: &Memory) {
+        if cpu.sp() as usize != self.frames.len() {
+            self.rebuild_frames(cpu, memory);
+        }
+
+        if let Some(count) = self.counts.get_mut(pc as usize) {
+            *count += 1;
+        }
+        self.total += 1;
+
+        if let Instruction::Call { addr } = *instruction {
+            self.subroutines.entry(addr).or_default().calls += 1;
+        }
+
+        if let Some(&top) = self.frames.last() {
+            self.subroutines.entry(top).or_default().flat += 1;
+        }
+        for (depth, &frame) in self.frames.iter().enumerate() {
+            // Recursive calls only count once towards the cumulative cost
+            if !self.frames[..depth].contains(&frame) {
+                self.subroutines.entry(frame).or_default().cumulative += 1;
+            }
+        }
+
+        match self.stacks.get_mut(self.frames.as_slice()) {
+            Some(count) => *count += 1,
+            None => {
+                self.stacks.insert(self.frames.clone(), 1);
+            }
+        }
+    }
+
+    fn rebuild_frames(&mut self, cpu: &Cpu, memory: &Memory) {
+        let bytes = memory.get_ref();
+
+        self.frames = cpu.stack()[..(cpu.sp() as usize).min(cpu.stack().len())]
+            .iter()
+            .map(|&ret| {
+                let call = ret.wrapping_sub(2) as usize;
+                match (bytes.get(call), bytes.get(call + 1)) {
+                    (Some(&high), Some(&low)) => u16::from_be_bytes([high, low]) & 0xFFF,
+                    _ => 0,
+                }
+            })
+            .collect();
+    }
+
+    pub fn total(&self) -> u64 {
+        self.total
+    }
+
+    /// Addresses that executed at least once, with their counts.
+    pub fn hot_spots(&self) -> Vec<(u16, u64)> {
+        self.counts
+            .iter()
+            .enumerate()
+            .filter(|&(_, &count)| count > 0)
+            .map(|(addr, &count)| (addr as u16, count))
+            .collect()
+    }
+
+    pub fn subroutines(&self) -> Vec<(u16, SubroutineCost)> {
+        self.subroutines
+            .iter()
+            .map(|(&addr, &cost)| (addr, cost))
+            .collect()
+    }
+
+    /// `addr,count` for every executed address, then `subroutine,calls,flat,cumulative`.
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("address,count\n");
+        for (addr, count) in self.hot_spots() {
+            let _ = writeln!(csv, "{addr:03X},{count}");
+        }
+
+        let mut subroutines = self.subroutines();
+        subroutines.sort_by_key(|&(addr, _)| addr);
+
+        csv.push_str("\nsubroutine,calls,flat,cumulative\n");
+        for (addr, cost) in subroutines {
+            let _ = writeln!(
+                csv,
+                "{addr:03X},{},{},{}",
+                cost.calls, cost.flat, cost.cumulative
+            );
+        }
+
+        csv
+    }
+
+    /// Folded stacks as read by `flamegraph.pl` and inferno: `main;draw_player;sub_300 1234`.
+    /// Subroutines are named by their label, or `sub_` and their address.
+    pub fn to_folded(&self, symbols: &Symbols) -> String {
+        let mut lines = self
+            .stacks
+            .iter()
+            .map(|(frames, count)| {
+                let mut line = String::from("main");
+                for &frame in frames {
+                    match symbols.name(frame) {
+                        // `;` and spaces separate frames and the count
+                        Some(name) => {
+                            let _ = write!(line, ";{}", name.replace([';', ' '], "_"));
+                        }
+                        None => {
+                            let _ = write!(line, ";sub_{frame:03X}");
+                        }
+                    }
+                }
+                format!("{line} {count}")
+            })
+            .collect::<Vec<_>>();
+        lines.sort();
+
+        lines.join("\n") + "\n"
+    }
+}
+
+impl Default for Profiler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+    use crate::{chip8::Chip8, key_matrix::KeyMatrix, memory::ROM_START_ADDR};
+
+    /// Calls 0x206 twice, which calls 0x20A, then loops at 0x204.
+    const ROM: [u8; 12] = [
+        0x22, 0x06, // 200: CALL 0x206
+        0x22, 0x06, // 202: CALL 0x206
+        0x12, 0x04, // 204: JP 0x204
+        0x22, 0x0A, // 206: CALL 0x20A
+        0x00, 0xEE, // 208: RET
+        0x00, 0xEE, // 20A: RET
+    ];
+
+    fn profile() -> Profiler {
+        let mut chip8 = Chip8::new(Arc::new(Mutex::new(KeyMatrix::new())));
+        for (offset, &byte) in ROM.iter().enumerate() {
+            chip8
+                .write_memory(ROM_START_ADDR + offset as u16, byte)
+                .unwrap();
+        }
+
+        chip8.set_profiling(true);
+        for _ in 0..10 {
+            chip8.tick().unwrap();
+        }
+        chip8.profile().clone()
+    }
+
+    #[test]
+    fn counts_each_address() {
+        let profiler = profile();
+
+        assert_eq!(profiler.total(), 10);
+        assert_eq!(
+            profiler.hot_spots(),
+            [
+                (0x200, 1),
+                (0x202, 1),
+                (0x204, 2),
+                (0x206, 2),
+                (0x208, 2),
+                (0x20A, 2)
+            ]
+        );
+    }
+
+    #[test]
+    fn attributes_instructions_to_subroutines() {
+        let mut subroutines = profile().subroutines();
+        subroutines.sort_by_key(|&(addr, _)| addr);
+
+        let costs: Vec<_> = subroutines
+            .iter()
+            .map(|(addr, cost)| (*addr, cost.calls, cost.flat, cost.cumulative))
+            .collect();
+        assert_eq!(costs, [(0x206, 2, 4, 6), (0x20A, 2, 2, 2)]);
+    }
+
+    #[test]
+    fn folds_stacks_by_label() {
+        let profiler = profile();
+
+        assert_eq!(
+            profiler.to_folded(&Symbols::new()),
+            "main 4\nmain;sub_206 4\nmain;sub_206;sub_20A 2\n"
+        );
+
+        let mut symbols = Symbols::new();
+        symbols.insert(0x20A, "draw sprite".to_string());
+        assert_eq!(
+            profiler.to_folded(&symbols),
+            "main 4\nmain;sub_206 4\nmain;sub_206;draw_sprite 2\n"
+        );
+    }
+}
@@ -0,0 +1,205 @@
+use eframe::egui::{self, RichText, Ui};
+
+use std::sync::Arc;
+
+use crate::{Message, handle::Chip8Handle, profiler::Profiler, symbols::Symbols};
+
+#[derive(Clone, Copy, PartialEq)]
+enum Column {
+    Address,
+    Count,
+    Calls,
+    Flat,
+    Cumulative,
+}
+
+impl From<Column> for &'static str {
+    fn from(column: Column) -> Self {
+        match column {
+            Column::Address => "Address",
+            Column::Count => "Count",
+            Column::Calls => "Calls",
+            Column::Flat => "Flat",
+            Column::Cumulative => "Cumulative",
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Tab {
+    HotSpots,
+    Subroutines,
+}
+
+/// Which export the user picked a file for.
+pub enum ProfileExport {
+    Csv,
+    Folded,
+}
+
+pub struct ProfilerView {
+    pub open: bool,
+    pub profile: Option<Box<Profiler>>,
+    /// Names for the folded-stack export.
+    pub symbols: Arc<Symbols>,
+    pub error: Option<String>,
+    profiling: bool,
+    tab: Tab,
+    sort: Column,
+    descending: bool,
+}
+
+impl ProfilerView {
+    pub fn new() -> Self {
+        Self {
+            open: false,
+            profile: None,
+            symbols: Arc::new(Symbols::new()),
+            error: None,
+            profiling: false,
+            tab: Tab::HotSpots,
+            sort: Column::Count,
+            descending: true,
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.profile = None;
+        self.profiling = false;
+    }
+
+    pub fn ui(&mut self, ui: &mut Ui, handle: &Chip8Handle) -> Option<ProfileExport> {
+        let mut export = None;
+
+        ui.horizontal(|ui| {
+            if self.profiling {
+                if ui.button("Stop").clicked() {
+                    self.profiling = false;
+                    handle.send(Message::SetProfiling(false));
+                }
+            } else if ui.button("Start").clicked() {
+                self.profiling = true;
+                handle.send(Message::SetProfiling(true));
+            }
+            if ui.button("Reset").clicked() {
+                handle.send(Message::ResetProfile);
+            }
+
+            ui.separator();
+            if ui.button("Export CSV").clicked() {
+                export = Some(ProfileExport::Csv);
+            }
+            if ui.button("Export folded stacks").clicked() {
+                export = Some(ProfileExport::Folded);
+            }
+        });
+
+        if let Some(ref error) = self.error {
+            ui.colored_label(ui.visuals().error_fg_color, error);
+        }
+
+        let Some(ref profile) = self.profile else {
+            ui.label("No profile yet");
+            return export;
+        };
+
+        ui.label(format!("{} instructions", profile.total()));
+
+        ui.horizontal(|ui| {
+            ui.selectable_value(&mut self.tab, Tab::HotSpots, "Hot spots");
+            ui.selectable_value(&mut self.tab, Tab::Subroutines, "Subroutines");
+        });
+        ui.separator();
+
+        let total = profile.total().max(1) as f64;
+
+        match self.tab {
+            Tab::HotSpots => {
+                let mut rows = profile.hot_spots();
+                match self.sort {
+                    Column::Address => rows.sort_by_key(|&(addr, _)| addr),
+                    _ => rows.sort_by_key(|&(addr, count)| (count, addr)),
+                }
+                if self.descending {
+                    rows.reverse();
+                }
+
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    egui::Grid::new("hot spots").striped(true).show(ui, |ui| {
+                        self.header(ui, Column::Address);
+                        self.header(ui, Column::Count);
+                        ui.label("%");
+                        ui.end_row();
+
+                        for (addr, count) in rows {
+                            ui.monospace(format!("{addr:03X}"));
+                            ui.monospace(count.to_string());
+                            ui.monospace(format!("{:.2}", count as f64 / total * 100.0));
+                            ui.end_row();
+                        }
+                    });
+                });
+            }
+            Tab::Subroutines => {
+                let mut rows = profile.subroutines();
+                rows.sort_by_key(|&(addr, cost)| {
+                    let key = match self.sort {
+                        Column::Address => 0,
+                        Column::Calls => cost.calls,
+                        Column::Flat | Column::Count => cost.flat,
+                        Column::Cumulative => cost.cumulative,
+                    };
+                    (key, addr)
+                });
+                if self.descending {
+                    rows.reverse();
+                }
+
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    egui::Grid::new("subroutines").striped(true).show(ui, |ui| {
+                        self.header(ui, Column::Address);
+                        self.header(ui, Column::Calls);
+                        self.header(ui, Column::Flat);
+                        self.header(ui, Column::Cumulative);
+                        ui.label("Cumulative %");
+                        ui.end_row();
+
+                        for (addr, cost) in rows {
+                            ui.monospace(format!("{addr:03X}"));
+                            ui.monospace(cost.calls.to_string());
+                            ui.monospace(cost.flat.to_string());
+                            ui.monospace(cost.cumulative.to_string());
+                            ui.monospace(format!("{:.2}", cost.cumulative as f64 / total * 100.0));
+                            ui.end_row();
+                        }
+                    });
+                });
+            }
+        }
+
+        export
+    }
+
+    /// Column header that sorts by the column, flipping the order when clicked again.
+    fn header(&mut self, ui: &mut Ui, column: Column) {
+        let mut text = <&'static str>::from(column).to_string();
+        if self.sort == column {
+            text.push_str(if self.descending { " ⏷" } else { " ⏶" });
+        }
+
+        if ui.button(RichText::new(text).strong()).clicked() {
+            if self.sort == column {
+                self.descending = !self.descending;
+            } else {
+                self.sort = column;
+                self.descending = column != Column::Address;
+            }
+        }
+    }
+}
+
+impl Default for ProfilerView {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -7,6 +7,7 @@ pub struct RemapState {
     pub open_selection: bool,
     pub target_key: Option<Chip8Key>,
     pub selected_key: Option<Key>,
+    pub new_profile_name: String,
 }
 
 impl RemapState {
@@ -16,6 +17,7 @@ impl RemapState {
             open_selection: false,
             target_key: None,
             selected_key: None,
+            new_profile_name: String::new(),
         }
     }
 
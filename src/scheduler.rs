@@ -0,0 +1,290 @@
+use std::time::{Duration, Instant};
+
+use crate::{
+    cpu::Cpu,
+    instruction::Instruction,
+    timing::{self, TimingModel, VIP_FRAME_US},
+};
+
+/// One 60 Hz frame.
+pub const FRAME_DURATION: Duration = Duration::from_nanos(1_000_000_000 / 60);
+/// Instructions per second unless configured otherwise.
+pub const DEFAULT_IPS: u32 = 500;
+/// Most frames `FramePolicy::CatchUp` will run back to back before giving up on the backlog.
+const MAX_CATCH_UP: u32 = 10;
+
+/// What to do when the emulator thread falls behind, e.g. after the host stalled.
+#[derive(Clone, Copy, PartialEq)]
+pub enum FramePolicy {
+    /// Drop the missed frames and carry on from now.
+    Skip,
+    /// Run the missed frames back to back (up to a limit) so emulated time keeps up.
+    CatchUp,
+}
+
+impl FramePolicy {
+    pub const ALL: [FramePolicy; 2] = [FramePolicy::Skip, FramePolicy::CatchUp];
+}
+
+impl From<FramePolicy> for &'static str {
+    fn from(policy: FramePolicy) -> Self {
+        match policy {
+            FramePolicy::Skip => "Skip missed frames",
+            FramePolicy::CatchUp => "Catch up",
+        }
+    }
+}
+
+/// Frame-locked pacing: a share of instructions per 1/60 s frame, with the deadline
+/// advanced by exactly one frame each time so rounding errors don't accumulate.
+///
+/// Each frame is run as `begin_frame`, then `admit` before every instruction until it
+/// returns false.
+pub struct FrameScheduler {
+    next_frame: Instant,
+    policy: FramePolicy,
+    model: TimingModel,
+    ips: u32,
+    /// Remainder carried between frames when `ips` isn't a multiple of 60.
+    ips_remainder: u32,
+    speed: u32,
+    /// Instructions left this frame, for `TimingModel::FixedIps`.
+    remaining: u32,
+    /// Microseconds left this frame for `TimingModel::CosmacVip`; negative when the last
+    /// instruction ran over, which is then taken out of the next frame.
+    vip_budget: i64,
+    /// Whether an instruction already ran this frame.
+    started: bool,
+}
+
+impl FrameScheduler {
+    pub fn new() -> Self {
+        Self {
+            next_frame: Instant::now() + FRAME_DURATION,
+            policy: FramePolicy::Skip,
+            model: TimingModel::FixedIps,
+            ips: DEFAULT_IPS,
+            ips_remainder: 0,
+            speed: 1,
+            remaining: 0,
+            vip_budget: 0,
+            started: false,
+        }
+    }
+
+    pub fn set_policy(&mut self, policy: FramePolicy) {
+        self.policy = policy;
+    }
+
+    pub fn set_model(&mut self, model: TimingModel) {
+        self.model = model;
+        self.vip_budget = 0;
+    }
+
+    pub fn set_ips(&mut self, ips: u32) {
+        self.ips = ips.max(1);
+    }
+
+    /// Runs `speed` times faster than real time, for fast-forward.
+    pub fn set_speed(&mut self, speed: u32) {
+        self.speed = speed.max(1);
+    }
+
+    fn frame_duration(&self) -> Duration {
+        FRAME_DURATION / self.speed
+    }
+
+    /// When the next frame is due.
+    pub fn deadline(&self) -> Instant {
+        self.next_frame
+    }
+
+    /// Starts counting from `now`, e.g. after the emulator was paused.
+    pub fn resync(&mut self, now: Instant) {
+        self.next_frame = now + self.frame_duration();
+    }
+
+    /// How many frames are due at `now`, applying the policy when more than one is.
+    pub fn due_frames(&mut self, now: Instant) -> u32 {
+        if now < self.next_frame {
+            return 0;
+        }
+
+        let frame = self.frame_duration();
+        let behind = (now - self.next_frame).as_nanos() / frame.as_nanos() + 1;
+        let frames = match self.policy {
+            FramePolicy::Skip => 1,
+            FramePolicy::CatchUp => behind.min(MAX_CATCH_UP as u128) as u32,
+        };
+
+        if frames as u128 == behind {
+            self.next_frame += frame * frames;
+        } else {
+            self.resync(now);
+        }
+
+        frames
+    }
+
+    pub fn begin_frame(&mut self) {
+        self.started = false;
+
+        match self.model {
+            TimingModel::FixedIps => {
+                self.ips_remainder += self.ips;
+                self.remaining = self.ips_remainder / 60;
+                self.ips_remainder %= 60;
+            }
+            TimingModel::CosmacVip => {
+                self.vip_budget = VIP_FRAME_US as i64 + self.vip_budget.min(0);
+            }
+        }
+    }
+
+    /// Whether `instruction` still runs in this frame; if so, its cost is charged.
+    pub fn admit(&mut self, instruction: &Instruction, cpu: &Cpu) -> bool {
+        match self.model {
+            TimingModel::FixedIps => {
+                if self.remaining == 0 {
+                    return false;
+                }
+                self.remaining -= 1;
+            }
+            TimingModel::CosmacVip => {
+                // The VIP draws sprites right after the vblank interrupt, so a sprite
+                // waits for the next frame unless it is the first thing in this one
+                let waits_for_vblank =
+                    self.started && matches!(instruction, Instruction::Drw { .. });
+                if self.vip_budget <= 0 || waits_for_vblank {
+                    self.vip_budget = self.vip_budget.min(0);
+                    return false;
+                }
+                self.vip_budget -= timing::vip_cost(instruction, cpu) as i64;
+            }
+        }
+
+        self.started = true;
+        true
+    }
+}
+
+impl Default for FrameScheduler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Instructions `admit` lets through in one frame.
+    fn run_frame(scheduler: &mut FrameScheduler, instruction: Instruction) -> u32 {
+        let cpu = Cpu::new();
+        scheduler.begin_frame();
+
+        let mut count = 0;
+        while scheduler.admit(&instruction, &cpu) {
+            count += 1;
+        }
+        count
+    }
+
+    #[test]
+    fn ips_remainder_carries_between_frames() {
+        let mut scheduler = FrameScheduler::new();
+        scheduler.set_ips(500);
+
+        let counts: Vec<u32> = (0..60)
+            .map(|_| run_frame(&mut scheduler, Instruction::LdByte { vx: 0, byte: 0 }))
+            .collect();
+
+        assert!(counts.iter().all(|&count| count == 8 || count == 9));
+        assert_eq!(counts.iter().sum::<u32>(), 500);
+    }
+
+    #[test]
+    fn nothing_due_before_the_deadline() {
+        let mut scheduler = FrameScheduler::new();
+        let deadline = scheduler.deadline();
+
+        assert_eq!(scheduler.due_frames(deadline - Duration::from_millis(1)), 0);
+        assert_eq!(scheduler.due_frames(deadline), 1);
+        assert_eq!(scheduler.deadline(), deadline + FRAME_DURATION);
+    }
+
+    #[test]
+    fn skip_drops_missed_frames() {
+        let mut scheduler = FrameScheduler::new();
+        let now = scheduler.deadline() + FRAME_DURATION * 5;
+
+        assert_eq!(scheduler.due_frames(now), 1);
+        assert_eq!(scheduler.deadline(), now + FRAME_DURATION);
+    }
+
+    #[test]
+    fn catch_up_runs_missed_frames() {
+        let mut scheduler = FrameScheduler::new();
+        scheduler.set_policy(FramePolicy::CatchUp);
+        let deadline = scheduler.deadline();
+
+        // Five frames late means this frame plus the five after it
+        assert_eq!(scheduler.due_frames(deadline + FRAME_DURATION * 5), 6);
+        assert_eq!(scheduler.deadline(), deadline + FRAME_DURATION * 6);
+    }
+
+    #[test]
+    fn catch_up_gives_up_past_the_limit() {
+        let mut scheduler = FrameScheduler::new();
+        scheduler.set_policy(FramePolicy::CatchUp);
+        let now = scheduler.deadline() + FRAME_DURATION * 100;
+
+        assert_eq!(scheduler.due_frames(now), MAX_CATCH_UP);
+        assert_eq!(scheduler.deadline(), now + FRAME_DURATION);
+    }
+
+    #[test]
+    fn resync_restarts_from_now() {
+        let mut scheduler = FrameScheduler::new();
+        let now = scheduler.deadline() + Duration::from_secs(10);
+
+        scheduler.resync(now);
+
+        assert_eq!(scheduler.due_frames(now), 0);
+        assert_eq!(scheduler.due_frames(now + FRAME_DURATION), 1);
+    }
+
+    #[test]
+    fn vip_carries_the_overrun_into_the_next_frame() {
+        let mut scheduler = FrameScheduler::new();
+        scheduler.set_model(TimingModel::CosmacVip);
+        let ld = Instruction::LdByte { vx: 0, byte: 0 };
+
+        // 12 000 µs at 27 µs each: the 445th runs over by 15 µs...
+        assert_eq!(run_frame(&mut scheduler, ld), 445);
+        // ...which comes out of the next frame
+        assert_eq!(run_frame(&mut scheduler, ld), 444);
+    }
+
+    #[test]
+    fn vip_sprite_waits_for_vblank_unless_first() {
+        let mut scheduler = FrameScheduler::new();
+        scheduler.set_model(TimingModel::CosmacVip);
+        let cpu = Cpu::new();
+        let ld = Instruction::LdByte { vx: 0, byte: 0 };
+        let drw = Instruction::Drw {
+            vx: 0,
+            vy: 0,
+            nibble: 5,
+        };
+
+        scheduler.begin_frame();
+        assert!(scheduler.admit(&ld, &cpu));
+        assert!(!scheduler.admit(&drw, &cpu));
+
+        scheduler.begin_frame();
+        assert!(scheduler.admit(&drw, &cpu));
+        assert!(scheduler.admit(&ld, &cpu));
+        assert!(!scheduler.admit(&drw, &cpu));
+    }
+}
@@ -0,0 +1,47 @@
+#[cfg(feature = "keys")]
+use std::{
+    fs,
+    path::PathBuf,
+    time::{SystemTime, UNIX_EPOCH},
+};
+use std::{
+    fs::File,
+    io::{self, BufWriter},
+    path::Path,
+};
+
+#[cfg(feature = "keys")]
+use crate::settings::Settings;
+
+/// Writes 8-bit RGB pixels, row by row, as a PNG.
+pub fn write_png(path: &Path, width: usize, height: usize, rgb: &[u8]) -> io::Result<()> {
+    let file = BufWriter::new(File::create(path)?);
+
+    let mut encoder = png::Encoder::new(file, width as u32, height as u32);
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+
+    let mut writer = encoder.write_header().map_err(io::Error::other)?;
+    writer.write_image_data(rgb).map_err(io::Error::other)?;
+    writer.finish().map_err(io::Error::other)
+}
+
+/// A fresh `screenshots/<rom>-<unix time>.png` path in the config directory.
+#[cfg(feature = "keys")]
+pub fn next_path(rom_name: Option<&str>) -> io::Result<PathBuf> {
+    let dir = Settings::dir()
+        .ok_or_else(|| io::Error::other("no config directory"))?
+        .join("screenshots");
+    fs::create_dir_all(&dir)?;
+
+    let stem = rom_name
+        .map(|name| Path::new(name).file_stem().unwrap_or_default())
+        .and_then(|stem| stem.to_str())
+        .unwrap_or("chip8");
+    let time = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis();
+
+    Ok(dir.join(format!("{stem}-{time}.png")))
+}
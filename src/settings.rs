@@ -0,0 +1,191 @@
+use std::{
+    collections::HashMap,
+    env,
+    fs::{self, File},
+    io::{BufReader, BufWriter},
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    key_mapping::{KeyPreset, KeyProfile},
+    palette::Palette,
+    shortcuts::Shortcuts,
+};
+
+/// User preferences that survive restarts, stored next to other per-user config.
+///
+/// Saved as JSON by field name, so adding a field keeps everything else in an older file;
+/// fields the file doesn't have take their default.
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub palette: Palette,
+    pub key_profiles: Vec<KeyProfile>,
+    pub active_profile: String,
+    /// ROM file name to the name of the profile picked for it.
+    pub rom_profiles: HashMap<String, String>,
+    pub shortcuts: Shortcuts,
+}
+
+impl Settings {
+    pub fn profile(&self, name: &str) -> Option<&KeyProfile> {
+        self.key_profiles
+            .iter()
+            .find(|profile| profile.name == name)
+    }
+
+    /// Replaces the profile with the same name, or adds it.
+    pub fn store_profile(&mut self, profile: KeyProfile) {
+        match self
+            .key_profiles
+            .iter_mut()
+            .find(|p| p.name == profile.name)
+        {
+            Some(existing) => *existing = profile,
+            None => self.key_profiles.push(profile),
+        }
+    }
+
+    pub fn remove_profile(&mut self, name: &str) {
+        self.key_profiles.retain(|profile| profile.name != name);
+        self.rom_profiles.retain(|_, profile| profile != name);
+    }
+
+    /// Falls back to defaults if the file is missing or isn't valid settings JSON.
+    pub fn load() -> Self {
+        Self::path()
+            .map(|path| Self::load_from(&path))
+            .unwrap_or_default()
+    }
+
+    /// A file that doesn't parse is moved aside to `settings.json.bak`, so the next save
+    /// doesn't overwrite what the user had.
+    fn load_from(path: &Path) -> Self {
+        let Ok(file) = File::open(path) else {
+            return Self::default();
+        };
+
+        match serde_json::from_reader(BufReader::new(file)) {
+            Ok(settings) => settings,
+            Err(err) => {
+                let backup = path.with_extension("json.bak");
+                eprintln!(
+                    "Couldn't read {}: {err}; starting from defaults, the file is kept as {}",
+                    path.display(),
+                    backup.display()
+                );
+                let _ = fs::rename(path, backup);
+                Self::default()
+            }
+        }
+    }
+
+    pub fn save(&self) {
+        let Some(path) = Self::path() else {
+            return;
+        };
+
+        if let Some(dir) = path.parent() {
+            let _ = fs::create_dir_all(dir);
+        }
+
+        if let Ok(file) = File::create(path) {
+            let _ = serde_json::to_writer_pretty(BufWriter::new(file), self);
+        }
+    }
+
+    /// `chip8` in the per-user config directory; quick saves and screenshots go here too.
+    pub fn dir() -> Option<PathBuf> {
+        let config_dir = env::var_os("XDG_CONFIG_HOME")
+            .or_else(|| env::var_os("APPDATA"))
+            .map(PathBuf::from)
+            .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+
+        Some(config_dir.join("chip8"))
+    }
+
+    fn path() -> Option<PathBuf> {
+        Some(Self::dir()?.join("settings.json"))
+    }
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            palette: Palette::default(),
+            key_profiles: KeyPreset::ALL.map(KeyPreset::profile).to_vec(),
+            active_profile: <&'static str>::from(KeyPreset::Default).to_string(),
+            rom_profiles: HashMap::new(),
+            shortcuts: Shortcuts::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn missing_fields_keep_the_rest() {
+        let settings: Settings =
+            serde_json::from_str(r#"{ "palette": { "colors": [[1, 2, 3], [4, 5, 6]] } }"#).unwrap();
+
+        assert_eq!(settings.palette.colors(), [[1, 2, 3], [4, 5, 6]]);
+        assert_eq!(settings.active_profile, Settings::default().active_profile);
+        assert_eq!(settings.key_profiles.len(), KeyPreset::ALL.len());
+    }
+
+    #[test]
+    fn round_trips() {
+        let mut settings = Settings {
+            active_profile: "arrows".to_string(),
+            ..Settings::default()
+        };
+        settings
+            .rom_profiles
+            .insert("pong.ch8".to_string(), "arrows".to_string());
+
+        let json = serde_json::to_string(&settings).unwrap();
+        let loaded: Settings = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(loaded.active_profile, "arrows");
+        assert_eq!(loaded.rom_profiles["pong.ch8"], "arrows");
+        assert!(loaded.palette == settings.palette);
+    }
+
+    #[test]
+    fn a_file_that_does_not_parse_is_kept() {
+        let dir = std::env::temp_dir().join(format!("chip8-settings-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("settings.json");
+        let backup = dir.join("settings.json.bak");
+
+        assert_eq!(
+            Settings::load_from(&path).active_profile,
+            Settings::default().active_profile
+        );
+
+        fs::write(&path, r#"{ "active_profile": "arrows" "#).unwrap();
+        let settings = Settings::load_from(&path);
+        assert_eq!(settings.active_profile, Settings::default().active_profile);
+        assert!(!path.exists());
+        assert_eq!(
+            fs::read_to_string(&backup).unwrap(),
+            r#"{ "active_profile": "arrows" "#
+        );
+
+        // A bad shortcut costs only that shortcut
+        fs::write(
+            &path,
+            r#"{ "active_profile": "arrows", "shortcuts": { "bindings": { "Step": "Nope" } } }"#,
+        )
+        .unwrap();
+        let settings = Settings::load_from(&path);
+        assert_eq!(settings.active_profile, "arrows");
+        assert!(path.exists());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
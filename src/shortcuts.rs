@@ -0,0 +1,312 @@
+use std::{collections::HashMap, fmt, str::FromStr};
+
+use egui::{Key, Modifiers};
+use serde::{Deserialize, Deserializer, Serialize, Serializer, de};
+
+use crate::key_mapping::KeyMapping;
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Action {
+    PauseResume,
+    Reset,
+    HardReset,
+    Step,
+    QuickSave,
+    QuickLoad,
+    Screenshot,
+    FastForward,
+    Rewind,
+    Fullscreen,
+    OpenRom,
+}
+
+impl Action {
+    pub const ALL: [Action; 11] = [
+        Action::PauseResume,
+        Action::Reset,
+        Action::HardReset,
+        Action::Step,
+        Action::QuickSave,
+        Action::QuickLoad,
+        Action::Screenshot,
+        Action::FastForward,
+        Action::Rewind,
+        Action::Fullscreen,
+        Action::OpenRom,
+    ];
+
+    /// Held actions last from key press to key release instead of firing once.
+    pub fn is_held(self) -> bool {
+        matches!(self, Action::FastForward | Action::Rewind)
+    }
+
+    fn default_shortcut(self) -> Shortcut {
+        match self {
+            Action::PauseResume => Shortcut::ctrl(Key::P),
+            Action::Reset => Shortcut::ctrl(Key::R),
+            Action::HardReset => Shortcut::new(Key::R, Modifiers::COMMAND | Modifiers::SHIFT),
+            Action::Step => Shortcut::plain(Key::F10),
+            Action::QuickSave => Shortcut::plain(Key::F5),
+            Action::QuickLoad => Shortcut::plain(Key::F7),
+            Action::Screenshot => Shortcut::plain(Key::F12),
+            Action::FastForward => Shortcut::plain(Key::Tab),
+            Action::Rewind => Shortcut::plain(Key::Backspace),
+            Action::Fullscreen => Shortcut::plain(Key::F11),
+            Action::OpenRom => Shortcut::ctrl(Key::O),
+        }
+    }
+}
+
+impl From<Action> for &'static str {
+    fn from(action: Action) -> Self {
+        match action {
+            Action::PauseResume => "Pause / Resume",
+            Action::Reset => "Reset",
+            Action::HardReset => "Hard Reset",
+            Action::Step => "Step",
+            Action::QuickSave => "Quick Save",
+            Action::QuickLoad => "Quick Load",
+            Action::Screenshot => "Screenshot",
+            Action::FastForward => "Fast-forward (hold)",
+            Action::Rewind => "Rewind (hold)",
+            Action::Fullscreen => "Fullscreen",
+            Action::OpenRom => "Open ROM",
+        }
+    }
+}
+
+/// A key plus modifiers, written as e.g. `Ctrl+Shift+O`.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct Shortcut {
+    pub key: Key,
+    pub ctrl: bool,
+    pub shift: bool,
+    pub alt: bool,
+}
+
+impl Shortcut {
+    pub fn new(key: Key, modifiers: Modifiers) -> Self {
+        Self {
+            key,
+            ctrl: modifiers.command || modifiers.ctrl,
+            shift: modifiers.shift,
+            alt: modifiers.alt,
+        }
+    }
+
+    fn plain(key: Key) -> Self {
+        Self::new(key, Modifiers::NONE)
+    }
+
+    fn ctrl(key: Key) -> Self {
+        Self::new(key, Modifiers::COMMAND)
+    }
+
+    pub fn has_modifiers(&self) -> bool {
+        self.ctrl || self.shift || self.alt
+    }
+}
+
+impl fmt::Display for Shortcut {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.ctrl {
+            write!(f, "Ctrl+")?;
+        }
+        if self.shift {
+            write!(f, "Shift+")?;
+        }
+        if self.alt {
+            write!(f, "Alt+")?;
+        }
+        write!(f, "{}", self.key.name())
+    }
+}
+
+impl FromStr for Shortcut {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let (modifiers, key) = text.rsplit_once('+').unwrap_or(("", text));
+        let key = Key::from_name(key).ok_or_else(|| format!("unknown key `{key}`"))?;
+
+        let mut shortcut = Shortcut::plain(key);
+        for modifier in modifiers.split('+').filter(|m| !m.is_empty()) {
+            match modifier {
+                "Ctrl" => shortcut.ctrl = true,
+                "Shift" => shortcut.shift = true,
+                "Alt" => shortcut.alt = true,
+                _ => return Err(format!("unknown modifier `{modifier}`")),
+            }
+        }
+
+        Ok(shortcut)
+    }
+}
+
+// Stored as text so the settings file doesn't depend on egui's `Key` layout
+impl Serialize for Shortcut {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Shortcut {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(de::Error::custom)
+    }
+}
+
+/// Shortcut table for emulator actions.
+///
+/// Shortcuts without modifiers never take a key away from the CHIP-8 keypad: while the
+/// active key profile binds the same key, the shortcut is ignored and flagged as a conflict.
+#[derive(Serialize, Clone)]
+pub struct Shortcuts {
+    bindings: HashMap<Action, Shortcut>,
+}
+
+impl Shortcuts {
+    pub fn new() -> Self {
+        Self {
+            bindings: Action::ALL
+                .into_iter()
+                .map(|action| (action, action.default_shortcut()))
+                .collect(),
+        }
+    }
+
+    pub fn get(&self, action: Action) -> Option<Shortcut> {
+        self.bindings.get(&action).copied()
+    }
+
+    pub fn set(&mut self, action: Action, shortcut: Shortcut) {
+        self.bindings.insert(action, shortcut);
+    }
+
+    pub fn clear(&mut self, action: Action) {
+        self.bindings.remove(&action);
+    }
+
+    /// The action a key press triggers, if any.
+    pub fn action(&self, shortcut: Shortcut, key_mapping: &KeyMapping) -> Option<Action> {
+        if !shortcut.has_modifiers() && key_mapping.get_chip8_key(&shortcut.key).is_some() {
+            return None;
+        }
+
+        Action::ALL
+            .into_iter()
+            .find(|&action| self.get(action) == Some(shortcut))
+    }
+
+    /// The held action a key release ends. Modifiers are ignored since they may have been
+    /// let go first.
+    pub fn held_action(&self, key: Key) -> Option<Action> {
+        Action::ALL
+            .into_iter()
+            .filter(|action| action.is_held())
+            .find(|&action| self.get(action).is_some_and(|s| s.key == key))
+    }
+
+    /// Why `action`'s shortcut won't work as expected, if it won't.
+    pub fn conflict(&self, action: Action, key_mapping: &KeyMapping) -> Option<String> {
+        let shortcut = self.get(action)?;
+
+        if let Some(other) = Action::ALL
+            .into_iter()
+            .find(|&other| other != action && self.get(other) == Some(shortcut))
+        {
+            return Some(format!("Also used by {}", <&'static str>::from(other)));
+        }
+
+        if !shortcut.has_modifiers()
+            && let Some(chip8_key) = key_mapping.get_chip8_key(&shortcut.key)
+        {
+            return Some(format!(
+                "Ignored: {} is bound to CHIP-8 key {}",
+                shortcut.key.name(),
+                <&'static str>::from(chip8_key)
+            ));
+        }
+
+        None
+    }
+}
+
+// Entry by entry, so one shortcut this version can't read (an action or key from a newer
+// version, or a hand-edited typo) doesn't throw away the whole settings file
+impl<'de> Deserialize<'de> for Shortcuts {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        struct Stored {
+            bindings: HashMap<String, serde_json::Value>,
+        }
+
+        let stored = Stored::deserialize(deserializer)?;
+
+        let mut bindings = HashMap::new();
+        for (action, shortcut) in stored.bindings {
+            let parsed =
+                serde_json::from_value::<Action>(serde_json::Value::String(action.clone()))
+                    .map_err(|_| format!("unknown action `{action}`"))
+                    .and_then(|action| match shortcut.as_str() {
+                        Some(text) => Ok((action, text.parse::<Shortcut>()?)),
+                        None => Err(format!("expected text, found `{shortcut}`")),
+                    });
+
+            match parsed {
+                Ok((action, shortcut)) => {
+                    bindings.insert(action, shortcut);
+                }
+                Err(err) => eprintln!("Skipping shortcut for {action}: {err}"),
+            }
+        }
+
+        Ok(Self { bindings })
+    }
+}
+
+impl Default for Shortcuts {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips() {
+        let mut shortcuts = Shortcuts::new();
+        shortcuts.set(Action::Step, Shortcut::new(Key::S, Modifiers::SHIFT));
+        shortcuts.clear(Action::OpenRom);
+
+        let json = serde_json::to_string(&shortcuts).unwrap();
+        let loaded: Shortcuts = serde_json::from_str(&json).unwrap();
+
+        for action in Action::ALL {
+            assert!(loaded.get(action) == shortcuts.get(action));
+        }
+    }
+
+    #[test]
+    fn skips_entries_it_cannot_read() {
+        let shortcuts: Shortcuts = serde_json::from_str(
+            r#"{ "bindings": {
+                "Step": "Shift+S",
+                "Reset": "Ctrl+NoSuchKey",
+                "Teleport": "F1",
+                "QuickSave": 5,
+                "QuickLoad": "Hyper+F7"
+            } }"#,
+        )
+        .unwrap();
+
+        assert!(shortcuts.get(Action::Step) == Some(Shortcut::new(Key::S, Modifiers::SHIFT)));
+        for action in [Action::Reset, Action::QuickSave, Action::QuickLoad] {
+            assert!(shortcuts.get(action).is_none());
+        }
+    }
+}
@@ -0,0 +1,118 @@
+use eframe::egui::{self, Ui};
+
+use crate::{
+    key_mapping::KeyMapping,
+    shortcuts::{Action, Shortcut, Shortcuts},
+};
+
+pub struct ShortcutsView {
+    pub open: bool,
+    /// Action waiting for its new key combination.
+    capturing: Option<Action>,
+}
+
+impl ShortcutsView {
+    pub fn new() -> Self {
+        Self {
+            open: false,
+            capturing: None,
+        }
+    }
+
+    pub fn is_capturing(&self) -> bool {
+        self.capturing.is_some()
+    }
+
+    pub fn close(&mut self) {
+        self.open = false;
+        self.capturing = None;
+    }
+
+    /// Returns true when `shortcuts` was changed and should be saved.
+    pub fn ui(&mut self, ui: &mut Ui, shortcuts: &mut Shortcuts, key_mapping: &KeyMapping) -> bool {
+        let mut changed = false;
+
+        if let Some(action) = self.capturing {
+            let pressed = ui.input(|i| {
+                i.events.iter().find_map(|event| match event {
+                    egui::Event::Key {
+                        key,
+                        pressed: true,
+                        modifiers,
+                        ..
+                    } => Some(Shortcut::new(*key, *modifiers)),
+                    _ => None,
+                })
+            });
+
+            match pressed {
+                Some(shortcut)
+                    if shortcut.key == egui::Key::Escape && !shortcut.has_modifiers() =>
+                {
+                    self.capturing = None;
+                }
+                Some(shortcut) => {
+                    shortcuts.set(action, shortcut);
+                    self.capturing = None;
+                    changed = true;
+                }
+                None => {}
+            }
+        }
+
+        egui::Grid::new("shortcuts")
+            .num_columns(4)
+            .striped(true)
+            .spacing([12.0, 6.0])
+            .show(ui, |ui| {
+                for action in Action::ALL {
+                    ui.label(<&'static str>::from(action));
+
+                    if self.capturing == Some(action) {
+                        ui.label("Press a key… (Esc to cancel)");
+                    } else {
+                        let text = shortcuts
+                            .get(action)
+                            .map(|shortcut| shortcut.to_string())
+                            .unwrap_or_else(|| "—".to_string());
+                        ui.monospace(text);
+                    }
+
+                    ui.horizontal(|ui| {
+                        if ui.small_button("Set").clicked() {
+                            self.capturing = Some(action);
+                        }
+                        if ui.small_button("Clear").clicked() {
+                            shortcuts.clear(action);
+                            changed = true;
+                        }
+                    });
+
+                    match shortcuts.conflict(action, key_mapping) {
+                        Some(conflict) => {
+                            ui.colored_label(ui.visuals().warn_fg_color, conflict);
+                        }
+                        None => {
+                            ui.label("");
+                        }
+                    }
+                    ui.end_row();
+                }
+            });
+
+        ui.separator();
+        if ui.button("Restore defaults").clicked() {
+            *shortcuts = Shortcuts::new();
+            self.capturing = None;
+            changed = true;
+        }
+
+        changed
+    }
+}
+
+impl Default for ShortcutsView {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,292 @@
+use std::{
+    collections::BTreeMap,
+    fmt, fs, io,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use crate::{disassembler::Line, instruction::Instruction, memory::parse_addr};
+
+#[derive(Debug)]
+pub enum SymbolError {
+    Io(io::Error),
+    /// 1-based line number of a line that is neither `label = addr` nor `addr label`.
+    Syntax(usize),
+    Json(String),
+}
+
+impl fmt::Display for SymbolError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SymbolError::Io(err) => write!(f, "{err}"),
+            SymbolError::Syntax(line) => {
+                write!(
+                    f,
+                    "expected `label = 0x2A0` or `0x2A0 label` on line {line}"
+                )
+            }
+            SymbolError::Json(err) => write!(f, "invalid JSON: {err}"),
+        }
+    }
+}
+
+/// Label names for ROM addresses.
+///
+/// Reads either `label = 0x2A0` or `0x2A0 label` lines (with `#` comments), or a JSON
+/// object as written by Octo. Names come from its `"labels"` and `"breakpoints"` maps, or
+/// the top level when it has neither; `"aliases"` name registers, not addresses.
+#[derive(Default)]
+pub struct Symbols {
+    labels: BTreeMap<u16, String>,
+}
+
+impl Symbols {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn load(path: &Path) -> Result<Self, SymbolError> {
+        let text = fs::read_to_string(path).map_err(SymbolError::Io)?;
+        text.parse()
+    }
+
+    /// Looks for `game.sym`, `game.json` or `game.ch8.sym` next to `game.ch8`.
+    pub fn discover(rom_path: &str) -> Option<Self> {
+        let rom = Path::new(rom_path);
+        let mut sym = rom.as_os_str().to_owned();
+        sym.push(".sym");
+
+        [
+            rom.with_extension("sym"),
+            rom.with_extension("json"),
+            PathBuf::from(sym),
+        ]
+        .iter()
+        .find(|path| path.is_file())
+        .and_then(|path| Self::load(path).ok())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.labels.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.labels.len()
+    }
+
+    pub fn insert(&mut self, addr: u16, name: String) {
+        self.labels.insert(addr, name);
+    }
+
+    pub fn name(&self, addr: u16) -> Option<&str> {
+        self.labels.get(&addr).map(String::as_str)
+    }
+
+    pub fn addr(&self, name: &str) -> Option<u16> {
+        self.labels
+            .iter()
+            .find(|(_, label)| label.as_str() == name)
+            .map(|(&addr, _)| addr)
+    }
+
+    /// The closest label at or before `addr`, as `label` or `label+offset`.
+    pub fn nearest(&self, addr: u16) -> Option<String> {
+        let (&start, name) = self.labels.range(..=addr).next_back()?;
+
+        Some(match addr - start {
+            0 => name.clone(),
+            offset => format!("{name}+{offset:X}"),
+        })
+    }
+
+    /// `label` if there is one, else the address in hex.
+    pub fn format(&self, addr: u16) -> String {
+        match self.name(addr) {
+            Some(name) => name.to_string(),
+            None => format!("{addr:03X}"),
+        }
+    }
+
+    /// Comment for a disassembly line: its own label and the label it jumps, calls or points to.
+    pub fn annotate(&self, line: &Line) -> Option<String> {
+        let target = match line.instruction {
+            Instruction::Jp { addr }
+            | Instruction::JpV0 { addr }
+            | Instruction::Call { addr }
+            | Instruction::LdI { addr } => self.name(addr),
+            _ => None,
+        };
+
+        match (self.name(line.addr), target) {
+            (Some(label), Some(target)) => Some(format!("<{label}> → {target}")),
+            (Some(label), None) => Some(format!("<{label}>")),
+            (None, Some(target)) => Some(format!("→ {target}")),
+            (None, None) => None,
+        }
+    }
+
+    fn parse_json(text: &str) -> Result<Self, SymbolError> {
+        let value = serde_json::from_str::<serde_json::Value>(text)
+            .map_err(|err| SymbolError::Json(err.to_string()))?;
+
+        let maps = match (value.get("labels"), value.get("breakpoints")) {
+            (None, None) => vec![&value],
+            // Labels go last so they win over a breakpoint at the same address
+            (labels, breakpoints) => breakpoints.into_iter().chain(labels).collect(),
+        };
+
+        let mut symbols = Symbols::new();
+        for map in maps {
+            let object = map
+                .as_object()
+                .ok_or_else(|| SymbolError::Json("expected an object of labels".to_string()))?;
+
+            for (name, addr) in object {
+                if let Some(addr) = addr.as_u64().and_then(|addr| u16::try_from(addr).ok()) {
+                    symbols.insert(addr, name.clone());
+                }
+            }
+        }
+
+        Ok(symbols)
+    }
+}
+
+impl FromStr for Symbols {
+    type Err = SymbolError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        if text.trim_start().starts_with('{') {
+            return Self::parse_json(text);
+        }
+
+        let mut symbols = Symbols::new();
+
+        for (index, line) in text.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            let (name, addr) = match line.split_once('=') {
+                Some((name, addr)) => (name.trim(), parse_addr(addr)),
+                // Assembler listings put the address first
+                None => match line.split_once(char::is_whitespace) {
+                    Some((addr, name)) => (name.trim(), parse_addr(addr)),
+                    None => ("", None),
+                },
+            };
+            let addr = addr.ok_or(SymbolError::Syntax(index + 1))?;
+
+            if name.is_empty() || name.contains(char::is_whitespace) {
+                return Err(SymbolError::Syntax(index + 1));
+            }
+
+            symbols.insert(addr, name.to_string());
+        }
+
+        Ok(symbols)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(text: &str) -> Symbols {
+        text.parse().unwrap()
+    }
+
+    fn syntax_error(text: &str) -> usize {
+        match text.parse::<Symbols>() {
+            Err(SymbolError::Syntax(line)) => line,
+            _ => panic!("expected a syntax error in {text:?}"),
+        }
+    }
+
+    #[test]
+    fn reads_labels_either_way_round() {
+        let symbols = parse("start = 0x200\n0x2A0 draw\n$2B0\tloop\n");
+
+        assert_eq!(symbols.len(), 3);
+        assert_eq!(symbols.name(0x200), Some("start"));
+        assert_eq!(symbols.name(0x2A0), Some("draw"));
+        assert_eq!(symbols.addr("loop"), Some(0x2B0));
+    }
+
+    #[test]
+    fn skips_comments_and_blank_lines() {
+        let symbols = parse("# generated\n\n  start = 0x200  # entry\n   \n0x2A0 draw # sprite\n");
+
+        assert_eq!(symbols.len(), 2);
+        assert_eq!(symbols.name(0x200), Some("start"));
+        assert_eq!(symbols.name(0x2A0), Some("draw"));
+    }
+
+    #[test]
+    fn reports_the_bad_line() {
+        assert_eq!(syntax_error("start = 0x200\n\nstart"), 3);
+        assert_eq!(syntax_error("start = 0x200\nloop = nowhere"), 2);
+        assert_eq!(syntax_error("= 0x200"), 1);
+        assert_eq!(syntax_error("# ok\nmain loop = 0x200"), 2);
+        assert_eq!(syntax_error("0x200 main loop"), 1);
+        assert_eq!(syntax_error("zzz start"), 1);
+    }
+
+    #[test]
+    fn reads_octo_json() {
+        let symbols = parse(
+            r#"{
+                "labels": { "main": 512, "draw": 672 },
+                "breakpoints": { "check-score": 700, "main-break": 512 },
+                "aliases": { "px": 0, "py": 1 }
+            }"#,
+        );
+
+        assert_eq!(symbols.len(), 3);
+        assert_eq!(symbols.name(0x200), Some("main"));
+        assert_eq!(symbols.name(0x2A0), Some("draw"));
+        assert_eq!(symbols.name(700), Some("check-score"));
+        // Register aliases aren't addresses
+        assert_eq!(symbols.addr("px"), None);
+
+        let symbols = parse(r#"{ "breakpoints": { "hit": 514 } }"#);
+        assert_eq!(symbols.name(0x202), Some("hit"));
+
+        let symbols = parse(r#"{ "main": 512, "aliases": { "px": 0 } }"#);
+        assert_eq!(symbols.len(), 1);
+        assert_eq!(symbols.name(0x200), Some("main"));
+
+        assert!(matches!(
+            "{ \"labels\": [] }".parse::<Symbols>(),
+            Err(SymbolError::Json(_))
+        ));
+        assert!(matches!(
+            "{ nope".parse::<Symbols>(),
+            Err(SymbolError::Json(_))
+        ));
+    }
+
+    #[test]
+    fn discovers_files_next_to_the_rom() {
+        for (name, extension, text) in [
+            ("sym", ".sym", "main = 0x200"),
+            ("json", ".json", r#"{ "labels": { "main": 512 } }"#),
+            ("ch8-sym", ".ch8.sym", "0x200 main"),
+        ] {
+            let dir =
+                std::env::temp_dir().join(format!("chip8-symbols-{}-{name}", std::process::id()));
+            fs::create_dir_all(&dir).unwrap();
+            let rom = dir.join("game.ch8");
+            let rom = rom.to_str().unwrap();
+
+            assert!(Symbols::discover(rom).is_none());
+
+            fs::write(dir.join(format!("game{extension}")), text).unwrap();
+            let symbols = Symbols::discover(rom).unwrap();
+            assert_eq!(symbols.name(0x200), Some("main"), "{extension}");
+
+            fs::remove_dir_all(&dir).unwrap();
+        }
+    }
+}
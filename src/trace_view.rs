@@ -0,0 +1,118 @@
+use eframe::egui::{self, Ui};
+
+use crate::{Message, handle::Chip8Handle, memory::parse_addr, tracer::TraceConfig};
+
+pub struct TraceView {
+    pub open: bool,
+    pub tracing: bool,
+    pub error: Option<String>,
+    pub path: String,
+
+    filter: bool,
+    range_start: String,
+    range_end: String,
+
+    ring: bool,
+    ring_size: usize,
+}
+
+impl TraceView {
+    pub fn new() -> Self {
+        Self {
+            open: false,
+            tracing: false,
+            error: None,
+            path: "trace.log".to_string(),
+            filter: false,
+            range_start: "200".to_string(),
+            range_end: "FFF".to_string(),
+            ring: false,
+            ring_size: 10_000,
+        }
+    }
+
+    /// Returns true when the user asked to pick the output file.
+    pub fn ui(&mut self, ui: &mut Ui, handle: &Chip8Handle) -> bool {
+        let mut browse = false;
+
+        ui.add_enabled_ui(!self.tracing, |ui| {
+            ui.horizontal(|ui| {
+                ui.label("Output");
+                ui.add(egui::TextEdit::singleline(&mut self.path).desired_width(220.0));
+                browse = ui.button("Browse").clicked();
+            });
+
+            ui.horizontal(|ui| {
+                ui.checkbox(&mut self.filter, "Only PC in");
+                ui.add_enabled(
+                    self.filter,
+                    egui::TextEdit::singleline(&mut self.range_start).desired_width(40.0),
+                );
+                ui.label("..=");
+                ui.add_enabled(
+                    self.filter,
+                    egui::TextEdit::singleline(&mut self.range_end).desired_width(40.0),
+                );
+            });
+
+            ui.horizontal(|ui| {
+                ui.checkbox(&mut self.ring, "Keep only the last");
+                ui.add_enabled(
+                    self.ring,
+                    egui::DragValue::new(&mut self.ring_size).range(1..=1_000_000),
+                );
+                ui.label("instructions");
+            });
+        });
+
+        ui.separator();
+
+        ui.horizontal(|ui| {
+            if self.tracing {
+                if ui.button("Stop").clicked() {
+                    handle.send(Message::StopTrace);
+                }
+                ui.label(format!("Tracing to {}", self.path));
+            } else if ui.button("Start").clicked() {
+                match self.config() {
+                    Some(config) => {
+                        handle.send(Message::StartTrace(config));
+                        self.error = None;
+                    }
+                    None => self.error = Some("Invalid address range".to_string()),
+                }
+            }
+        });
+
+        if let Some(ref error) = self.error {
+            ui.colored_label(ui.visuals().error_fg_color, error);
+        }
+
+        browse
+    }
+
+    fn config(&self) -> Option<TraceConfig> {
+        let range = if self.filter {
+            let start = parse_addr(&self.range_start)?;
+            let end = parse_addr(&self.range_end)?;
+            if start > end {
+                return None;
+            }
+            Some((start, end))
+        } else {
+            None
+        };
+
+        Some(TraceConfig {
+            path: self.path.clone(),
+            range,
+            ring_size: self.ring.then_some(self.ring_size),
+        })
+    }
+}
+
+impl Default for TraceView {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,210 @@
+use std::{
+    collections::VecDeque,
+    fmt::Write as _,
+    fs::File,
+    io::{self, BufWriter, Write},
+    sync::Arc,
+};
+
+use crate::{cpu::Cpu, instruction::Instruction, symbols::Symbols};
+
+#[derive(Clone)]
+pub struct TraceConfig {
+    pub path: String,
+    /// Only instructions with a PC in `start..=end` are logged.
+    pub range: Option<(u16, u16)>,
+    /// Keep only the last `n` lines in memory and write them out when the trace stops.
+    pub ring_size: Option<usize>,
+}
+
+/// Writes one line per executed instruction:
+///
+/// `PPP OOOO MNEMONIC             V0=.. .. VF=.. I=III SP=S DT=.. ST=..`
+///
+/// with PC and opcode of the instruction and the register state after it ran, followed by
+/// the label at PC when there is one.
+pub struct Tracer {
+    range: Option<(u16, u16)>,
+    ring_size: Option<usize>,
+    ring: VecDeque<String>,
+    writer: BufWriter<File>,
+    symbols: Arc<Symbols>,
+}
+
+impl Tracer {
+    pub fn new(config: TraceConfig, symbols: Arc<Symbols>) -> io::Result<Self> {
+        Ok(Self {
+            range: config.range,
+            ring_size: config.ring_size,
+            ring: VecDeque::new(),
+            writer: BufWriter::new(File::create(config.path)?),
+            symbols,
+        })
+    }
+
+    pub fn record(
+        &mut self,
+        pc: u16,
+        opcode: u16,
+        instruction: &Instruction,
+        cpu: &Cpu,
+    ) -> io::Result<()> {
+        if let Some((start, end)) = self.range
+            && !(start..=end).contains(&pc)
+        {
+            return Ok(());
+        }
+
+        let line = format_line(pc, opcode, instruction, cpu, &self.symbols);
+
+        match self.ring_size {
+            Some(size) => {
+                if self.ring.len() >= size {
+                    self.ring.pop_front();
+                }
+                if size > 0 {
+                    self.ring.push_back(line);
+                }
+                Ok(())
+            }
+            None => writeln!(self.writer, "{line}"),
+        }
+    }
+
+    /// Writes out any buffered lines; called when tracing stops.
+    pub fn finish(&mut self) -> io::Result<()> {
+        for line in self.ring.drain(..) {
+            writeln!(self.writer, "{line}")?;
+        }
+        self.writer.flush()
+    }
+}
+
+impl Drop for Tracer {
+    fn drop(&mut self) {
+        let _ = self.finish();
+    }
+}
+
+pub fn format_line(
+    pc: u16,
+    opcode: u16,
+    instruction: &Instruction,
+    cpu: &Cpu,
+    symbols: &Symbols,
+) -> String {
+    let mut line = format!("{pc:03X} {opcode:04X} {:<20}", instruction.to_string());
+
+    for (index, value) in cpu.v().iter().enumerate() {
+        let _ = write!(line, " V{index:X}={value:02X}");
+    }
+    let _ = write!(
+        line,
+        " I={:03X} SP={:X} DT={:02X} ST={:02X}",
+        cpu.i(),
+        cpu.sp(),
+        cpu.dt(),
+        cpu.st()
+    );
+    if let Some(label) = symbols.name(pc) {
+        let _ = write!(line, " {label}");
+    }
+
+    line
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        fs,
+        sync::{Arc, Mutex},
+    };
+
+    use super::*;
+    use crate::{chip8::Chip8, cpu::Register, key_matrix::KeyMatrix, memory::ROM_START_ADDR};
+
+    /// LD V0, 1; LD V1, 2; LD V2, 3; LD V3, 4
+    const ROM: [u8; 8] = [0x60, 0x01, 0x61, 0x02, 0x62, 0x03, 0x63, 0x04];
+
+    /// A path in the temp directory that other test runs won't touch.
+    fn scratch(name: &str) -> String {
+        let dir = std::env::temp_dir();
+        let path = dir.join(format!("chip8-tracer-{}-{name}", std::process::id()));
+        path.display().to_string()
+    }
+
+    /// Runs the ROM with a trace to `name` and returns the lines written.
+    fn trace(name: &str, range: Option<(u16, u16)>, ring_size: Option<usize>) -> Vec<String> {
+        let path = scratch(name);
+        let mut chip8 = Chip8::new(Arc::new(Mutex::new(KeyMatrix::new())));
+        for (offset, &byte) in ROM.iter().enumerate() {
+            chip8
+                .write_memory(ROM_START_ADDR + offset as u16, byte)
+                .unwrap();
+        }
+
+        chip8
+            .start_trace(TraceConfig {
+                path: path.clone(),
+                range,
+                ring_size,
+            })
+            .unwrap();
+        for _ in 0..ROM.len() / 2 {
+            chip8.tick().unwrap();
+        }
+        chip8.stop_trace().unwrap();
+
+        let text = fs::read_to_string(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        text.lines().map(str::to_string).collect()
+    }
+
+    /// The program counter each line was logged at.
+    fn pcs(lines: &[String]) -> Vec<&str> {
+        lines.iter().map(|line| &line[..3]).collect()
+    }
+
+    #[test]
+    fn formats_registers_after_the_instruction_and_its_label() {
+        let mut cpu = Cpu::new();
+        cpu.set_register(Register::V(0), 0x2A);
+        let mut symbols = Symbols::new();
+        symbols.insert(0x200, "start".to_string());
+
+        let instruction = Instruction::LdByte { vx: 0, byte: 0x2A };
+        assert_eq!(
+            format_line(0x200, 0x602A, &instruction, &cpu, &symbols),
+            "200 602A LD V0, 0x2A          \
+             V0=2A V1=00 V2=00 V3=00 V4=00 V5=00 V6=00 V7=00 \
+             V8=00 V9=00 VA=00 VB=00 VC=00 VD=00 VE=00 VF=00 \
+             I=000 SP=0 DT=00 ST=00 start"
+        );
+
+        let line = format_line(0x202, 0x602A, &instruction, &cpu, &symbols);
+        assert!(line.ends_with("ST=00"));
+    }
+
+    #[test]
+    fn writes_every_instruction_to_the_file() {
+        let lines = trace("all", None, None);
+
+        assert_eq!(pcs(&lines), ["200", "202", "204", "206"]);
+        assert!(lines[1].starts_with("202 6102 LD V1, 0x02 "));
+        assert!(lines[1].contains(" V0=01 V1=02 V2=00 "));
+    }
+
+    #[test]
+    fn logs_only_the_range() {
+        let lines = trace("range", Some((0x202, 0x204)), None);
+        assert_eq!(pcs(&lines), ["202", "204"]);
+    }
+
+    #[test]
+    fn ring_keeps_the_last_lines_until_the_trace_stops() {
+        let lines = trace("ring", None, Some(2));
+        assert_eq!(pcs(&lines), ["204", "206"]);
+
+        assert!(trace("ring-empty", None, Some(0)).is_empty());
+    }
+}
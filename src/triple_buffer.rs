@@ -0,0 +1,144 @@
+use std::sync::{
+    Arc,
+    atomic::{AtomicU8, AtomicU64, Ordering},
+};
+
+use crate::frame_buffer::{FRAME_BUFFER_ROWS, FrameBuffer};
+
+/// Set in `middle` while the slot it names holds a frame the reader hasn't taken.
+const FRESH: u8 = 0b100;
+const INDEX: u8 = 0b011;
+
+type Slot = [AtomicU64; FRAME_BUFFER_ROWS];
+
+/// Three frame slots. The writer owns one (the back), the reader owns one (the front),
+/// and the third (the middle) is handed between them with a single atomic swap, so
+/// neither side ever waits for the other.
+struct Shared {
+    slots: [Slot; 3],
+    /// Index of the middle slot, plus `FRESH`.
+    middle: AtomicU8,
+}
+
+/// The emulator thread's end of a triple buffer.
+pub struct FrameWriter {
+    shared: Arc<Shared>,
+    back: usize,
+}
+
+/// The UI's end of a triple buffer.
+pub struct FrameReader {
+    shared: Arc<Shared>,
+    front: usize,
+}
+
+/// Hands frames from one thread to another without locks. Publishing never blocks, and
+/// a reader that falls behind (a minimized window stops repainting) gets only the newest
+/// frame instead of a queue of old ones.
+pub fn triple_buffer() -> (FrameWriter, FrameReader) {
+    let shared = Arc::new(Shared {
+        slots: Default::default(),
+        middle: AtomicU8::new(1),
+    });
+
+    (
+        FrameWriter {
+            shared: shared.clone(),
+            back: 0,
+        },
+        FrameReader { shared, front: 2 },
+    )
+}
+
+impl FrameWriter {
+    /// Publishes `frame_buffer`, replacing a frame the reader hasn't taken yet.
+    pub fn write(&mut self, frame_buffer: &FrameBuffer) {
+        // Only this side touches the back slot, so relaxed stores do; the swap below
+        // releases them to the reader
+        for (slot, &row) in self.shared.slots[self.back].iter().zip(frame_buffer.rows()) {
+            slot.store(row, Ordering::Relaxed);
+        }
+
+        let middle = self
+            .shared
+            .middle
+            .swap(self.back as u8 | FRESH, Ordering::AcqRel);
+        self.back = (middle & INDEX) as usize;
+    }
+}
+
+impl FrameReader {
+    /// The newest frame, if one was published since the last call.
+    pub fn read(&mut self) -> Option<FrameBuffer> {
+        // Only the writer sets `FRESH`, so it can't be cleared between here and the swap
+        if self.shared.middle.load(Ordering::Relaxed) & FRESH == 0 {
+            return None;
+        }
+
+        let middle = self.shared.middle.swap(self.front as u8, Ordering::AcqRel);
+        self.front = (middle & INDEX) as usize;
+
+        let mut rows = [0; FRAME_BUFFER_ROWS];
+        for (row, slot) in rows.iter_mut().zip(&self.shared.slots[self.front]) {
+            *row = slot.load(Ordering::Relaxed);
+        }
+        Some(FrameBuffer::from_rows(rows))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use super::*;
+
+    /// A frame with the first `n` pixels of the top and bottom rows lit.
+    fn frame(n: usize) -> FrameBuffer {
+        let mut frame_buffer = FrameBuffer::new();
+        for x in 0..n {
+            frame_buffer.xor(x, 0, true);
+            frame_buffer.xor(x, FRAME_BUFFER_ROWS - 1, true);
+        }
+        frame_buffer
+    }
+
+    #[test]
+    fn reader_gets_only_the_newest_frame() {
+        let (mut writer, mut reader) = triple_buffer();
+        assert!(reader.read().is_none());
+
+        writer.write(&frame(1));
+        writer.write(&frame(2));
+        writer.write(&frame(3));
+
+        assert!(reader.read() == Some(frame(3)));
+        assert!(reader.read().is_none());
+
+        writer.write(&frame(4));
+        assert!(reader.read() == Some(frame(4)));
+    }
+
+    #[test]
+    fn frames_arrive_whole_across_threads() {
+        let (mut writer, mut reader) = triple_buffer();
+
+        let writing = thread::spawn(move || {
+            for n in 1..=64 {
+                writer.write(&frame(n));
+            }
+        });
+
+        let mut last = 0;
+        while last < 64 {
+            if let Some(frame_buffer) = reader.read() {
+                // Never torn between rows, and never older than the last one
+                let lit = (0..64).take_while(|&x| frame_buffer.get(x, 0)).count();
+                assert!(frame_buffer == frame(lit));
+                assert!(lit > last);
+                last = lit;
+            }
+        }
+
+        writing.join().unwrap();
+    }
+}
@@ -0,0 +1,382 @@
+use std::{
+    fs,
+    io::{self, Stdout, Write, stdout},
+    path::Path,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use crossterm::{
+    cursor::{Hide, MoveTo, Show},
+    event::{
+        self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers, KeyboardEnhancementFlags,
+        PopKeyboardEnhancementFlags, PushKeyboardEnhancementFlags,
+    },
+    execute, queue,
+    style::{Color, Print, ResetColor, SetBackgroundColor, SetForegroundColor},
+    terminal::{self, Clear, ClearType, EnterAlternateScreen, LeaveAlternateScreen},
+};
+use egui::{Key, Modifiers};
+
+use crate::{
+    Message,
+    frame_buffer::{FRAME_BUFFER_COLS, FRAME_BUFFER_ROWS, FrameBuffer},
+    handle::{Chip8Handle, Chip8Source},
+    key_mapping::KeyMapping,
+    key_matrix::{Chip8Key, KeyMatrix},
+    palette::Palette,
+    settings::Settings,
+    shortcuts::{Action, Shortcut, Shortcuts},
+};
+
+pub const USAGE: &str = "usage: chip8 tui <rom.ch8>";
+
+/// How long a key stays down after its last press or auto-repeat, for terminals that
+/// don't report key releases.
+const KEY_HOLD: Duration = Duration::from_millis(200);
+/// How often the status line asks the emulator thread for PC and instruction count.
+const STATUS_INTERVAL: Duration = Duration::from_millis(250);
+/// How long the input loop waits for a key before checking for new frames.
+const POLL_INTERVAL: Duration = Duration::from_millis(5);
+/// Text rows used by the display; each holds two pixel rows.
+const DISPLAY_ROWS: u16 = (FRAME_BUFFER_ROWS / 2) as u16;
+
+/// Entry point for `chip8 tui`; returns the process exit code.
+pub fn main(args: &[String]) -> i32 {
+    let [rom] = args else {
+        eprintln!("{USAGE}");
+        return 2;
+    };
+    if let Err(err) = fs::metadata(rom) {
+        eprintln!("Couldn't read {rom}: {err}");
+        return 2;
+    }
+
+    match Tui::new(rom).and_then(|mut tui| tui.run()) {
+        Ok(()) => 0,
+        Err(err) => {
+            eprintln!("Terminal error: {err}");
+            1
+        }
+    }
+}
+
+/// Raw mode and the alternate screen for as long as it lives, so the terminal is
+/// restored however the frontend exits.
+struct Terminal {
+    out: Stdout,
+    /// Whether the terminal reports key releases (the kitty keyboard protocol).
+    reports_releases: bool,
+}
+
+impl Terminal {
+    fn enter() -> io::Result<Self> {
+        terminal::enable_raw_mode()?;
+
+        let mut out = stdout();
+        execute!(out, EnterAlternateScreen, Hide, Clear(ClearType::All))?;
+
+        let reports_releases = terminal::supports_keyboard_enhancement().unwrap_or(false);
+        if reports_releases {
+            execute!(
+                out,
+                PushKeyboardEnhancementFlags(KeyboardEnhancementFlags::REPORT_EVENT_TYPES)
+            )?;
+        }
+
+        Ok(Self {
+            out,
+            reports_releases,
+        })
+    }
+}
+
+impl Drop for Terminal {
+    fn drop(&mut self) {
+        if self.reports_releases {
+            let _ = execute!(self.out, PopKeyboardEnhancementFlags);
+        }
+        let _ = execute!(self.out, ResetColor, Show, LeaveAlternateScreen);
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+/// Terminal frontend: the same emulator thread as the egui `App`, drawn with half-block
+/// characters, two pixel rows per text row, and played with the saved key profile.
+pub struct Tui {
+    terminal: Terminal,
+    handle: Chip8Handle,
+    key_matrix: Arc<Mutex<KeyMatrix>>,
+    key_mapping: KeyMapping,
+    shortcuts: Shortcuts,
+    palette: Palette,
+
+    frame_buffer: FrameBuffer,
+    /// When each held CHIP-8 key lets go, if the terminal won't tell us.
+    release_at: [Option<Instant>; 16],
+
+    pc: u16,
+    paused: bool,
+    ips: u64,
+    last_status: Instant,
+    /// Instruction count and time of the last IPS sample.
+    ips_sample: Option<(u64, Instant)>,
+}
+
+impl Tui {
+    pub fn new(rom: &str) -> io::Result<Self> {
+        let settings = Settings::load();
+
+        // The profile picked for this ROM in the GUI, as `App` does on load
+        let profile = Path::new(rom)
+            .file_name()
+            .and_then(|name| settings.rom_profiles.get(&*name.to_string_lossy()))
+            .unwrap_or(&settings.active_profile);
+        let key_mapping = settings
+            .profile(profile)
+            .map(KeyMapping::from_profile)
+            .unwrap_or_default();
+
+        let key_matrix = Arc::new(Mutex::new(KeyMatrix::new()));
+        let handle = Chip8Handle::new(key_matrix.clone(), Chip8Source::ROM(rom.to_string()))
+            .map_err(|err| io::Error::new(err.kind(), format!("couldn't load {rom}: {err}")))?;
+
+        Ok(Self {
+            terminal: Terminal::enter()?,
+            handle,
+            key_matrix,
+            key_mapping,
+            shortcuts: settings.shortcuts,
+            palette: settings.palette,
+            frame_buffer: FrameBuffer::new(),
+            release_at: [None; 16],
+            pc: 0,
+            paused: false,
+            ips: 0,
+            last_status: Instant::now(),
+            ips_sample: None,
+        })
+    }
+
+    pub fn run(&mut self) -> io::Result<()> {
+        self.handle.request_snapshot();
+
+        loop {
+            if event::poll(POLL_INTERVAL)? {
+                match event::read()? {
+                    Event::Key(event) if self.key_event(event) => return Ok(()),
+                    Event::Resize(..) => {
+                        queue!(self.terminal.out, Clear(ClearType::All))?;
+                        self.draw_frame()?;
+                        self.draw_status()?;
+                    }
+                    _ => {}
+                }
+            }
+
+            self.release_expired_keys();
+
+            while let Some(message) = self.handle.try_recv() {
+                if let Message::Snapshot(snapshot) = message {
+                    self.pc = snapshot.cpu.pc();
+                    self.paused = snapshot.paused;
+                    self.sample_ips(snapshot.instructions);
+                    self.draw_status()?;
+                }
+            }
+            if let Some(frame_buffer) = self.handle.take_frame() {
+                self.frame_buffer = frame_buffer;
+                self.draw_frame()?;
+            }
+
+            if self.last_status.elapsed() >= STATUS_INTERVAL {
+                self.last_status = Instant::now();
+                self.handle.request_snapshot();
+            }
+
+            self.terminal.out.flush()?;
+        }
+    }
+
+    /// Handles a key; returns whether to quit.
+    fn key_event(&mut self, event: KeyEvent) -> bool {
+        let ctrl = event.modifiers.contains(KeyModifiers::CONTROL);
+        if event.kind == KeyEventKind::Press
+            && (event.code == KeyCode::Esc || ctrl && event.code == KeyCode::Char('c'))
+        {
+            return true;
+        }
+
+        let Some(key) = egui_key(event.code) else {
+            return false;
+        };
+
+        if event.kind == KeyEventKind::Release {
+            if let Some(chip8_key) = self.key_mapping.get_chip8_key(&key) {
+                self.release_key(chip8_key);
+            }
+            return false;
+        }
+
+        if event.kind == KeyEventKind::Press
+            && let Some(action) = self.shortcuts.action(
+                Shortcut::new(key, modifiers(event.modifiers)),
+                &self.key_mapping,
+            )
+        {
+            self.run_action(action);
+            return false;
+        }
+
+        if let Some(chip8_key) = self.key_mapping.get_chip8_key(&key) {
+            self.press_key(chip8_key);
+        }
+
+        false
+    }
+
+    /// Held actions need key releases and the rest need a window, so only these apply.
+    fn run_action(&mut self, action: Action) {
+        match action {
+            Action::PauseResume => self.handle.send(Message::TogglePause),
+            Action::Reset => self.handle.send(Message::Reset),
+            Action::HardReset => self.handle.send(Message::HardReset),
+            Action::Step => self.handle.step(),
+            _ => {}
+        }
+        self.handle.request_snapshot();
+    }
+
+    fn press_key(&mut self, key: Chip8Key) {
+        if !self.terminal.reports_releases {
+            self.release_at[u8::from(key) as usize] = Some(Instant::now() + KEY_HOLD);
+        }
+
+        let mut key_matrix = self.key_matrix.lock().unwrap();
+
+        // Key repeat shouldn't look like fresh presses to FX0A
+        if !key_matrix.is_pressed(key) {
+            key_matrix.press(key);
+            self.handle.send_key_press_message(key);
+        }
+    }
+
+    fn release_key(&mut self, key: Chip8Key) {
+        self.release_at[u8::from(key) as usize] = None;
+        self.key_matrix.lock().unwrap().release(key);
+        self.handle.send_key_release_message(key);
+    }
+
+    fn release_expired_keys(&mut self) {
+        let now = Instant::now();
+
+        for index in 0..self.release_at.len() {
+            if self.release_at[index].is_some_and(|at| at <= now)
+                && let Ok(key) = Chip8Key::try_from(index as u8)
+            {
+                self.release_key(key);
+            }
+        }
+    }
+
+    fn sample_ips(&mut self, instructions: u64) {
+        let now = Instant::now();
+
+        match self.ips_sample {
+            Some((count, at)) if now - at >= Duration::from_secs(1) => {
+                let elapsed = (now - at).as_secs_f64();
+                self.ips = (instructions.saturating_sub(count) as f64 / elapsed).round() as u64;
+                self.ips_sample = Some((instructions, now));
+            }
+            Some(_) => {}
+            None => self.ips_sample = Some((instructions, now)),
+        }
+    }
+
+    fn draw_frame(&mut self) -> io::Result<()> {
+        let [off, on] =
+            [self.palette.off(), self.palette.on()].map(|[r, g, b]| Color::Rgb { r, g, b });
+        let mut colors = None;
+
+        for row in 0..DISPLAY_ROWS {
+            queue!(self.terminal.out, MoveTo(0, row))?;
+
+            for x in 0..FRAME_BUFFER_COLS {
+                let y = row as usize * 2;
+                let top = if self.frame_buffer.get(x, y) { on } else { off };
+                let bottom = if self.frame_buffer.get(x, y + 1) {
+                    on
+                } else {
+                    off
+                };
+
+                if colors != Some((top, bottom)) {
+                    colors = Some((top, bottom));
+                    queue!(
+                        self.terminal.out,
+                        SetForegroundColor(top),
+                        SetBackgroundColor(bottom)
+                    )?;
+                }
+                queue!(self.terminal.out, Print('▀'))?;
+            }
+        }
+
+        queue!(self.terminal.out, ResetColor)
+    }
+
+    fn draw_status(&mut self) -> io::Result<()> {
+        let state = if self.paused { "Paused" } else { "Running" };
+        let pause = self
+            .shortcuts
+            .get(Action::PauseResume)
+            .map(|shortcut| format!("  {shortcut} pause"))
+            .unwrap_or_default();
+
+        queue!(
+            self.terminal.out,
+            MoveTo(0, DISPLAY_ROWS),
+            Clear(ClearType::CurrentLine),
+            Print(format!(
+                "PC {:03X}  IPS {}  {state}  |  Esc quit{pause}",
+                self.pc, self.ips
+            ))
+        )
+    }
+}
+
+fn modifiers(modifiers: KeyModifiers) -> Modifiers {
+    let ctrl = modifiers.contains(KeyModifiers::CONTROL);
+
+    Modifiers {
+        alt: modifiers.contains(KeyModifiers::ALT),
+        ctrl,
+        shift: modifiers.contains(KeyModifiers::SHIFT),
+        mac_cmd: false,
+        command: ctrl,
+    }
+}
+
+/// The egui key a terminal key corresponds to, so key profiles and shortcuts saved by
+/// the GUI work unchanged.
+fn egui_key(code: KeyCode) -> Option<Key> {
+    match code {
+        KeyCode::Char(' ') => Some(Key::Space),
+        KeyCode::Char(c) => Key::from_name(&c.to_ascii_uppercase().to_string()),
+        KeyCode::F(n) => Key::from_name(&format!("F{n}")),
+        KeyCode::Enter => Some(Key::Enter),
+        KeyCode::Tab => Some(Key::Tab),
+        KeyCode::Backspace => Some(Key::Backspace),
+        KeyCode::Up => Some(Key::ArrowUp),
+        KeyCode::Down => Some(Key::ArrowDown),
+        KeyCode::Left => Some(Key::ArrowLeft),
+        KeyCode::Right => Some(Key::ArrowRight),
+        KeyCode::Home => Some(Key::Home),
+        KeyCode::End => Some(Key::End),
+        KeyCode::PageUp => Some(Key::PageUp),
+        KeyCode::PageDown => Some(Key::PageDown),
+        KeyCode::Insert => Some(Key::Insert),
+        KeyCode::Delete => Some(Key::Delete),
+        _ => None,
+    }
+}